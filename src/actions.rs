@@ -0,0 +1,189 @@
+//! Actions modifying a process (killing it, changing its priority, sending it a signal...).
+//!
+//! They all go through `run_process_action` so their failures are reported the same way.
+
+use gtk::glib::IsA;
+use gtk::prelude::{DialogExt, GtkWindowExt, WidgetExt};
+use sysinfo::{Process, ProcessExt};
+
+use std::io;
+
+fn error_reason(error: &io::Error) -> String {
+    #[cfg(unix)]
+    {
+        if error.raw_os_error() == Some(libc::ESRCH) {
+            return "the process doesn't exist anymore.".to_owned();
+        }
+    }
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        "permission denied. You might need to run the process viewer with more privileges."
+            .to_owned()
+    } else {
+        error.to_string()
+    }
+}
+
+fn show_action_error<T: IsA<gtk::Window>>(window: &T, action: &str, name: &str, error: &io::Error) {
+    let m = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Ok,
+        &format!("Failed to {} \"{}\": {}", action, name, error_reason(error)),
+    );
+    m.set_modal(true);
+    m.connect_response(|dialog, _| {
+        dialog.close();
+    });
+    m.show_all();
+}
+
+/// Runs `f` and reports its failure to the user, if any. `action` is used in the error message
+/// ("Failed to `action` `name`: ..."). Returns `true` if it succeeded.
+pub fn run_process_action<T, F>(window: &T, action: &str, name: &str, f: F) -> bool
+where
+    T: IsA<gtk::Window>,
+    F: FnOnce() -> io::Result<()>,
+{
+    match f() {
+        Ok(()) => true,
+        Err(e) => {
+            show_action_error(window, action, name, &e);
+            false
+        }
+    }
+}
+
+/// Asks the user to confirm `question` and calls `on_confirm` if so.
+pub fn confirm_process_action<T, F>(window: &T, question: &str, on_confirm: F)
+where
+    T: IsA<gtk::Window>,
+    F: Fn() + 'static,
+{
+    let m = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::YesNo,
+        question,
+    );
+    m.set_modal(true);
+    m.connect_response(move |dialog, response| {
+        dialog.close();
+        if response == gtk::ResponseType::Yes {
+            on_confirm();
+        }
+    });
+    m.show_all();
+}
+
+#[cfg(not(windows))]
+pub fn kill(process: &Process) -> io::Result<()> {
+    use sysinfo::PidExt;
+
+    if unsafe { libc::kill(process.pid().as_u32() as libc::pid_t, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+pub fn kill(process: &Process) -> io::Result<()> {
+    if process.kill() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "the process couldn't be killed.",
+        ))
+    }
+}
+
+/// Changes the nice value of the process.
+#[cfg(not(windows))]
+pub fn set_priority(pid: sysinfo::Pid, nice: i32) -> io::Result<()> {
+    use sysinfo::PidExt;
+
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid.as_u32() as libc::id_t, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// The Windows priority classes, from the lowest to the highest.
+#[cfg(windows)]
+pub const PRIORITY_CLASSES: &[(&str, u32)] = &[
+    ("idle", winapi::um::winbase::IDLE_PRIORITY_CLASS),
+    (
+        "below normal",
+        winapi::um::winbase::BELOW_NORMAL_PRIORITY_CLASS,
+    ),
+    ("normal", winapi::um::winbase::NORMAL_PRIORITY_CLASS),
+    (
+        "above normal",
+        winapi::um::winbase::ABOVE_NORMAL_PRIORITY_CLASS,
+    ),
+    ("high", winapi::um::winbase::HIGH_PRIORITY_CLASS),
+    ("realtime", winapi::um::winbase::REALTIME_PRIORITY_CLASS),
+];
+
+/// Returns the priority class of the process, `None` if it cannot be retrieved.
+#[cfg(windows)]
+pub fn priority_class(pid: sysinfo::Pid) -> Option<u32> {
+    use sysinfo::PidExt;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetPriorityClass, OpenProcess};
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid.as_u32());
+        if handle.is_null() {
+            return None;
+        }
+        let class = GetPriorityClass(handle);
+        CloseHandle(handle);
+        if class == 0 {
+            None
+        } else {
+            Some(class)
+        }
+    }
+}
+
+/// Changes the priority class of the process, which is what replaces the nice value on Windows.
+#[cfg(windows)]
+pub fn set_priority_class(pid: sysinfo::Pid, class: u32) -> io::Result<()> {
+    use sysinfo::PidExt;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{OpenProcess, SetPriorityClass};
+    use winapi::um::winnt::PROCESS_SET_INFORMATION;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_SET_INFORMATION, 0, pid.as_u32());
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let result = if SetPriorityClass(handle, class) != 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        };
+        CloseHandle(handle);
+        result
+    }
+}
+
+#[cfg(not(windows))]
+pub fn send_signal(process: &Process, signal: sysinfo::Signal) -> io::Result<()> {
+    match process.kill_with(signal) {
+        Some(true) => Ok(()),
+        // `kill_with` uses `libc::kill` so `errno` is still set.
+        Some(false) => Err(io::Error::last_os_error()),
+        None => Err(io::Error::new(
+            io::ErrorKind::Other,
+            "this signal isn't supported on this platform.",
+        )),
+    }
+}
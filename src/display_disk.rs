@@ -13,10 +13,35 @@ use sysinfo::{self, DiskExt, SystemExt};
 struct DiskInfo {
     label: gtk::Label,
     progress: gtk::ProgressBar,
+    io_label: gtk::Label,
+    // Bytes read and written since boot at the previous refresh.
+    io_counters: Option<(u64, u64)>,
     mount_point: String,
     updated: bool,
 }
 
+/// Returns the number of bytes read and written since boot by the given disk.
+#[cfg(target_os = "linux")]
+fn get_disk_io(disk_name: &str) -> Option<(u64, u64)> {
+    let name = disk_name.trim_start_matches("/dev/");
+    let content = std::fs::read_to_string("/proc/diskstats").ok()?;
+    content.lines().find_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 10 || fields[2] != name {
+            return None;
+        }
+        // Values are given in sectors, which are always 512 bytes in this file.
+        let read = fields[5].parse::<u64>().ok()?;
+        let written = fields[9].parse::<u64>().ok()?;
+        Some((read * 512, written * 512))
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_disk_io(_disk_name: &str) -> Option<(u64, u64)> {
+    None
+}
+
 fn update_disk(info: &mut DiskInfo, disk: &sysinfo::Disk) {
     info.label.set_text(
         format!(
@@ -37,6 +62,19 @@ fn update_disk(info: &mut DiskInfo, disk: &sysinfo::Disk) {
     info.progress.set_fraction(
         (disk.total_space() - disk.available_space()) as f64 / disk.total_space() as f64,
     );
+    if let Some((read, written)) = get_disk_io(disk.name().to_str().unwrap_or("")) {
+        let (old_read, old_written) = info.io_counters.unwrap_or((read, written));
+        // Counters can be reset (when a device is reconnected for example) so we don't want
+        // the difference to go negative.
+        info.io_label.set_text(&format!(
+            "read since boot: {} (+{}), written since boot: {} (+{})",
+            format_number(read),
+            format_number(read.saturating_sub(old_read)),
+            format_number(written),
+            format_number(written.saturating_sub(old_written)),
+        ));
+        info.io_counters = Some((read, written));
+    }
     info.updated = true;
 }
 
@@ -53,12 +91,16 @@ fn refresh_disks(container: &gtk::Box, disks: &[sysinfo::Disk], elems: &mut Vec<
 
                 let progress = gtk::ProgressBar::new();
                 progress.set_show_text(true);
+                let io_label = gtk::Label::new(None);
 
                 container.add(&label);
                 container.add(&progress);
+                container.add(&io_label);
                 elems.push(DiskInfo {
                     label,
                     progress,
+                    io_label,
+                    io_counters: None,
                     mount_point: mount_point.to_owned(),
                     updated: false,
                 });
@@ -70,6 +112,7 @@ fn refresh_disks(container: &gtk::Box, disks: &[sysinfo::Disk], elems: &mut Vec<
     for entry in elems.iter().filter(|e| !e.updated) {
         container.remove(&entry.label);
         container.remove(&entry.progress);
+        container.remove(&entry.io_label);
     }
     elems.retain(|e| e.updated);
     for entry in elems.iter_mut() {
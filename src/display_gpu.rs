@@ -0,0 +1,182 @@
+//! GPU usage tab. The information is retrieved with `nvidia-smi`, so only NVIDIA GPUs are
+//! supported and the tab isn't created if none is found.
+
+use gtk::glib;
+use gtk::prelude::{BoxExt, ContainerExt, ProgressBarExt, WidgetExt};
+
+use std::cell::RefCell;
+use std::process::Command;
+use std::rc::Rc;
+
+use crate::display_sysinfo::create_progress_bar;
+use crate::graph::{new_history, Graph};
+use crate::notebook::NoteBook;
+use crate::utils::{connect_graph, format_number, graph_label_percent, graph_label_units};
+
+#[derive(Debug, Clone)]
+pub struct GpuInfo {
+    pub name: String,
+    // In percent.
+    pub utilization: f32,
+    // In bytes.
+    pub memory_used: u64,
+    pub memory_total: u64,
+}
+
+/// Returns the NVIDIA GPUs and their current usage. It's empty if `nvidia-smi` isn't available.
+pub fn query_gpus() -> Vec<GpuInfo> {
+    let output = match Command::new("nvidia-smi")
+        .args(&[
+            "--query-gpu=name,utilization.gpu,memory.used,memory.total",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split(',').map(|f| f.trim()).collect::<Vec<_>>();
+            if fields.len() != 4 {
+                return None;
+            }
+            // The memory is in MiB. Any of the values can be "[N/A]" on some models, it's
+            // displayed as 0 rather than hiding the GPU.
+            Some(GpuInfo {
+                name: fields[0].to_owned(),
+                utilization: fields[1].parse().unwrap_or(0.),
+                memory_used: fields[2].parse::<u64>().unwrap_or(0) * 1_024 * 1_024,
+                memory_total: fields[3].parse::<u64>().unwrap_or(0) * 1_024 * 1_024,
+            })
+        })
+        .collect()
+}
+
+pub struct DisplayGpu {
+    load_bars: Vec<gtk::ProgressBar>,
+    memory_bars: Vec<gtk::ProgressBar>,
+    load_history: Rc<RefCell<Graph>>,
+    memory_history: Rc<RefCell<Graph>>,
+}
+
+impl DisplayGpu {
+    pub fn new(gpus: &[GpuInfo], note: &mut NoteBook) -> DisplayGpu {
+        let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+
+        let mut load_history = Graph::new(None, false);
+        load_history.set_label_callbacks(Some(Box::new(graph_label_percent)));
+        // The total memory isn't known for all models.
+        let max_memory = gpus
+            .iter()
+            .map(|gpu| gpu.memory_total)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let mut memory_history = Graph::new(Some(max_memory as f64), true);
+        memory_history.set_label_callbacks(Some(Box::new(graph_label_units)));
+        memory_history.set_labels_width(70);
+
+        //
+        // LOAD PART
+        //
+        vertical_layout.pack_start(&gtk::Label::new(Some("GPU usage")), false, false, 15);
+        let load_layout = gtk::Grid::new();
+        load_layout.set_column_homogeneous(true);
+        load_layout.set_margin_start(5);
+        load_layout.set_margin_end(5);
+        let load_bars = gpus
+            .iter()
+            .enumerate()
+            .map(|(line, gpu)| {
+                load_history.push(new_history(), &gpu_name(line, gpu), None);
+                create_progress_bar(&load_layout, line as i32, &gpu_name(line, gpu), "")
+            })
+            .collect();
+        vertical_layout.add(&load_layout);
+        load_history.attach_to(&vertical_layout);
+
+        //
+        // MEMORY PART
+        //
+        vertical_layout.pack_start(&gtk::Label::new(Some("GPU memory usage")), false, false, 15);
+        let memory_layout = gtk::Grid::new();
+        memory_layout.set_column_homogeneous(true);
+        memory_layout.set_margin_start(5);
+        memory_layout.set_margin_end(5);
+        let memory_bars = gpus
+            .iter()
+            .enumerate()
+            .map(|(line, gpu)| {
+                memory_history.push(new_history(), &gpu_name(line, gpu), None);
+                create_progress_bar(&memory_layout, line as i32, &gpu_name(line, gpu), "")
+            })
+            .collect();
+        vertical_layout.add(&memory_layout);
+        memory_history.attach_to(&vertical_layout);
+
+        load_history.area.set_size_request(-1, 200);
+        memory_history.area.set_size_request(-1, 200);
+        let load_history = connect_graph(load_history);
+        let memory_history = connect_graph(memory_history);
+
+        scroll.add(&vertical_layout);
+        note.create_tab("GPU usage", &scroll);
+
+        // The graphs hide some of their parts (like the legend), which `show_all` would display.
+        scroll.connect_show(
+            glib::clone!(@weak load_history, @weak memory_history => move |_| {
+                load_history.borrow().show_all();
+                memory_history.borrow().show_all();
+            }),
+        );
+
+        let tmp = DisplayGpu {
+            load_bars,
+            memory_bars,
+            load_history,
+            memory_history,
+        };
+        tmp.update(gpus);
+        tmp
+    }
+
+    pub fn update(&self, gpus: &[GpuInfo]) {
+        let mut load_history = self.load_history.borrow_mut();
+        let mut memory_history = self.memory_history.borrow_mut();
+        // A GPU can disappear (or be added), only the ones known at startup are displayed.
+        for (pos, gpu) in gpus.iter().enumerate().take(self.load_bars.len()) {
+            let load = &self.load_bars[pos];
+            load.set_text(Some(&format!("{:.1} %", gpu.utilization)));
+            load.set_fraction(f64::from(gpu.utilization) / 100.);
+            load_history.data[pos].move_start();
+            if let Some(p) = load_history.data[pos].get_mut(0) {
+                *p = f64::from(gpu.utilization) / 100.;
+            }
+
+            let memory = &self.memory_bars[pos];
+            memory.set_text(Some(&format!(
+                "{} / {}",
+                format_number(gpu.memory_used),
+                format_number(gpu.memory_total)
+            )));
+            if gpu.memory_total != 0 {
+                memory.set_fraction(gpu.memory_used as f64 / gpu.memory_total as f64);
+            } else {
+                memory.set_fraction(0.);
+            }
+            memory_history.data[pos].move_start();
+            if let Some(p) = memory_history.data[pos].get_mut(0) {
+                *p = gpu.memory_used as f64;
+            }
+        }
+        load_history.invalidate();
+        memory_history.invalidate();
+    }
+}
+
+fn gpu_name(index: usize, gpu: &GpuInfo) -> String {
+    format!("{}: {}", index, gpu.name)
+}
@@ -46,6 +46,8 @@ fn append_column(
 }
 
 pub struct Network {
+    // The notebook page.
+    pub page: gtk::Box,
     list_store: gtk::ListStore,
     pub filter_entry: gtk::Entry,
     pub search_bar: gtk::SearchBar,
@@ -217,6 +219,7 @@ impl Network {
         );
 
         Network {
+            page: vertical_layout,
             list_store,
             filter_entry,
             search_bar,
@@ -1,79 +1,572 @@
-use gtk::glib::object::Cast;
+use gtk::glib::object::{Cast, IsA};
 use gtk::glib::Type;
 use gtk::prelude::{
-    BoxExt, ButtonExt, CellRendererExt, ContainerExt, EntryExt, GridExt, GtkListStoreExtManual,
-    GtkWindowExt, OverlayExt, SearchBarExt, TreeModelExt, TreeModelFilterExt, TreeSelectionExt,
-    TreeViewColumnExt, TreeViewExt, WidgetExt,
+    BoxExt, ButtonExt, CellLayoutExt, CellRendererExt, CellRendererTextExt, CheckMenuItemExt,
+    ContainerExt, EditableSignals, EntryExt, GridExt, GtkMenuExt, GtkMenuItemExt, GtkWindowExt,
+    Inhibit, MenuShellExt, ObjectExt, OverlayExt, SearchBarExt, StyleContextExt, ToggleButtonExt,
+    TreeModelExt, TreeModelFilterExt, TreeModelSortExt, TreeSelectionExt, TreeSortableExtManual,
+    TreeStoreExtManual, TreeViewColumnExt, TreeViewExt, WidgetExt, WidgetExtManual,
 };
-use gtk::{self, glib};
+use gtk::{self, gdk, glib};
+use regex::{Regex, RegexBuilder};
 
-use sysinfo::{Pid, PidExt, Process, ProcessExt};
+use sysinfo::{Pid, PidExt, Process, ProcessExt, Signal, User};
 
+use crate::graph::Graph;
 use crate::notebook::NoteBook;
-use crate::utils::{create_button_with_image, format_number};
+use crate::settings::Settings;
+use crate::utils::{
+    connect_graph, create_button_with_image, format_number, format_time, format_timestamp,
+    kib_to_bytes, RotateVec,
+};
 
-use std::cell::Cell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::path::Path;
 use std::rc::Rc;
+use std::time::Duration;
+
+// Number of samples kept for each process in the CPU usage preview.
+const PREVIEW_HISTORY_LEN: usize = 31;
+// Thresholds used by the "High CPU" and "High Mem" quick filters. The latter keeps the processes
+// whose memory usage is displayed with a severity color.
+const HIGH_CPU_THRESHOLD: f32 = 10.;
+const HIGH_MEMORY_THRESHOLD: u64 = MEMORY_MEDIUM_SEVERITY;
+// Changes bigger than these between two refreshes get the CPU/memory cell highlighted.
+const CPU_CHANGE_THRESHOLD: f32 = 10.;
+const MEMORY_CHANGE_RATIO_THRESHOLD: f64 = 0.1;
+const CHANGE_HIGHLIGHT_COLOR: &str = "rgba(255, 193, 7, 0.35)";
+// From these values, the CPU/memory usage is displayed in the medium or high severity color.
+const CPU_MEDIUM_SEVERITY: f32 = 25.;
+const CPU_HIGH_SEVERITY: f32 = 75.;
+const MEMORY_MEDIUM_SEVERITY: u64 = 500_000_000;
+const MEMORY_HIGH_SEVERITY: u64 = 2_000_000_000;
+const MEDIUM_SEVERITY_COLOR: &str = "#c4a000";
+const HIGH_SEVERITY_COLOR: &str = "#cc0000";
+// Background of the rows of the processes which appeared or ended since the last refresh. The
+// highlight fades out in `CHANGE_FADE_STEPS` steps of `CHANGE_FADE_STEP` each.
+const APPEARED_COLOR: (u8, u8, u8) = (78, 154, 6);
+const ENDED_COLOR: (u8, u8, u8) = (204, 0, 0);
+const CHANGE_FADE_STEPS: u32 = 10;
+const CHANGE_FADE_STEP: Duration = Duration::from_millis(150);
+// Columns of the model, filled by `create_and_fill_model` (and by the replay of exported process
+// lists). The "sort" ones contain the values used to sort the displayed texts.
+pub const PID_COLUMN: i32 = 0;
+pub const NAME_COLUMN: i32 = 1;
+pub const CPU_COLUMN: i32 = 2;
+pub const MEMORY_COLUMN: i32 = 3;
+pub const DISK_READ_COLUMN: i32 = 4;
+pub const NAME_SORT_COLUMN: i32 = 5;
+pub const CPU_SORT_COLUMN: i32 = 6;
+pub const MEMORY_SORT_COLUMN: i32 = 7;
+pub const DISK_READ_SORT_COLUMN: i32 = 8;
+pub const UID_COLUMN: i32 = 9;
+pub const GRAPHICAL_SESSION_COLUMN: i32 = 10;
+pub const LEADER_COLUMN: i32 = 11;
+pub const LEAK_COLUMN: i32 = 12;
+// The name without the annotation.
+pub const RAW_NAME_COLUMN: i32 = 13;
+pub const NICE_COLUMN: i32 = 14;
+pub const CPU_HIGHLIGHT_COLUMN: i32 = 15;
+pub const MEMORY_HIGHLIGHT_COLUMN: i32 = 16;
+pub const DISK_WRITE_COLUMN: i32 = 17;
+pub const DISK_WRITE_SORT_COLUMN: i32 = 18;
+pub const PARENT_PID_COLUMN: i32 = 19;
+pub const USER_COLUMN: i32 = 20;
+pub const CPU_COLOR_COLUMN: i32 = 21;
+pub const MEMORY_COLOR_COLUMN: i32 = 22;
+pub const MEMORY_PERCENT_COLUMN: i32 = 23;
+pub const MEMORY_PERCENT_SORT_COLUMN: i32 = 24;
+pub const START_TIME_COLUMN: i32 = 25;
+pub const START_TIME_SORT_COLUMN: i32 = 26;
+pub const THREADS_COLUMN: i32 = 27;
+// The background of the rows, used to highlight the processes which appeared or ended.
+pub const ROW_BACKGROUND_COLUMN: i32 = 28;
+pub const PINNED_COLUMN: i32 = 29;
+pub const RUN_TIME_COLUMN: i32 = 30;
+pub const RUN_TIME_SORT_COLUMN: i32 = 31;
+// Set on the rows gathering the processes of an executable in grouped layout.
+pub const GROUP_COLUMN: i32 = 32;
+pub const KERNEL_THREAD_COLUMN: i32 = 33;
+const PINNED_ICON: &str = "starred-symbolic";
+// Signals which can be sent from the context menu of the process list and from the process
+// dialog.
+#[cfg(not(windows))]
+pub const SIGNALS: &[(&str, Signal)] = &[
+    ("SIGTERM (terminate)", Signal::Term),
+    ("SIGKILL (kill)", Signal::Kill),
+    ("SIGHUP (hang up)", Signal::Hangup),
+    ("SIGINT (interrupt)", Signal::Interrupt),
+    ("SIGQUIT (quit)", Signal::Quit),
+    ("SIGSTOP (pause)", Signal::Stop),
+    ("SIGCONT (resume)", Signal::Continue),
+    ("SIGUSR1", Signal::User1),
+    ("SIGUSR2", Signal::User2),
+];
+
+/// Keeps a short CPU usage history for every process and displays the one of the currently
+/// selected process.
+pub struct CpuPreview {
+    histories: HashMap<Pid, RotateVec<f64>>,
+    graph: Rc<RefCell<Graph>>,
+}
+
+impl CpuPreview {
+    fn new(graph: Rc<RefCell<Graph>>) -> CpuPreview {
+        CpuPreview {
+            histories: HashMap::new(),
+            graph,
+        }
+    }
+
+    pub fn update(&mut self, processes: &HashMap<Pid, Process>, current_pid: Option<Pid>) {
+        self.histories.retain(|pid, _| processes.contains_key(pid));
+        for (pid, process) in processes {
+            let history = self.histories.entry(*pid).or_insert_with(|| {
+                RotateVec::new(iter::repeat(0f64).take(PREVIEW_HISTORY_LEN).collect())
+            });
+            history.move_start();
+            if let Some(value) = history.get_mut(0) {
+                *value = process.cpu_usage().into();
+            }
+        }
+        self.bind(current_pid);
+    }
+
+    /// Makes the preview graph display the history of the given process.
+    pub fn bind(&self, pid: Option<Pid>) {
+        let mut graph = self.graph.borrow_mut();
+        graph.data[0] = match pid.and_then(|pid| self.histories.get(&pid)) {
+            Some(history) => history.clone(),
+            None => RotateVec::new(iter::repeat(0f64).take(PREVIEW_HISTORY_LEN).collect()),
+        };
+        graph.invalidate();
+    }
+}
+
+/// Flags the processes whose memory usage increased at each of the last `samples` refreshes.
+pub struct LeakDetector {
+    // Last memory usage and number of consecutive increases for each process.
+    processes: HashMap<Pid, (u64, u32)>,
+    samples: u32,
+}
+
+impl LeakDetector {
+    fn new(samples: u32) -> LeakDetector {
+        LeakDetector {
+            processes: HashMap::new(),
+            samples,
+        }
+    }
+
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples;
+    }
+
+    pub fn update(&mut self, processes: &HashMap<Pid, Process>) {
+        self.processes.retain(|pid, _| processes.contains_key(pid));
+        for (pid, process) in processes {
+            let memory = process.memory();
+            let entry = self.processes.entry(*pid).or_insert((memory, 0));
+            if memory > entry.0 {
+                entry.1 += 1;
+            } else {
+                entry.1 = 0;
+            }
+            entry.0 = memory;
+        }
+    }
+
+    pub fn is_suspected(&self, pid: Pid) -> bool {
+        self.processes
+            .get(&pid)
+            .map(|(_, increases)| *increases >= self.samples)
+            .unwrap_or(false)
+    }
+}
+
+/// Highlights the rows of the processes which appeared or ended since the last refresh. The
+/// highlight fades out and the rows of the ended processes are removed once it's done.
+pub struct ProcessChanges {
+    // The processes found at the last refresh.
+    previous: HashSet<Pid>,
+    // Whether the highlighted processes ended and the number of remaining fade steps.
+    highlighted: HashMap<Pid, (bool, u32)>,
+    enabled: bool,
+    // Whether the fade out timeout is running.
+    fading: bool,
+}
+
+impl ProcessChanges {
+    fn new(processes: &HashMap<Pid, Process>, enabled: bool) -> ProcessChanges {
+        ProcessChanges {
+            previous: processes.keys().copied().collect(),
+            highlighted: HashMap::new(),
+            enabled,
+            fading: false,
+        }
+    }
+
+    /// The current highlights are removed at the next step of the fade out.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            for (_, steps) in self.highlighted.values_mut() {
+                *steps = 0;
+            }
+        }
+    }
+
+    /// Compares `processes` with the ones of the last refresh.
+    pub fn update(&mut self, processes: &HashMap<Pid, Process>) {
+        let current = processes.keys().copied().collect::<HashSet<_>>();
+        if self.enabled {
+            for pid in current.difference(&self.previous) {
+                self.highlighted.insert(*pid, (false, CHANGE_FADE_STEPS));
+            }
+            for pid in self.previous.difference(&current) {
+                self.highlighted.insert(*pid, (true, CHANGE_FADE_STEPS));
+            }
+        }
+        // The pid of an ended process can be reused by a new one, and the other way around.
+        self.highlighted
+            .retain(|pid, (ended, _)| *ended != current.contains(pid));
+        self.previous = current;
+    }
+
+    /// Returns `true` if the process ended but its row is kept until its highlight faded out.
+    pub fn is_ending(&self, pid: Pid) -> bool {
+        matches!(self.highlighted.get(&pid), Some((true, steps)) if *steps > 0)
+    }
+
+    /// Updates the background of the highlighted rows, removing the ones of the ended processes
+    /// once their highlight faded out.
+    fn apply(&mut self, list_store: &gtk::TreeStore) {
+        if self.highlighted.is_empty() {
+            return;
+        }
+        let mut rows = Vec::new();
+        list_store.foreach(|model, _, iter| {
+            if let Ok(pid) = model.value(iter, 0).get::<u32>() {
+                if let Some(highlight) = self.highlighted.get(&Pid::from_u32(pid)) {
+                    rows.push((iter.clone(), *highlight));
+                }
+            }
+            false
+        });
+        // The rows of the ended processes don't have children, removing them doesn't invalidate
+        // the other iterators.
+        for (iter, (ended, steps)) in rows {
+            if ended && steps == 0 {
+                list_store.remove(&iter);
+                continue;
+            }
+            let (r, g, b) = if ended { ENDED_COLOR } else { APPEARED_COLOR };
+            let background = if steps == 0 {
+                None
+            } else {
+                let alpha = 0.5 * f64::from(steps) / f64::from(CHANGE_FADE_STEPS);
+                Some(format!("rgba({}, {}, {}, {:.2})", r, g, b, alpha))
+            };
+            // The CPU and memory cells have their own background.
+            list_store.set(
+                &iter,
+                &[
+                    (15, &background),
+                    (16, &background),
+                    (ROW_BACKGROUND_COLUMN as u32, &background),
+                ],
+            );
+        }
+        self.highlighted.retain(|_, (_, steps)| *steps > 0);
+    }
+}
+
+/// Displays the changes found by the last `ProcessChanges::update` call and fades them out.
+pub fn highlight_process_changes(
+    changes: &Rc<RefCell<ProcessChanges>>,
+    list_store: &gtk::TreeStore,
+) {
+    let mut changes_ref = changes.borrow_mut();
+    changes_ref.apply(list_store);
+    if changes_ref.fading || changes_ref.highlighted.is_empty() {
+        return;
+    }
+    changes_ref.fading = true;
+    glib::timeout_add_local(
+        CHANGE_FADE_STEP,
+        glib::clone!(@weak changes, @weak list_store => @default-return glib::Continue(false), move || {
+            let mut changes = changes.borrow_mut();
+            for (_, steps) in changes.highlighted.values_mut() {
+                *steps = steps.saturating_sub(1);
+            }
+            changes.apply(&list_store);
+            changes.fading = !changes.highlighted.is_empty();
+            glib::Continue(changes.fading)
+        }),
+    );
+}
+
+/// The processes pinned by the user. They're marked with a star and can be kept at the top of the
+/// list, whatever the sort order.
+#[derive(Clone)]
+pub struct PinnedProcesses {
+    pub pids: Rc<RefCell<HashSet<Pid>>>,
+    on_top: Rc<Cell<bool>>,
+}
+
+impl PinnedProcesses {
+    fn new(on_top: bool) -> PinnedProcesses {
+        PinnedProcesses {
+            pids: Rc::new(RefCell::new(HashSet::new())),
+            on_top: Rc::new(Cell::new(on_top)),
+        }
+    }
+
+    pub fn contains(&self, pid: Pid) -> bool {
+        self.pids.borrow().contains(&pid)
+    }
+
+    /// Returns `Ordering::Less` if only `a` is pinned (and the pinned processes are kept on top),
+    /// `Ordering::Greater` if only `b` is.
+    fn compare(&self, a: Pid, b: Pid) -> Ordering {
+        if !self.on_top.get() {
+            return Ordering::Equal;
+        }
+        let pids = self.pids.borrow();
+        pids.contains(&b).cmp(&pids.contains(&a))
+    }
+
+    /// Updates the star of the rows of the given processes, which sorts them again as well.
+    fn update_rows(&self, list_store: &gtk::TreeStore, pids: &[Pid]) {
+        let mut rows = Vec::new();
+        list_store.foreach(|model, _, iter| {
+            if let Ok(pid) = model.value(iter, 0).get::<u32>() {
+                if pids.contains(&Pid::from_u32(pid)) {
+                    rows.push((iter.clone(), Pid::from_u32(pid)));
+                }
+            }
+            false
+        });
+        for (iter, pid) in rows {
+            let icon = if self.contains(pid) {
+                Some(PINNED_ICON)
+            } else {
+                None
+            };
+            list_store.set(&iter, &[(29, &icon)]);
+        }
+    }
+}
+
+/// How the processes are laid out in the list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessLayout {
+    // All the processes are at the root.
+    Flat,
+    // The processes are under their parent.
+    Tree,
+    // The processes are under a row per executable name, which sums their usage.
+    Grouped,
+}
 
 #[allow(dead_code)]
 pub struct Procs {
     pub left_tree: gtk::TreeView,
     pub scroll: gtk::ScrolledWindow,
-    pub current_pid: Rc<Cell<Option<Pid>>>,
+    // The processes selected in the list, in the list order.
+    pub selected_pids: Rc<RefCell<Vec<Pid>>>,
     pub kill_button: gtk::Button,
     pub info_button: gtk::Button,
     pub vertical_layout: gtk::Box,
-    pub list_store: gtk::ListStore,
+    // Processes are nested under their parent in tree layout, under their executable in grouped
+    // layout, otherwise they're all at the root.
+    pub list_store: gtk::TreeStore,
+    // Hides the processes which don't match the filter entry, the quick filters and the kernel
+    // threads (if enabled in the settings).
+    pub filter_model: gtk::TreeModelFilter,
+    pub tree_button: gtk::ToggleButton,
+    pub group_button: gtk::ToggleButton,
+    pub layout: Rc<Cell<ProcessLayout>>,
     pub columns: Vec<gtk::TreeViewColumn>,
     pub filter_entry: gtk::Entry,
     pub search_bar: gtk::SearchBar,
     pub filter_button: gtk::Button,
+    pub export_button: gtk::Button,
+    pub refresh_button: gtk::Button,
+    pub cpu_preview: Rc<RefCell<CpuPreview>>,
+    pub leak_detector: Rc<RefCell<LeakDetector>>,
+    pub process_changes: Rc<RefCell<ProcessChanges>>,
+    pub pinned: PinnedProcesses,
+    // User annotations of the processes, displayed next to their name.
+    pub annotations: Rc<RefCell<HashMap<Pid, String>>>,
+    // Entries of the process list context menu.
+    pub end_task_item: gtk::MenuItem,
+    // Always empty on Windows where only ending the process is meaningful.
+    pub signal_items: Vec<(gtk::MenuItem, Signal)>,
+    // Number of listed processes (and threads), and how many of them pass the filter.
+    pub count_label: gtk::Label,
+    // Set while process lists exported to CSV are displayed instead of the live processes.
+    pub replaying: Rc<Cell<bool>>,
 }
 
 impl Procs {
     pub fn new(
         proc_list: &HashMap<Pid, Process>,
+        users: &[User],
+        total_memory: u64,
         note: &mut NoteBook,
         window: &gtk::ApplicationWindow,
+        settings: &Rc<RefCell<Settings>>,
     ) -> Procs {
         let left_tree = gtk::TreeView::new();
         let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
-        let current_pid = Rc::new(Cell::new(None));
+        let selected_pids = Rc::new(RefCell::new(Vec::new()));
+        let replaying = Rc::new(Cell::new(false));
         let kill_button = gtk::Button::with_label("End task");
         let info_button = gtk::Button::with_label("More information");
+        info_button.set_tooltip_text(Some(
+            "Display more information about the selected processes (or double-click on a process)",
+        ));
+        let export_button = gtk::Button::with_label("Export");
+        export_button.set_tooltip_text(Some("Export the displayed processes to a CSV file"));
+        let refresh_button =
+            gtk::Button::from_icon_name(Some("view-refresh"), gtk::IconSize::Button);
+        refresh_button.set_tooltip_text(Some("Refresh now, even when paused"));
 
         let filter_button =
             create_button_with_image(include_bytes!("../assets/magnifier.png"), "Filter");
+        let tree_button = gtk::ToggleButton::with_label("Tree");
+        tree_button.set_tooltip_text(Some("Display the processes under their parent"));
+        let group_button = gtk::ToggleButton::with_label("Group");
+        group_button.set_tooltip_text(Some("Group the processes by executable name"));
+        let layout = Rc::new(Cell::new(ProcessLayout::Flat));
+        // The tree and the grouped layouts are exclusive. The new layout is set before the other
+        // button is released so the list is only rebuilt once.
+        tree_button.connect_toggled(
+            glib::clone!(@weak layout, @weak left_tree, @weak group_button => move |tree_button| {
+                if tree_button.is_active() {
+                    layout.set(ProcessLayout::Tree);
+                    group_button.set_active(false);
+                } else if layout.get() == ProcessLayout::Tree {
+                    layout.set(ProcessLayout::Flat);
+                }
+                left_tree.set_show_expanders(layout.get() != ProcessLayout::Flat);
+            }),
+        );
+        group_button.connect_toggled(
+            glib::clone!(@weak layout, @weak left_tree, @weak tree_button => move |group_button| {
+                if group_button.is_active() {
+                    layout.set(ProcessLayout::Grouped);
+                    tree_button.set_active(false);
+                } else if layout.get() == ProcessLayout::Grouped {
+                    layout.set(ProcessLayout::Flat);
+                }
+                left_tree.set_show_expanders(layout.get() != ProcessLayout::Flat);
+            }),
+        );
+        left_tree.set_show_expanders(false);
 
-        // TODO: maybe add an 'X' button to close search as well?
         let overlay = gtk::Overlay::new();
         let filter_entry = gtk::Entry::new();
+        filter_entry.set_tooltip_text(Some(
+            "Filters by pid or name. \"user:\", \"pid:\" and \"name:\" restrict the match to \
+             one field, for example \"user:root ssh\"",
+        ));
         let search_bar = gtk::SearchBar::new();
 
+        let regex_button = gtk::ToggleButton::with_label(".*");
+        regex_button.set_tooltip_text(Some("Use a regular expression"));
+        let case_button = gtk::ToggleButton::with_label("Aa");
+        case_button.set_tooltip_text(Some("Case sensitive"));
+        let close_filter_button =
+            gtk::Button::from_icon_name(Some("window-close-symbolic"), gtk::IconSize::Button);
+        close_filter_button.set_tooltip_text(Some("Close the filter"));
+        let filter_layout = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        filter_layout.style_context().add_class("linked");
+        filter_layout.pack_start(&filter_entry, false, false, 0);
+        filter_layout.pack_start(&regex_button, false, false, 0);
+        filter_layout.pack_start(&case_button, false, false, 0);
+        filter_layout.pack_start(&close_filter_button, false, false, 0);
+        // The filter options are only displayed alongside the filter entry.
+        for button in [
+            regex_button.upcast_ref::<gtk::Widget>(),
+            case_button.upcast_ref(),
+            close_filter_button.upcast_ref(),
+        ] {
+            filter_entry
+                .bind_property("visible", button, "visible")
+                .flags(glib::BindingFlags::SYNC_CREATE)
+                .build();
+        }
+
         // We put the filter entry at the right bottom.
-        filter_entry.set_halign(gtk::Align::End);
-        filter_entry.set_valign(gtk::Align::End);
+        filter_layout.set_halign(gtk::Align::End);
+        filter_layout.set_valign(gtk::Align::End);
         filter_entry.hide(); // By default, we don't show it.
         search_bar.connect_entry(&filter_entry);
         search_bar.set_show_close_button(true);
+        close_filter_button.connect_clicked(
+            glib::clone!(@weak filter_entry, @weak search_bar => move |_| {
+                hide_filter(&filter_entry, &search_bar);
+            }),
+        );
+        // The search bar leaves the search mode when its own close button is clicked.
+        search_bar.connect_search_mode_enabled_notify(
+            glib::clone!(@weak filter_entry => move |search_bar| {
+                if !search_bar.is_search_mode() && WidgetExt::is_visible(&filter_entry) {
+                    hide_filter(&filter_entry, search_bar);
+                }
+            }),
+        );
 
-        overlay.add_overlay(&filter_entry);
+        overlay.add_overlay(&filter_layout);
 
         let mut columns: Vec<gtk::TreeViewColumn> = Vec::new();
 
-        let list_store = gtk::ListStore::new(&[
-            // The first four columns of the model are going to be visible in the view.
+        let list_store = gtk::TreeStore::new(&[
+            // The first five columns of the model are displayed as is in the view.
             Type::U32,    // pid
             Type::STRING, // name
             Type::STRING, // CPU
             Type::STRING, // mem
-            Type::STRING, // disk I/O
-            // These two will serve as keys when sorting by process name and CPU usage.
+            Type::STRING, // disk read
+            // These ones serve as keys when sorting by process name, CPU, memory and disk read,
+            // since the displayed texts wouldn't be sorted numerically.
             Type::STRING, // name_lowercase
             Type::F32,    // CPU_f32
             Type::U64,    // mem
-            Type::U64,    // disk I/O
+            Type::U64,    // disk read
+            Type::U32,    // uid (used by the quick filters)
+            Type::BOOL,   // is in the graphical session (used by the quick filters)
+            Type::STRING, // session/group leader
+            Type::STRING, // memory leak icon
+            // The process name without the user annotation.
+            Type::STRING, // raw name
+            Type::I32,    // nice value
+            // Background of the CPU and memory cells when they changed significantly.
+            Type::STRING, // CPU highlight
+            Type::STRING, // mem highlight
+            Type::STRING, // disk write
+            Type::U64,    // disk write
+            Type::U32,    // parent pid (0 if none)
+            Type::STRING, // user
+            // Text colors of the CPU and memory cells depending on the usage.
+            Type::STRING, // CPU severity color
+            Type::STRING, // mem severity color
+            Type::STRING, // mem %
+            Type::F32,    // mem %
+            Type::STRING, // start time
+            Type::U64,    // start time (unix timestamp)
+            Type::U32,    // number of threads
+            Type::STRING, // row background (processes which appeared or ended)
+            Type::STRING, // pinned icon
+            Type::STRING, // running for
+            Type::U64,    // running for (in seconds)
+            Type::BOOL,   // is a group of processes
+            Type::BOOL,   // is a kernel thread
         ]);
 
         for pro in proc_list.values() {
@@ -85,11 +578,13 @@ impl Procs {
             {
                 create_and_fill_model(
                     &list_store,
-                    pro.pid().as_u32(),
-                    pro.cmd(),
+                    None,
+                    pro,
                     exe,
-                    pro.cpu_usage(),
-                    pro.memory() * 1_000,
+                    None,
+                    false,
+                    users,
+                    total_memory,
                 );
             }
         }
@@ -97,30 +592,166 @@ impl Procs {
         left_tree.set_headers_visible(true);
         scroll.add(&left_tree);
         overlay.add(&scroll);
+
+        // The CPU usage preview of the selected process.
+        let preview_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        preview_layout.set_margin_start(5);
+        preview_layout.set_margin_end(5);
+        let mut preview = Graph::new(Some(100.), false);
+        preview.set_display_labels(false);
+        preview.set_minimum(Some(100.));
+        preview.push(
+            RotateVec::new(iter::repeat(0f64).take(PREVIEW_HISTORY_LEN).collect()),
+            "",
+            None,
+        );
+        preview.area.set_size_request(200, 120);
+        preview_layout.pack_start(&gtk::Label::new(Some("CPU usage")), false, false, 5);
+        preview.attach_to(&preview_layout);
+        let preview = connect_graph(preview);
+        preview_layout.connect_show(glib::clone!(@weak preview => move |_| {
+            preview.borrow().show_all();
+        }));
+        let cpu_preview = Rc::new(RefCell::new(CpuPreview::new(preview)));
+        let leak_detector = Rc::new(RefCell::new(LeakDetector::new(
+            settings.borrow().leak_detection_samples,
+        )));
+        let process_changes = Rc::new(RefCell::new(ProcessChanges::new(
+            proc_list,
+            settings.borrow().highlight_process_changes,
+        )));
+
+        // The quick filters.
+        let chips_layout = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        let high_cpu_chip = gtk::ToggleButton::with_label("High CPU");
+        let high_mem_chip = gtk::ToggleButton::with_label("High Mem");
+        let my_user_chip = gtk::ToggleButton::with_label("My user");
+        // Most graphical applications have access to the display, but so do the processes
+        // started from a terminal of the graphical session.
+        let graphical_session_chip = gtk::ToggleButton::with_label("Graphical session");
+        graphical_session_chip.set_tooltip_text(Some(
+            "Processes with access to the display (DISPLAY or WAYLAND_DISPLAY is set)",
+        ));
+        chips_layout.set_margin_top(5);
+        chips_layout.set_margin_bottom(5);
+        chips_layout.set_margin_start(5);
+        chips_layout.pack_start(&high_cpu_chip, false, false, 0);
+        chips_layout.pack_start(&high_mem_chip, false, false, 0);
+        // There is no user id on Windows.
+        #[cfg(not(windows))]
+        {
+            chips_layout.pack_start(&my_user_chip, false, false, 0);
+        }
+        chips_layout.pack_start(&graphical_session_chip, false, false, 0);
+        let count_label = gtk::Label::new(None);
+        count_label.set_margin_end(5);
+        chips_layout.pack_end(&count_label, false, false, 0);
+
+        let list_layout = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        list_layout.pack_start(&overlay, true, true, 0);
+        list_layout.pack_start(&preview_layout, false, false, 0);
         let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
         let horizontal_layout = gtk::Grid::new();
 
-        left_tree.connect_cursor_changed(
-            glib::clone!(@weak current_pid, @weak kill_button, @weak info_button => move |tree_view| {
-                let selection = tree_view.selection();
-                let (pid, ret) = if let Some((model, iter)) = selection.selected() {
-                    if let Ok(x) = model.value(&iter, 0).get::<u32>() {
-                        (Some(Pid::from_u32(x)), true)
-                    } else {
-                        (None, false)
-                    }
-                } else {
-                    (None, false)
-                };
-                current_pid.set(pid);
-                kill_button.set_sensitive(ret);
-                info_button.set_sensitive(ret);
+        // Several processes can be selected to end them all at once.
+        left_tree.selection().set_mode(gtk::SelectionMode::Multiple);
+        left_tree.selection().connect_changed(
+            glib::clone!(@weak selected_pids, @weak kill_button, @weak info_button, @weak cpu_preview,
+                         @weak replaying => move |selection| {
+                let (paths, model) = selection.selected_rows();
+                let pids = paths
+                    .iter()
+                    .filter_map(|path| model.iter(path))
+                    .filter(|iter| !is_group_row(&model, iter))
+                    .filter_map(|iter| model.value(&iter, 0).get::<u32>().ok())
+                    .map(Pid::from_u32)
+                    .collect::<Vec<_>>();
+                // The replayed processes aren't the running ones.
+                let actions = !pids.is_empty() && !replaying.get();
+                kill_button.set_sensitive(actions);
+                info_button.set_sensitive(actions);
+                cpu_preview.borrow().bind(pids.first().copied());
+                *selected_pids.borrow_mut() = pids;
             }),
         );
         kill_button.set_sensitive(false);
         info_button.set_sensitive(false);
 
-        vertical_layout.pack_start(&overlay, true, true, 0);
+        // Dragging a process out of the list provides its pid as text.
+        left_tree.drag_source_set(
+            gdk::ModifierType::BUTTON1_MASK,
+            &[gtk::TargetEntry::new(
+                "text/plain",
+                gtk::TargetFlags::OTHER_APP,
+                0,
+            )],
+            gdk::DragAction::COPY,
+        );
+        left_tree.connect_drag_data_get(
+            glib::clone!(@weak selected_pids => move |_, _, selection_data, _, _| {
+                let pids = selected_pids
+                    .borrow()
+                    .iter()
+                    .map(|pid| pid.to_string())
+                    .collect::<Vec<_>>();
+                if !pids.is_empty() {
+                    selection_data.set_text(&pids.join("\n"));
+                }
+            }),
+        );
+
+        // Right-clicking a process selects it (unless it's already part of the selection) and
+        // opens the context menu.
+        let context_menu = gtk::Menu::new();
+        let end_task_item = gtk::MenuItem::with_label("End task");
+        context_menu.append(&end_task_item);
+        let pin_item = gtk::MenuItem::with_label("Pin");
+        context_menu.append(&pin_item);
+        let pinned = PinnedProcesses::new(settings.borrow().pinned_processes_on_top);
+        let pinned_on_top_item = gtk::CheckMenuItem::with_label("Keep pinned processes on top");
+        pinned_on_top_item.set_active(pinned.on_top.get());
+        context_menu.append(&pinned_on_top_item);
+        #[allow(unused_mut)]
+        let mut signal_items = Vec::new();
+        #[cfg(not(windows))]
+        {
+            let signals_menu = gtk::Menu::new();
+            for (label, signal) in SIGNALS {
+                let item = gtk::MenuItem::with_label(label);
+                signals_menu.append(&item);
+                signal_items.push((item, *signal));
+            }
+            let send_signal_item = gtk::MenuItem::with_label("Send signal");
+            send_signal_item.set_submenu(Some(&signals_menu));
+            context_menu.append(&send_signal_item);
+        }
+        context_menu.show_all();
+        left_tree.connect_button_press_event(
+            glib::clone!(@strong context_menu, @weak pin_item, @weak selected_pids,
+                         @strong pinned => @default-return Inhibit(false), move |tree_view, event| {
+                if event.event_type() != gdk::EventType::ButtonPress || event.button() != 3 {
+                    return Inhibit(false);
+                }
+                let (x, y) = event.position();
+                match tree_view.path_at_pos(x as i32, y as i32) {
+                    Some((Some(path), _, _, _)) => {
+                        if !tree_view.selection().path_is_selected(&path) {
+                            tree_view.set_cursor(&path, None::<&gtk::TreeViewColumn>, false);
+                        }
+                        // The selection is unpinned if all its processes are pinned.
+                        let all_pinned =
+                            selected_pids.borrow().iter().all(|pid| pinned.contains(*pid));
+                        pin_item.set_label(if all_pinned { "Unpin" } else { "Pin" });
+                        context_menu.popup_at_pointer(Some(&**event));
+                        Inhibit(true)
+                    }
+                    _ => Inhibit(false),
+                }
+            }),
+        );
+
+        vertical_layout.pack_start(&chips_layout, false, true, 0);
+        vertical_layout.pack_start(&list_layout, true, true, 0);
         horizontal_layout.attach(&info_button, 0, 0, 4, 1);
         horizontal_layout.attach_next_to(
             &kill_button,
@@ -136,36 +767,159 @@ impl Procs {
             1,
             1,
         );
+        horizontal_layout.attach_next_to(
+            &tree_button,
+            Some(&filter_button),
+            gtk::PositionType::Right,
+            1,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &group_button,
+            Some(&tree_button),
+            gtk::PositionType::Right,
+            1,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &export_button,
+            Some(&group_button),
+            gtk::PositionType::Right,
+            2,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &refresh_button,
+            Some(&export_button),
+            gtk::PositionType::Right,
+            1,
+            1,
+        );
         horizontal_layout.set_column_homogeneous(true);
         vertical_layout.pack_start(&horizontal_layout, false, true, 0);
 
+        // The focus chain is: tree -> info -> kill -> filter -> filter entry -> regex -> case ->
+        // close filter -> tree mode -> quick filters.
+        // Since the filter entry and its options are inside the overlay, they're not part of the
+        // automatic chain and we need to move the focus to and from them manually.
+        overlay.set_focus_chain(&[scroll.clone().upcast()]);
+        list_layout.set_focus_chain(&[overlay.clone().upcast()]);
+        filter_layout.set_focus_chain(&[
+            filter_entry.clone().upcast(),
+            regex_button.clone().upcast(),
+            case_button.clone().upcast(),
+            close_filter_button.clone().upcast(),
+        ]);
+        horizontal_layout.set_focus_chain(&[
+            info_button.clone().upcast(),
+            kill_button.clone().upcast(),
+            filter_button.clone().upcast(),
+            tree_button.clone().upcast(),
+            group_button.clone().upcast(),
+            export_button.clone().upcast(),
+            refresh_button.clone().upcast(),
+        ]);
+        vertical_layout.set_focus_chain(&[
+            list_layout.clone().upcast(),
+            horizontal_layout.clone().upcast(),
+            chips_layout.clone().upcast(),
+        ]);
+        filter_button.connect_key_press_event(
+            glib::clone!(@weak filter_entry => @default-return Inhibit(false), move |_, key| {
+                if key.keyval() == gdk::keys::constants::Tab && WidgetExt::is_visible(&filter_entry) {
+                    filter_entry.grab_focus();
+                    return Inhibit(true);
+                }
+                Inhibit(false)
+            }),
+        );
+        close_filter_button.connect_key_press_event(
+            glib::clone!(@weak tree_button => @default-return Inhibit(false), move |_, key| {
+                if key.keyval() == gdk::keys::constants::Tab {
+                    tree_button.grab_focus();
+                    return Inhibit(true);
+                }
+                Inhibit(false)
+            }),
+        );
+        filter_entry.connect_key_press_event(glib::clone!(@weak left_tree, @weak search_bar
+                     => @default-return Inhibit(false), move |filter_entry, key| {
+            let keyval = key.keyval();
+            if keyval == gdk::keys::constants::Escape {
+                hide_filter(filter_entry, &search_bar);
+                left_tree.grab_focus();
+                return Inhibit(true);
+            }
+            // The arrows move in the process list so a process can be selected while
+            // typing the filter. The selection change updates the buttons as usual.
+            let direction = if keyval == gdk::keys::constants::Up {
+                -1
+            } else if keyval == gdk::keys::constants::Down {
+                1
+            } else {
+                return Inhibit(false);
+            };
+            // The tree view only moves its cursor when it has the focus.
+            left_tree.grab_focus();
+            left_tree.emit_move_cursor(gtk::MovementStep::DisplayLines, direction);
+            filter_entry.grab_focus_without_selecting();
+            Inhibit(true)
+        }));
+
         // The filter part.
         let filter_model = gtk::TreeModelFilter::new(&list_store, None);
+        // Compiled from the filter entry text when the regex mode is enabled, `None` if the
+        // expression is invalid.
+        let filter_regex: Rc<RefCell<Option<Regex>>> = Rc::new(RefCell::new(None));
         filter_model.set_visible_func(
-            glib::clone!(@weak filter_entry => @default-return false, move |model, iter| {
-                if !WidgetExt::is_visible(&filter_entry) || filter_entry.text_length() < 1 {
+            glib::clone!(@weak filter_entry, @weak high_cpu_chip, @weak high_mem_chip,
+                         @weak my_user_chip, @weak graphical_session_chip, @weak regex_button,
+                         @weak case_button, @weak settings, @strong filter_regex
+                         => @default-return false, move |model, iter| {
+                if is_hidden_kernel_thread(model, iter, &settings.borrow()) {
+                    return false;
+                }
+                let chips_active = high_cpu_chip.is_active()
+                    || high_mem_chip.is_active()
+                    || my_user_chip.is_active()
+                    || graphical_session_chip.is_active();
+                let query = if WidgetExt::is_visible(&filter_entry) && filter_entry.text_length() > 0 {
+                    Some(FilterQuery::parse(&filter_entry.text()))
+                } else {
+                    None
+                };
+                if !chips_active && query.is_none() {
                     return true;
                 }
-                let text = filter_entry.text();
-                    if text.is_empty() {
-                        return true;
+                let case_sensitive = case_button.is_active();
+                let regex = filter_regex.borrow();
+                let regex = if regex_button.is_active() { regex.as_ref() } else { None };
+                row_or_descendant_matches(model, iter, &|model, iter| {
+                    // All the active quick filters have to match.
+                    if high_cpu_chip.is_active()
+                        && model.value(iter, 6).get::<f32>().unwrap_or(0.) < HIGH_CPU_THRESHOLD {
+                        return false;
+                    }
+                    if high_mem_chip.is_active()
+                        && model.value(iter, 7).get::<u64>().unwrap_or(0) < HIGH_MEMORY_THRESHOLD {
+                        return false;
                     }
-                    let text: &str = text.as_ref();
-                    // TODO: Maybe add an option to make searches case sensitive?
-                    let pid = model.value(iter, 0)
-                                   .get::<u32>()
-                                   .map(|p| p.to_string())
-                                   .ok()
-                                   .unwrap_or_else(String::new);
-                    let name = model.value(iter, 1)
-                                    .get::<String>()
-                                    .map(|s| s.to_lowercase())
-                                    .ok()
-                                    .unwrap_or_else(String::new);
-                    pid.contains(text) ||
-                    text.contains(&pid) ||
-                    name.contains(text) ||
-                    text.contains(&name)
+                    if my_user_chip.is_active()
+                        && model.value(iter, 9).get::<u32>().ok() != Some(current_uid()) {
+                        return false;
+                    }
+                    if graphical_session_chip.is_active()
+                        && !model.value(iter, 10).get::<bool>().unwrap_or(false) {
+                        return false;
+                    }
+                    match query {
+                        Some(ref query) => {
+                            query.matches_fields(model, iter, case_sensitive)
+                                && query.matches_text(model, iter, regex, case_sensitive)
+                        }
+                        None => true,
+                    }
+                })
             }),
         );
         // For the filtering to be taken into account, we need to add it directly into the
@@ -173,17 +927,239 @@ impl Procs {
         let sort_model = gtk::TreeModelSort::new(&filter_model);
         left_tree.set_model(Some(&sort_model));
 
-        append_column("pid", &mut columns, &left_tree, None);
-        append_column("process name", &mut columns, &left_tree, Some(200));
-        append_column("cpu usage", &mut columns, &left_tree, None);
-        append_column("memory usage", &mut columns, &left_tree, None);
+        append_column(
+            "pid",
+            0,
+            &mut columns,
+            &left_tree,
+            None,
+            ROW_BACKGROUND_COLUMN,
+        );
+        let name_renderer = append_column(
+            "process name",
+            1,
+            &mut columns,
+            &left_tree,
+            Some(200),
+            ROW_BACKGROUND_COLUMN,
+        );
+        // The star of the pinned processes is displayed before their name.
+        {
+            let renderer = gtk::CellRendererPixbuf::new();
+            columns[1].pack_start(&renderer, false);
+            columns[1].reorder(&renderer, 0);
+            columns[1].add_attribute(&renderer, "icon-name", 29);
+            columns[1].add_attribute(&renderer, "cell-background", ROW_BACKGROUND_COLUMN);
+        }
+        let cpu_renderer = append_column("cpu usage", 2, &mut columns, &left_tree, None, 15);
+        columns[2].add_attribute(&cpu_renderer, "foreground", 21);
+        let memory_renderer = append_column("memory usage", 3, &mut columns, &left_tree, None, 16);
+        columns[3].add_attribute(&memory_renderer, "foreground", 22);
+        append_column(
+            "mem %",
+            23,
+            &mut columns,
+            &left_tree,
+            None,
+            ROW_BACKGROUND_COLUMN,
+        );
         #[cfg(not(windows))]
         {
-            append_column("disk I/O usage", &mut columns, &left_tree, None);
+            append_column(
+                "disk read",
+                4,
+                &mut columns,
+                &left_tree,
+                None,
+                ROW_BACKGROUND_COLUMN,
+            );
+            append_column(
+                "disk write",
+                17,
+                &mut columns,
+                &left_tree,
+                None,
+                ROW_BACKGROUND_COLUMN,
+            );
         }
         #[cfg(windows)]
         {
-            append_column("I/O usage", &mut columns, &left_tree, None);
+            append_column(
+                "I/O read",
+                4,
+                &mut columns,
+                &left_tree,
+                None,
+                ROW_BACKGROUND_COLUMN,
+            );
+            append_column(
+                "I/O write",
+                17,
+                &mut columns,
+                &left_tree,
+                None,
+                ROW_BACKGROUND_COLUMN,
+            );
+        }
+        append_column(
+            "parent pid",
+            19,
+            &mut columns,
+            &left_tree,
+            None,
+            ROW_BACKGROUND_COLUMN,
+        );
+        append_column(
+            "start time",
+            25,
+            &mut columns,
+            &left_tree,
+            None,
+            ROW_BACKGROUND_COLUMN,
+        );
+        // Sorted by the timestamp rather than by the displayed date.
+        if let Some(column) = columns.last() {
+            column.set_sort_column_id(26);
+        }
+        append_column(
+            "running for",
+            30,
+            &mut columns,
+            &left_tree,
+            None,
+            ROW_BACKGROUND_COLUMN,
+        );
+        // Sorted by the number of seconds rather than by the displayed duration.
+        if let Some(column) = columns.last() {
+            column.set_sort_column_id(31);
+        }
+        #[cfg(not(windows))]
+        {
+            append_column(
+                "user",
+                20,
+                &mut columns,
+                &left_tree,
+                None,
+                ROW_BACKGROUND_COLUMN,
+            );
+        }
+        #[cfg(target_os = "linux")]
+        {
+            append_column(
+                "leader",
+                11,
+                &mut columns,
+                &left_tree,
+                None,
+                ROW_BACKGROUND_COLUMN,
+            );
+        }
+        {
+            let renderer = gtk::CellRendererPixbuf::new();
+            let column = gtk::TreeViewColumn::new();
+            column.set_title("leak");
+            column.set_reorderable(true);
+            column.pack_start(&renderer, false);
+            column.add_attribute(&renderer, "icon-name", 12);
+            column.add_attribute(&renderer, "cell-background", ROW_BACKGROUND_COLUMN);
+            column.set_sort_column_id(12);
+            left_tree.append_column(&column);
+            columns.push(column);
+        }
+
+        // The user can annotate a process by editing its name.
+        let annotations: Rc<RefCell<HashMap<Pid, String>>> = Rc::new(RefCell::new(HashMap::new()));
+        name_renderer.set_editable(true);
+        name_renderer.connect_editing_started(
+            glib::clone!(@weak annotations, @weak sort_model => move |_, editable, path| {
+                let pid = sort_model
+                    .iter(&path)
+                    .filter(|iter| !is_group_row(&sort_model, iter))
+                    .and_then(|iter| sort_model.value(&iter, 0).get::<u32>().ok());
+                if let (Some(pid), Some(entry)) = (pid, editable.downcast_ref::<gtk::Entry>()) {
+                    let annotations = annotations.borrow();
+                    entry.set_text(
+                        annotations
+                            .get(&Pid::from_u32(pid))
+                            .map(|s| s.as_str())
+                            .unwrap_or(""),
+                    );
+                }
+            }),
+        );
+        name_renderer.connect_edited(
+            glib::clone!(@weak annotations, @weak sort_model, @weak filter_model, @weak list_store => move |_, path, new_text| {
+                let iter = match list_store_iter(&sort_model, &filter_model, &path) {
+                    Some(iter) if !is_group_row(&list_store, &iter) => iter,
+                    _ => return,
+                };
+                let pid = match list_store.value(&iter, 0).get::<u32>() {
+                    Ok(pid) => Pid::from_u32(pid),
+                    Err(_) => return,
+                };
+                let name = list_store.value(&iter, 13).get::<String>().unwrap_or_default();
+                let annotation = new_text.trim();
+                if annotation.is_empty() {
+                    annotations.borrow_mut().remove(&pid);
+                } else {
+                    annotations.borrow_mut().insert(pid, annotation.to_owned());
+                }
+                let annotations = annotations.borrow();
+                let name = annotated_name(&name, annotations.get(&pid).map(|a| a.as_str()));
+                list_store.set(&iter, &[(1, &name)]);
+            }),
+        );
+
+        // The priority can be changed by editing the "nice" column.
+        #[cfg(not(windows))]
+        {
+            use gtk::prelude::CellRendererSpinExt;
+
+            let renderer = gtk::CellRendererSpin::new();
+            renderer.set_adjustment(Some(&gtk::Adjustment::new(0., -20., 19., 1., 5., 0.)));
+            renderer.set_editable(true);
+            renderer.set_xalign(1.0);
+            let column = gtk::TreeViewColumn::new();
+            column.set_title("nice");
+            column.set_resizable(true);
+            column.set_reorderable(true);
+            column.pack_start(&renderer, true);
+            column.add_attribute(&renderer, "text", 14);
+            column.add_attribute(&renderer, "cell-background", ROW_BACKGROUND_COLUMN);
+            column.set_clickable(true);
+            column.set_sort_column_id(14);
+            left_tree.append_column(&column);
+            columns.push(column);
+            set_stable_sort_func::<i32>(&sort_model, 14, &pinned);
+
+            renderer.connect_edited(
+                glib::clone!(@weak sort_model, @weak filter_model, @weak list_store, @weak window =>
+                    move |_, path, new_text| {
+                    let nice = match new_text.trim().parse::<i32>() {
+                        Ok(nice) => nice,
+                        Err(_) => return,
+                    };
+                    let iter = match list_store_iter(&sort_model, &filter_model, &path) {
+                        Some(iter) if !is_group_row(&list_store, &iter) => iter,
+                        _ => return,
+                    };
+                    let pid = match list_store.value(&iter, 0).get::<u32>() {
+                        Ok(pid) => Pid::from_u32(pid),
+                        Err(_) => return,
+                    };
+                    let name = list_store.value(&iter, 13).get::<String>().unwrap_or_default();
+                    let changed = crate::actions::run_process_action(
+                        &window,
+                        "change the priority of",
+                        &name,
+                        || crate::actions::set_priority(pid, nice),
+                    );
+                    if changed {
+                        list_store.set(&iter, &[(14, &process_priority(pid))]);
+                    }
+                }),
+            );
         }
 
         // When we click the "name" column the order is defined by the
@@ -195,13 +1171,187 @@ impl Procs {
         // The memory usage display has been improved, so to make efficient sort,
         // we have to separate the display and the actual number.
         columns[3].set_sort_column_id(7);
-        // The disk I/O usage display has been improved, so to make efficient sort,
-        // we have to separate the display and the actual number.
-        columns[4].set_sort_column_id(8);
+        // Same for the memory percentage and the disk read and write columns.
+        columns[4].set_sort_column_id(24);
+        columns[5].set_sort_column_id(8);
+        columns[6].set_sort_column_id(18);
+        // Rows with the same sort key are ordered by pid so they don't move at every refresh.
+        set_stable_sort_func::<String>(&sort_model, 5, &pinned);
+        set_stable_sort_func::<f32>(&sort_model, 6, &pinned);
+        set_stable_sort_func::<u64>(&sort_model, 7, &pinned);
+        set_stable_sort_func::<f32>(&sort_model, 24, &pinned);
+        set_stable_sort_func::<u64>(&sort_model, 8, &pinned);
+        set_stable_sort_func::<u64>(&sort_model, 18, &pinned);
+        set_stable_sort_func::<u32>(&sort_model, 19, &pinned);
+        set_stable_sort_func::<u64>(&sort_model, 26, &pinned);
+        set_stable_sort_func::<u64>(&sort_model, 31, &pinned);
+        set_stable_sort_func::<String>(&sort_model, 20, &pinned);
+        set_stable_sort_func::<String>(&sort_model, 12, &pinned);
+        set_stable_sort_func::<String>(&sort_model, 11, &pinned);
 
-        filter_entry.connect_text_length_notify(move |_| {
-            filter_model.refilter();
-        });
+        pin_item.connect_activate(
+            glib::clone!(@weak selected_pids, @weak list_store, @strong pinned => move |_| {
+                let selected = selected_pids.borrow().clone();
+                {
+                    let mut pids = pinned.pids.borrow_mut();
+                    if selected.iter().all(|pid| pids.contains(pid)) {
+                        for pid in &selected {
+                            pids.remove(pid);
+                        }
+                    } else {
+                        pids.extend(selected.iter().copied());
+                    }
+                }
+                pinned.update_rows(&list_store, &selected);
+            }),
+        );
+        pinned_on_top_item.connect_toggled(
+            glib::clone!(@weak list_store, @weak settings, @strong pinned => move |item| {
+                pinned.on_top.set(item.is_active());
+                let pids = pinned.pids.borrow().iter().copied().collect::<Vec<_>>();
+                pinned.update_rows(&list_store, &pids);
+                let mut settings = settings.borrow_mut();
+                settings.pinned_processes_on_top = item.is_active();
+                settings.save();
+            }),
+        );
+        if settings.borrow().sort_by_name_by_default {
+            sort_model.set_sort_column_id(gtk::SortColumn::Index(5), gtk::SortType::Ascending);
+        }
+
+        // We restore the columns order.
+        let mut previous: Option<gtk::TreeViewColumn> = None;
+        for title in settings.borrow().process_columns_order.iter() {
+            if let Some(column) = columns
+                .iter()
+                .find(|c| c.title().as_deref() == Some(title.as_str()))
+            {
+                left_tree.move_column_after(column, previous.as_ref());
+                previous = Some(column.clone());
+            }
+        }
+        // Right-clicking a column header opens a menu to choose the displayed columns.
+        let columns_menu = gtk::Menu::new();
+        for column in columns.iter() {
+            let title = column.title().map(|t| t.to_string()).unwrap_or_default();
+            if settings.borrow().hidden_process_columns.contains(&title) {
+                column.set_visible(false);
+            }
+            let item = gtk::CheckMenuItem::with_label(&title);
+            item.set_active(column.is_visible());
+            // The name column can't be hidden so there's always a header to open this menu.
+            item.set_sensitive(title != "process name");
+            item.connect_toggled(glib::clone!(@weak column, @weak settings => move |item| {
+                column.set_visible(item.is_active());
+                let mut settings = settings.borrow_mut();
+                settings.hidden_process_columns.retain(|t| *t != title);
+                if !item.is_active() {
+                    settings.hidden_process_columns.push(title.clone());
+                }
+                settings.save();
+            }));
+            columns_menu.append(&item);
+        }
+        columns_menu.show_all();
+        for button in columns.iter().filter_map(|c| c.button()) {
+            button.connect_button_press_event(
+                glib::clone!(@strong columns_menu => move |_, event| {
+                    if event.event_type() != gdk::EventType::ButtonPress || event.button() != 3 {
+                        return Inhibit(false);
+                    }
+                    columns_menu.popup_at_pointer(Some(&**event));
+                    Inhibit(true)
+                }),
+            );
+        }
+
+        let columns_len = columns.len();
+        left_tree.connect_columns_changed(glib::clone!(@weak settings => move |tree| {
+            // Columns are removed one by one when the tree view is destroyed, we don't want to
+            // save the order in this case.
+            if tree.columns().len() != columns_len {
+                return;
+            }
+            let mut settings = settings.borrow_mut();
+            settings.process_columns_order = tree
+                .columns()
+                .iter()
+                .filter_map(|c| c.title().map(|t| t.to_string()))
+                .collect();
+            settings.save();
+        }));
+
+        for chip in [
+            &high_cpu_chip,
+            &high_mem_chip,
+            &my_user_chip,
+            &graphical_session_chip,
+        ] {
+            chip.connect_toggled(
+                glib::clone!(@weak filter_model, @weak left_tree => move |_| {
+                    filter_model.refilter();
+                    scroll_to_selection(&left_tree);
+                }),
+            );
+        }
+        filter_entry.connect_changed(
+            glib::clone!(@weak filter_model, @weak left_tree, @weak regex_button,
+                         @weak case_button, @strong filter_regex => move |filter_entry| {
+                update_filter_regex(filter_entry, &regex_button, &case_button, &filter_regex);
+                filter_model.refilter();
+                scroll_to_selection(&left_tree);
+            }),
+        );
+        for button in [&regex_button, &case_button] {
+            button.connect_toggled(
+                glib::clone!(@weak filter_model, @weak left_tree, @weak filter_entry,
+                             @weak regex_button, @weak case_button,
+                             @strong filter_regex => move |_| {
+                    update_filter_regex(&filter_entry, &regex_button, &case_button, &filter_regex);
+                    filter_model.refilter();
+                    scroll_to_selection(&left_tree);
+                }),
+            );
+        }
+
+        // Rows are inserted/deleted in the filter model whenever their visibility changes, so
+        // listening to both models is enough to keep the process count up to date. The thread
+        // count changes on each refresh though. Since a refresh updates all the rows, the count is
+        // only computed once they're all done.
+        let count_pending = Rc::new(Cell::new(false));
+        let schedule_count_update = Rc::new(glib::clone!(
+            @weak count_label, @weak filter_model, @weak list_store, @weak settings => move || {
+                if count_pending.replace(true) {
+                    return;
+                }
+                glib::idle_add_local_once(glib::clone!(
+                    @weak count_label, @weak filter_model, @weak list_store, @weak settings,
+                    @strong count_pending => move || {
+                        count_pending.set(false);
+                        update_count_label(
+                            &count_label,
+                            &filter_model,
+                            &list_store,
+                            &settings.borrow(),
+                        );
+                    }
+                ));
+            }
+        ));
+        filter_model.connect_row_inserted(
+            glib::clone!(@strong schedule_count_update => move |_, _, _| schedule_count_update()),
+        );
+        filter_model.connect_row_deleted(
+            glib::clone!(@strong schedule_count_update => move |_, _| schedule_count_update()),
+        );
+        list_store.connect_row_inserted(
+            glib::clone!(@strong schedule_count_update => move |_, _, _| schedule_count_update()),
+        );
+        list_store.connect_row_deleted(
+            glib::clone!(@strong schedule_count_update => move |_, _| schedule_count_update()),
+        );
+        list_store.connect_row_changed(move |_, _, _| schedule_count_update());
+        update_count_label(&count_label, &filter_model, &list_store, &settings.borrow());
 
         note.create_tab("Process list", &vertical_layout);
 
@@ -217,34 +1367,271 @@ impl Procs {
         Procs {
             left_tree,
             scroll,
-            current_pid,
+            selected_pids,
             kill_button,
             info_button,
             vertical_layout: vertical_layout
                 .downcast::<gtk::Box>()
                 .expect("downcast failed"),
             list_store,
+            filter_model,
+            tree_button,
+            group_button,
+            layout,
             columns,
             filter_entry,
             search_bar,
             filter_button,
+            export_button,
+            refresh_button,
+            cpu_preview,
+            leak_detector,
+            process_changes,
+            pinned,
+            annotations,
+            end_task_item,
+            signal_items,
+            count_label,
+            replaying,
         }
     }
 
     pub fn hide_filter(&self) {
-        self.filter_entry.hide();
-        self.filter_entry.set_text("");
-        self.search_bar.set_search_mode(false);
+        hide_filter(&self.filter_entry, &self.search_bar);
     }
+
+    /// Stops the live updates of the list and disables everything acting on the processes, since
+    /// the listed ones are replayed. The columns with the given titles are hidden, without
+    /// changing the settings.
+    pub fn start_replay(&self, hidden_columns: &[&str]) {
+        self.replaying.set(true);
+        self.kill_button.set_sensitive(false);
+        self.info_button.set_sensitive(false);
+        self.end_task_item.set_sensitive(false);
+        for (item, _) in &self.signal_items {
+            item.set_sensitive(false);
+        }
+        self.refresh_button.set_sensitive(false);
+        self.tree_button.set_sensitive(false);
+        self.group_button.set_sensitive(false);
+        for column in &self.columns {
+            if column
+                .title()
+                .map_or(false, |title| hidden_columns.contains(&title.as_str()))
+            {
+                column.set_visible(false);
+            }
+            // The priority is changed by editing the "nice" column.
+            for cell in column.cells() {
+                if cell.is::<gtk::CellRendererSpin>() {
+                    cell.set_property("editable", false);
+                }
+            }
+        }
+    }
+}
+
+/// Hides the filter entry and clears it, so all the processes are displayed again.
+fn hide_filter(filter_entry: &gtk::Entry, search_bar: &gtk::SearchBar) {
+    filter_entry.hide();
+    filter_entry.set_text("");
+    search_bar.set_search_mode(false);
+}
+
+/// Writes the rows of `model` into a CSV file. Exporting the model displayed by the process list
+/// gives the processes matching the filter, in the displayed order.
+pub fn export_to_csv(model: &gtk::TreeModel, path: &Path) -> Result<(), String> {
+    let mut csv = String::from(
+        "pid,name,cpu usage (%),memory (bytes),disk read (bytes),disk written (bytes)\n",
+    );
+    model.foreach(|model, _, iter| {
+        // The processes of a group are exported on their own.
+        if is_group_row(model, iter) {
+            return false;
+        }
+        let name = model.value(iter, 13).get::<String>().unwrap_or_default();
+        csv.push_str(&format!(
+            "{},\"{}\",{:.1},{},{},{}\n",
+            model.value(iter, 0).get::<u32>().unwrap_or(0),
+            name.replace('"', "\"\""),
+            model.value(iter, 6).get::<f32>().unwrap_or(0.),
+            model.value(iter, 7).get::<u64>().unwrap_or(0),
+            model.value(iter, 8).get::<u64>().unwrap_or(0),
+            model.value(iter, 18).get::<u64>().unwrap_or(0),
+        ));
+        false
+    });
+    std::fs::write(path, csv).map_err(|e| {
+        format!(
+            "Failed to export the process list to '{}': {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// The filter entry text, split into its `key:value` parts and the rest.
+#[derive(Default)]
+struct FilterQuery {
+    // "user:" only keeps the processes of this user.
+    user: Option<String>,
+    // "pid:" only keeps the process with this pid.
+    pid: Option<String>,
+    // "name:" only keeps the processes whose name contains it.
+    name: Option<String>,
+    // Matched against the pid and the name (or used as a regex).
+    text: String,
+}
+
+impl FilterQuery {
+    fn parse(text: &str) -> FilterQuery {
+        let mut query = FilterQuery::default();
+        let mut rest = Vec::new();
+        for word in text.split_whitespace() {
+            match word.split_once(':') {
+                Some(("user", value)) => query.user = Some(value.to_owned()),
+                Some(("pid", value)) => query.pid = Some(value.to_owned()),
+                Some(("name", value)) => query.name = Some(value.to_owned()),
+                _ => rest.push(word),
+            }
+        }
+        // Without any `key:value` part, the text is kept as is (spaces included).
+        query.text = if query.user.is_none() && query.pid.is_none() && query.name.is_none() {
+            text.to_owned()
+        } else {
+            rest.join(" ")
+        };
+        query
+    }
+
+    /// Returns `true` if the row matches all the `key:value` parts.
+    fn matches_fields(
+        &self,
+        model: &gtk::TreeModel,
+        iter: &gtk::TreeIter,
+        case_sensitive: bool,
+    ) -> bool {
+        let normalize = |s: &str| {
+            if case_sensitive {
+                s.to_owned()
+            } else {
+                s.to_lowercase()
+            }
+        };
+        if let Some(ref user) = self.user {
+            let row_user = model.value(iter, 20).get::<String>().unwrap_or_default();
+            if normalize(&row_user) != normalize(user) {
+                return false;
+            }
+        }
+        // The groups don't have a pid, their processes are filtered instead.
+        if let Some(ref pid) = self.pid {
+            if !is_group_row(model, iter)
+                && model
+                    .value(iter, 0)
+                    .get::<u32>()
+                    .ok()
+                    .map(|p| p.to_string())
+                    .as_ref()
+                    != Some(pid)
+            {
+                return false;
+            }
+        }
+        if let Some(ref name) = self.name {
+            let row_name = model.value(iter, 1).get::<String>().unwrap_or_default();
+            if !normalize(&row_name).contains(&normalize(name)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Matches the text (or `regex` if it's valid) against the pid and the name of the row.
+    fn matches_text(
+        &self,
+        model: &gtk::TreeModel,
+        iter: &gtk::TreeIter,
+        regex: Option<&Regex>,
+        case_sensitive: bool,
+    ) -> bool {
+        if self.text.is_empty() {
+            return true;
+        }
+        let pid = model
+            .value(iter, 0)
+            .get::<u32>()
+            .map(|p| p.to_string())
+            .unwrap_or_default();
+        let name = model.value(iter, 1).get::<String>().unwrap_or_default();
+        if let Some(regex) = regex {
+            return regex.is_match(&pid) || regex.is_match(&name);
+        }
+        let (text, name) = if case_sensitive {
+            (self.text.clone(), name)
+        } else {
+            (self.text.to_lowercase(), name.to_lowercase())
+        };
+        pid.contains(&text) || text.contains(&pid) || name.contains(&text) || text.contains(&name)
+    }
+}
+
+/// Returns `true` if `matches` returns `true` for the row or one of its descendants. The filter
+/// model hides the children of the rows it hides, so the parents (or the group) of a matching
+/// process have to stay visible for it to be displayed.
+fn row_or_descendant_matches<F: Fn(&gtk::TreeModel, &gtk::TreeIter) -> bool>(
+    model: &gtk::TreeModel,
+    iter: &gtk::TreeIter,
+    matches: &F,
+) -> bool {
+    if matches(model, iter) {
+        return true;
+    }
+    if let Some(child) = model.iter_children(Some(iter)) {
+        loop {
+            if row_or_descendant_matches(model, &child, matches) {
+                return true;
+            }
+            if !model.iter_next(&child) {
+                break;
+            }
+        }
+    }
+    false
+}
+
+/// Compiles the filter entry text if the regex mode is enabled. The entry is highlighted in red if
+/// the expression is invalid.
+fn update_filter_regex(
+    filter_entry: &gtk::Entry,
+    regex_button: &gtk::ToggleButton,
+    case_button: &gtk::ToggleButton,
+    filter_regex: &RefCell<Option<Regex>>,
+) {
+    let text = FilterQuery::parse(&filter_entry.text()).text;
+    let mut regex = None;
+    let style_context = filter_entry.style_context();
+    style_context.remove_class("error");
+    if regex_button.is_active() && !text.is_empty() {
+        match RegexBuilder::new(&text)
+            .case_insensitive(!case_button.is_active())
+            .build()
+        {
+            Ok(r) => regex = Some(r),
+            Err(_) => style_context.add_class("error"),
+        }
+    }
+    *filter_regex.borrow_mut() = regex;
 }
 
 fn append_column(
     title: &str,
+    id: i32,
     v: &mut Vec<gtk::TreeViewColumn>,
     left_tree: &gtk::TreeView,
     max_width: Option<i32>,
-) {
-    let id = v.len() as i32;
+    background_id: i32,
+) -> gtk::CellRendererText {
     let renderer = gtk::CellRendererText::new();
 
     if title != "process name" {
@@ -254,6 +1641,7 @@ fn append_column(
     let column = gtk::TreeViewColumn::new();
     column.set_title(title);
     column.set_resizable(true);
+    column.set_reorderable(true);
     if let Some(max_width) = max_width {
         column.set_max_width(max_width);
         column.set_expand(true);
@@ -261,35 +1649,509 @@ fn append_column(
     column.set_min_width(10);
     column.pack_start(&renderer, true);
     column.add_attribute(&renderer, "text", id);
+    column.add_attribute(&renderer, "cell-background", background_id);
     column.set_clickable(true);
     column.set_sort_column_id(id);
     left_tree.append_column(&column);
     v.push(column);
+    renderer
 }
 
-pub fn create_and_fill_model(
-    list_store: &gtk::ListStore,
-    pid: u32,
-    cmdline: &[String],
-    name: &str,
-    cpu: f32,
-    memory: u64,
+/// Returns the background color of the CPU cell if the usage changed significantly since the last
+/// refresh.
+pub fn cpu_change_highlight(previous: f32, current: f32) -> Option<&'static str> {
+    if (current - previous).abs() >= CPU_CHANGE_THRESHOLD {
+        Some(CHANGE_HIGHLIGHT_COLOR)
+    } else {
+        None
+    }
+}
+
+/// Returns the memory used by the process in percent of the total memory (in KiB, as returned by
+/// `sysinfo`).
+pub fn memory_percent(process: &Process, total_memory: u64) -> f32 {
+    if total_memory == 0 {
+        0.
+    } else {
+        (process.memory() as f64 * 100. / total_memory as f64) as f32
+    }
+}
+
+/// Returns the text color of the CPU cell, `None` if the usage is low.
+pub fn cpu_severity_color(cpu: f32) -> Option<&'static str> {
+    if cpu >= CPU_HIGH_SEVERITY {
+        Some(HIGH_SEVERITY_COLOR)
+    } else if cpu >= CPU_MEDIUM_SEVERITY {
+        Some(MEDIUM_SEVERITY_COLOR)
+    } else {
+        None
+    }
+}
+
+/// Same as `cpu_severity_color` but for the memory cell, `memory` being in bytes.
+pub fn memory_severity_color(memory: u64) -> Option<&'static str> {
+    if memory >= MEMORY_HIGH_SEVERITY {
+        Some(HIGH_SEVERITY_COLOR)
+    } else if memory >= MEMORY_MEDIUM_SEVERITY {
+        Some(MEDIUM_SEVERITY_COLOR)
+    } else {
+        None
+    }
+}
+
+/// Same as `cpu_change_highlight` but for the memory cell, relatively to the previous usage.
+pub fn memory_change_highlight(previous: u64, current: u64) -> Option<&'static str> {
+    let delta = (current as f64 - previous as f64).abs();
+    if previous > 0 && delta / previous as f64 >= MEMORY_CHANGE_RATIO_THRESHOLD {
+        Some(CHANGE_HIGHLIGHT_COLOR)
+    } else {
+        None
+    }
+}
+
+/// Converts a path of the process list view into an iterator of the underlying list store.
+fn list_store_iter(
+    sort_model: &gtk::TreeModelSort,
+    filter_model: &gtk::TreeModelFilter,
+    path: &gtk::TreePath,
+) -> Option<gtk::TreeIter> {
+    let iter = sort_model.iter(path)?;
+    let iter = sort_model.convert_iter_to_child_iter(&iter);
+    Some(filter_model.convert_iter_to_child_iter(&iter))
+}
+
+/// Sorts the rows by the given column and then by pid.
+fn set_stable_sort_func<T>(sort_model: &gtk::TreeModelSort, column: u32, pinned: &PinnedProcesses)
+where
+    T: for<'a> glib::value::FromValue<'a> + PartialOrd + 'static,
+{
+    let pinned = pinned.clone();
+    let weak_sort_model = sort_model.downgrade();
+    sort_model.set_sort_func(gtk::SortColumn::Index(column), move |model, a, b| {
+        let pid_a = model.value(a, 0).get::<u32>().unwrap_or(0);
+        let pid_b = model.value(b, 0).get::<u32>().unwrap_or(0);
+        let pinned_first = pinned.compare(Pid::from_u32(pid_a), Pid::from_u32(pid_b));
+        if pinned_first != Ordering::Equal {
+            // The order is reversed when sorting in descending order, the pinned processes have
+            // to stay on top though.
+            let descending = weak_sort_model
+                .upgrade()
+                .and_then(|sort_model| TreeSortableExtManual::sort_column_id(&sort_model))
+                .map_or(false, |(_, order)| order == gtk::SortType::Descending);
+            return if descending {
+                pinned_first.reverse()
+            } else {
+                pinned_first
+            };
+        }
+        let key_a = model.value(a, column as i32).get::<T>().ok();
+        let key_b = model.value(b, column as i32).get::<T>().ok();
+        key_a
+            .partial_cmp(&key_b)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| pid_a.cmp(&pid_b))
+    });
+}
+
+/// Selects the given process in the process list, expanding its parents in tree mode. Returns
+/// `false` if it isn't displayed.
+pub fn select_process(tree: &gtk::TreeView, pid: Pid) -> bool {
+    select_processes(tree, &[pid]) > 0
+}
+
+/// Selects all the processes of `pids` which are displayed and scrolls to the first one. Returns
+/// the number of selected processes.
+pub fn select_processes(tree: &gtk::TreeView, pids: &[Pid]) -> usize {
+    let model = match tree.model() {
+        Some(model) => model,
+        None => return 0,
+    };
+    let mut found = Vec::new();
+    model.foreach(|model, path, iter| {
+        if is_group_row(model, iter) {
+            return false;
+        }
+        if let Ok(pid) = model.value(iter, 0).get::<u32>() {
+            if pids.iter().any(|p| p.as_u32() == pid) {
+                found.push(path.clone());
+            }
+        }
+        false
+    });
+    if let Some(first) = found.first() {
+        for path in &found {
+            tree.expand_to_path(path);
+        }
+        // Setting the cursor replaces the selection, so the other processes are selected after.
+        tree.set_cursor(first, None::<&gtk::TreeViewColumn>, false);
+        tree.scroll_to_cell(Some(first), None::<&gtk::TreeViewColumn>, false, 0., 0.);
+        let selection = tree.selection();
+        for path in &found[1..] {
+            selection.select_path(path);
+        }
+    }
+    found.len()
+}
+
+/// Scrolls the process list to the first selected process, or to the first process if none is
+/// selected, so it remains visible after the list was filtered.
+fn scroll_to_selection(tree: &gtk::TreeView) {
+    let (paths, model) = tree.selection().selected_rows();
+    let path = paths
+        .into_iter()
+        .next()
+        .unwrap_or_else(gtk::TreePath::new_first);
+    if model.iter(&path).is_some() {
+        tree.scroll_to_cell(Some(&path), None::<&gtk::TreeViewColumn>, false, 0., 0.);
+    }
+}
+
+/// Returns `true` if the row is a kernel thread which shouldn't be displayed.
+fn is_hidden_kernel_thread(
+    model: &gtk::TreeModel,
+    iter: &gtk::TreeIter,
+    settings: &Settings,
+) -> bool {
+    if !settings.hide_kernel_threads
+        || !model
+            .value(iter, KERNEL_THREAD_COLUMN)
+            .get::<bool>()
+            .unwrap_or(false)
+    {
+        return false;
+    }
+    let name = model.value(iter, 13).get::<String>().unwrap_or_default();
+    !settings.displayed_kernel_threads.iter().any(|n| *n == name)
+}
+
+/// Counts the rows and the threads of their processes at all levels. The hidden kernel threads
+/// aren't counted.
+fn count_rows<T: TreeModelExt>(model: &T, settings: &Settings) -> (usize, u32) {
+    let mut count = 0;
+    let mut threads = 0;
+    model.foreach(|model, _, iter| {
+        // The processes of a group are counted on their own.
+        if is_group_row(model, iter) || is_hidden_kernel_thread(model, iter, settings) {
+            return false;
+        }
+        count += 1;
+        threads += model.value(iter, 27).get::<u32>().unwrap_or(0);
+        false
+    });
+    (count, threads)
+}
+
+fn update_count_label(
+    label: &gtk::Label,
+    filter_model: &gtk::TreeModelFilter,
+    list_store: &gtk::TreeStore,
+    settings: &Settings,
 ) {
-    if cmdline.is_empty() || name.is_empty() {
-        return;
+    let (shown, shown_threads) = count_rows(filter_model, settings);
+    let (total, total_threads) = count_rows(list_store, settings);
+    // The thread count isn't available on all platforms.
+    let text = if total_threads == 0 {
+        if shown == total {
+            format!("{} processes", total)
+        } else {
+            format!("showing {} of {} processes", shown, total)
+        }
+    } else if shown == total {
+        format!("{} processes, {} threads", total, total_threads)
+    } else {
+        format!(
+            "showing {} of {} processes ({} of {} threads)",
+            shown, total, shown_threads, total_threads
+        )
+    };
+    label.set_text(&text);
+}
+
+/// Returns the number of threads of the process, 0 if it isn't known.
+#[cfg(target_os = "linux")]
+pub fn process_thread_count(process: &Process) -> u32 {
+    // The main thread is part of the tasks, except if they couldn't be retrieved.
+    process.tasks.len().max(1) as u32
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn process_thread_count(_process: &Process) -> u32 {
+    0
+}
+
+#[cfg(not(windows))]
+fn process_uid(process: &Process) -> u32 {
+    process.uid
+}
+
+#[cfg(windows)]
+fn process_uid(_process: &Process) -> u32 {
+    0
+}
+
+/// Returns the name of the user owning the process, or its uid if the user is unknown.
+#[cfg(not(windows))]
+pub fn process_user_name(process: &Process, users: &[User]) -> String {
+    use sysinfo::UserExt;
+
+    let uid = process_uid(process);
+    users
+        .iter()
+        .find(|user| *user.uid() == uid)
+        .map_or_else(|| uid.to_string(), |user| user.name().to_owned())
+}
+
+// The owner of the processes isn't available on Windows.
+#[cfg(windows)]
+pub fn process_user_name(_process: &Process, _users: &[User]) -> String {
+    String::new()
+}
+
+/// Returns the nice value of the process.
+#[cfg(not(windows))]
+pub fn process_priority(pid: Pid) -> i32 {
+    // `getpriority` can return -1 on success, so errors can only be detected through `errno`.
+    // They are ignored here anyway: the process might already be gone.
+    unsafe { libc::getpriority(libc::PRIO_PROCESS, pid.as_u32() as libc::id_t) }
+}
+
+#[cfg(windows)]
+pub fn process_priority(_pid: Pid) -> i32 {
+    0
+}
+
+#[cfg(not(windows))]
+fn current_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+// The "My user" quick filter isn't available on Windows so this is never called.
+#[cfg(windows)]
+fn current_uid() -> u32 {
+    0
+}
+
+/// Returns the fields of `/proc/<pid>/stat` following the process name: state, ppid, pgrp,
+/// session, tty_nr, tpgid, flags...
+#[cfg(target_os = "linux")]
+fn process_stat_fields(pid: Pid) -> Option<Vec<String>> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The process name can contain spaces and parentheses so we start after the last ')'.
+    let pos = stat.rfind(')')?;
+    Some(
+        stat[pos + 1..]
+            .split_whitespace()
+            .map(|field| field.to_owned())
+            .collect(),
+    )
+}
+
+/// Returns if the process is a session leader or a process group leader.
+#[cfg(target_os = "linux")]
+fn process_leader_status(pid: Pid) -> &'static str {
+    let fields = match process_stat_fields(pid) {
+        Some(fields) => fields,
+        None => return "",
+    };
+    let pid = pid.to_string();
+    if fields.get(3) == Some(&pid) {
+        "session leader"
+    } else if fields.get(2) == Some(&pid) {
+        "group leader"
+    } else {
+        ""
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_leader_status(_pid: Pid) -> &'static str {
+    ""
+}
+
+fn is_in_graphical_session(process: &Process) -> bool {
+    process
+        .environ()
+        .iter()
+        .any(|env| env.starts_with("DISPLAY=") || env.starts_with("WAYLAND_DISPLAY="))
+}
+
+/// The name displayed in the process list, followed by the user annotation if any.
+pub fn annotated_name(name: &str, annotation: Option<&str>) -> String {
+    match annotation {
+        Some(annotation) => format!("{} ({})", name, annotation),
+        None => name.to_owned(),
     }
+}
+
+/// Returns `true` if the row gathers the processes of an executable instead of being a process.
+pub fn is_group_row<M: IsA<gtk::TreeModel>>(model: &M, iter: &gtk::TreeIter) -> bool {
+    model
+        .value(iter, GROUP_COLUMN)
+        .get::<bool>()
+        .unwrap_or(false)
+}
+
+/// Adds a row at the root for the processes with the given executable name. Its values are
+/// computed from the processes added under it by `update_group_row`.
+pub fn create_group_row(list_store: &gtk::TreeStore, name: &str) -> gtk::TreeIter {
     list_store.insert_with_values(
+        None,
         None,
         &[
-            (0, &pid),
             (1, &name),
+            (5, &name.to_lowercase()),
+            (13, &name),
+            (GROUP_COLUMN as u32, &true),
+        ],
+    )
+}
+
+/// Sums the usage of the processes under the group row.
+pub fn update_group_row(list_store: &gtk::TreeStore, iter: &gtk::TreeIter, total_memory: u64) {
+    let name = list_store
+        .value(iter, 13)
+        .get::<String>()
+        .unwrap_or_default();
+    let mut count = 0;
+    let mut cpu = 0.;
+    let mut memory = 0;
+    let mut threads = 0;
+    let mut uid = None;
+    let mut user = None;
+    let mut in_graphical_session = false;
+    if let Some(child) = list_store.iter_children(Some(iter)) {
+        loop {
+            count += 1;
+            cpu += list_store.value(&child, 6).get::<f32>().unwrap_or(0.);
+            memory += list_store.value(&child, 7).get::<u64>().unwrap_or(0);
+            threads += list_store.value(&child, 27).get::<u32>().unwrap_or(0);
+            // The processes of an executable usually all belong to the same user.
+            uid = uid.or_else(|| list_store.value(&child, 9).get::<u32>().ok());
+            user = user.or_else(|| list_store.value(&child, 20).get::<String>().ok());
+            in_graphical_session |= list_store.value(&child, 10).get::<bool>().unwrap_or(false);
+            if !list_store.iter_next(&child) {
+                break;
+            }
+        }
+    }
+    // `total_memory` is in KiB while the memory of the rows is in bytes.
+    let memory_percent = if total_memory == 0 {
+        0.
+    } else {
+        (memory as f64 * 100. / kib_to_bytes(total_memory) as f64) as f32
+    };
+    list_store.set(
+        iter,
+        &[
+            (1, &format!("{} ({})", name, count)),
             (2, &format!("{:.1}", cpu)),
             (3, &format_number(memory)),
-            (4, &String::new()),
-            (5, &name.to_lowercase()),
             (6, &cpu),
             (7, &memory),
-            (8, &0),
+            (9, &uid.unwrap_or(0)),
+            (10, &in_graphical_session),
+            (20, &user.unwrap_or_default()),
+            (21, &cpu_severity_color(cpu)),
+            (22, &memory_severity_color(memory)),
+            (23, &format!("{:.1}", memory_percent)),
+            (24, &memory_percent),
+            (27, &threads),
+        ],
+    );
+}
+
+/// Kernel threads have the `PF_KTHREAD` flag. If the flags can't be read, `kthreadd` (pid 2) and
+/// its children are considered kernel threads, as well as the processes whose name is displayed
+/// between brackets (like "[kworker/0:1]"). The processes wrongly detected can be displayed with
+/// the `displayed_kernel_threads` setting.
+fn is_kernel_thread(process: &Process) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        const PF_KTHREAD: u32 = 0x0020_0000;
+
+        if let Some(flags) = process_stat_fields(process.pid())
+            .and_then(|fields| fields.get(6).and_then(|flags| flags.parse::<u32>().ok()))
+        {
+            return flags & PF_KTHREAD != 0;
+        }
+        let kthreadd = Pid::from_u32(2);
+        if process.pid() == kthreadd || process.parent() == Some(kthreadd) {
+            return true;
+        }
+    }
+    let name = process.name();
+    name.starts_with('[') && name.ends_with(']')
+}
+
+/// Adds the process under `parent` (or at the root if `None`). Returns `None` if the process isn't
+/// displayed.
+#[allow(clippy::too_many_arguments)]
+pub fn create_and_fill_model(
+    list_store: &gtk::TreeStore,
+    parent: Option<&gtk::TreeIter>,
+    process: &Process,
+    name: &str,
+    annotation: Option<&str>,
+    pinned: bool,
+    users: &[User],
+    total_memory: u64,
+) -> Option<gtk::TreeIter> {
+    if name.is_empty() {
+        return None;
+    }
+    let cpu = process.cpu_usage();
+    let memory = kib_to_bytes(process.memory());
+    let memory_percent = memory_percent(process, total_memory);
+    let iter = list_store.insert_with_values(
+        parent,
+        None,
+        &[
+            (PID_COLUMN as u32, &process.pid().as_u32()),
+            (NAME_COLUMN as u32, &annotated_name(name, annotation)),
+            (CPU_COLUMN as u32, &format!("{:.1}", cpu)),
+            (MEMORY_COLUMN as u32, &format_number(memory)),
+            (DISK_READ_COLUMN as u32, &String::new()),
+            (NAME_SORT_COLUMN as u32, &name.to_lowercase()),
+            (CPU_SORT_COLUMN as u32, &cpu),
+            (MEMORY_SORT_COLUMN as u32, &memory),
+            (DISK_READ_SORT_COLUMN as u32, &0),
+            (UID_COLUMN as u32, &process_uid(process)),
+            (
+                GRAPHICAL_SESSION_COLUMN as u32,
+                &is_in_graphical_session(process),
+            ),
+            (LEADER_COLUMN as u32, &process_leader_status(process.pid())),
+            (LEAK_COLUMN as u32, &None::<&str>),
+            (RAW_NAME_COLUMN as u32, &name),
+            (NICE_COLUMN as u32, &process_priority(process.pid())),
+            (DISK_WRITE_COLUMN as u32, &String::new()),
+            (DISK_WRITE_SORT_COLUMN as u32, &0u64),
+            (
+                PARENT_PID_COLUMN as u32,
+                &process.parent().map_or(0, |pid| pid.as_u32()),
+            ),
+            (USER_COLUMN as u32, &process_user_name(process, users)),
+            (CPU_COLOR_COLUMN as u32, &cpu_severity_color(cpu)),
+            (MEMORY_COLOR_COLUMN as u32, &memory_severity_color(memory)),
+            (
+                MEMORY_PERCENT_COLUMN as u32,
+                &format!("{:.1}", memory_percent),
+            ),
+            (MEMORY_PERCENT_SORT_COLUMN as u32, &memory_percent),
+            (
+                START_TIME_COLUMN as u32,
+                &format_timestamp(process.start_time()),
+            ),
+            (START_TIME_SORT_COLUMN as u32, &process.start_time()),
+            (THREADS_COLUMN as u32, &process_thread_count(process)),
+            (
+                PINNED_COLUMN as u32,
+                &if pinned { Some(PINNED_ICON) } else { None },
+            ),
+            (RUN_TIME_COLUMN as u32, &format_time(process.run_time())),
+            (RUN_TIME_SORT_COLUMN as u32, &process.run_time()),
+            (KERNEL_THREAD_COLUMN as u32, &is_kernel_thread(process)),
         ],
     );
+    Some(iter)
 }
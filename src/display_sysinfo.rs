@@ -1,25 +1,31 @@
 use gtk::glib;
 use gtk::prelude::{
-    AdjustmentExt, BoxExt, ContainerExt, GridExt, LabelExt, ProgressBarExt, ScrolledWindowExt,
-    ToggleButtonExt, WidgetExt,
+    AdjustmentExt, BoxExt, ButtonExt, ContainerExt, CssProviderExt, GridExt, Inhibit, LabelExt,
+    ProgressBarExt, ScrolledWindowExt, StyleContextExt, ToggleButtonExt, WidgetExt,
+};
+use sysinfo::{
+    self, ComponentExt, DiskExt, NetworkExt, NetworksExt, ProcessExt, ProcessorExt, SystemExt,
 };
-use sysinfo::{self, ComponentExt, ProcessorExt, SystemExt};
 
-use std::cell::RefCell;
-use std::iter;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::graph::Graph;
+use crate::graph::{new_history, save_graphs_to_png, Graph, GraphColors};
 use crate::notebook::NoteBook;
 use crate::settings::Settings;
-use crate::utils::{connect_graph, format_number, RotateVec};
+use crate::utils::{
+    connect_graph, format_number, format_number_bits, graph_label_percent, graph_label_units,
+    kib_to_bytes,
+};
 
 pub fn create_header(
     label_text: &str,
     parent_layout: &gtk::Box,
     display_graph: bool,
-) -> gtk::CheckButton {
+) -> (gtk::CheckButton, gtk::Box) {
     let check_box = gtk::CheckButton::with_label("Graph view");
     check_box.set_active(display_graph);
 
@@ -40,7 +46,7 @@ pub fn create_header(
     );
     grid.set_column_homogeneous(true);
     parent_layout.pack_start(&grid, false, false, 15);
-    check_box
+    (check_box, horizontal_layout)
 }
 
 pub fn create_progress_bar(
@@ -59,29 +65,293 @@ pub fn create_progress_bar(
     p
 }
 
+// The total CPU usage bar goes from green to yellow to red depending on the load.
+const CPU_LOAD_CSS: &str = "
+progressbar.cpu-load-low progress { background-color: #73d216; background-image: none; }
+progressbar.cpu-load-medium progress { background-color: #edd400; background-image: none; }
+progressbar.cpu-load-high progress { background-color: #cc0000; background-image: none; }
+";
+const CPU_LOAD_CLASSES: [&str; 3] = ["cpu-load-low", "cpu-load-medium", "cpu-load-high"];
+
+/// `global_processor_info` is the average usage of all the cores. When
+/// `total_cpu_usage_summed` is set, it is displayed as the sum of all the cores instead (so
+/// between 0 and N * 100 %).
+fn update_total_cpu_usage(
+    bar: &gtk::ProgressBar,
+    label: &gtk::Label,
+    sys: &sysinfo::System,
+    settings: &Settings,
+) {
+    let usage = sys.global_processor_info().cpu_usage();
+    let nb_processors = sys.processors().len();
+
+    let load_class = if usage >= settings.cpu_load_high_threshold {
+        CPU_LOAD_CLASSES[2]
+    } else if usage >= settings.cpu_load_medium_threshold {
+        CPU_LOAD_CLASSES[1]
+    } else {
+        CPU_LOAD_CLASSES[0]
+    };
+    let style_context = bar.style_context();
+    for class in CPU_LOAD_CLASSES {
+        if class != load_class {
+            style_context.remove_class(class);
+        }
+    }
+    style_context.add_class(load_class);
+
+    if settings.total_cpu_usage_summed {
+        label.set_text(&format!("Total CPU usage (sum of {} cores)", nb_processors));
+        bar.set_text(Some(&format!(
+            "{:.1} % / {} %",
+            usage * nb_processors as f32,
+            nb_processors * 100
+        )));
+    } else {
+        label.set_text("Total CPU usage (average of all cores)");
+        bar.set_text(Some(&format!("{:.1} %", usage)));
+    }
+    bar.set_fraction(f64::from(usage / 100.));
+}
+
+// Duration over which the rolling average of the total CPU usage is computed.
+const CPU_AVERAGE_DURATION: Duration = Duration::from_secs(30);
+
+/// Displays the rolling average and the peak of the total CPU usage since the last reset, which
+/// is useful to quantify a stress test run.
+struct CpuLoadMeter {
+    label: gtk::Label,
+    start: Cell<Instant>,
+    peak: Cell<f32>,
+}
+
+impl CpuLoadMeter {
+    fn new(parent_layout: &gtk::Box) -> Rc<CpuLoadMeter> {
+        let layout = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        let label = gtk::Label::new(Some("Measuring..."));
+        let reset_button = gtk::Button::with_label("Reset average");
+        layout.set_margin_start(5);
+        layout.set_margin_end(5);
+        layout.pack_start(&label, true, true, 0);
+        layout.pack_start(&reset_button, false, false, 0);
+        parent_layout.add(&layout);
+
+        let meter = Rc::new(CpuLoadMeter {
+            label,
+            start: Cell::new(Instant::now()),
+            peak: Cell::new(0.),
+        });
+        reset_button.connect_clicked(glib::clone!(@weak meter => move |_| {
+            meter.start.set(Instant::now());
+            meter.peak.set(0.);
+            meter.label.set_text("Measuring...");
+        }));
+        meter
+    }
+
+    /// The average is computed from the processors usage history, only taking into account the
+    /// samples added since the last reset.
+    fn update(&self, usage: f32, history: &Graph) {
+        if usage > self.peak.get() {
+            self.peak.set(usage);
+        }
+        let since = match Instant::now().checked_sub(CPU_AVERAGE_DURATION) {
+            Some(t) => t.max(self.start.get()),
+            None => self.start.get(),
+        };
+        let nb_samples = history.data.first().map_or(0, |d| {
+            (0..d.len())
+                .take_while(|&pos| d.timestamp(pos).map_or(false, |t| t >= since))
+                .count()
+        });
+        if nb_samples == 0 {
+            self.label.set_text("Measuring...");
+            return;
+        }
+        let total: f64 = (0..nb_samples)
+            .map(|pos| history.data.iter().map(|d| d[pos]).sum::<f64>() / history.data.len() as f64)
+            .sum();
+        self.label.set_text(&format!(
+            "Average over the last {} s: {:.1} % (peak: {:.1} %)",
+            CPU_AVERAGE_DURATION.as_secs(),
+            total * 100. / nb_samples as f64,
+            self.peak.get(),
+        ));
+    }
+}
+
+/// Throughput of a network interface, computed from the difference of its totals between two
+/// updates (the networks aren't refreshed at the same rate as the rest of the system).
+struct NetworkUsage {
+    name: String,
+    label: gtk::Label,
+    total_received: u64,
+    total_transmitted: u64,
+}
+
+/// Returns the mount point and the used and total space of the disks, pseudo filesystems (which
+/// have no space) excluded.
+fn get_disks(sys: &sysinfo::System) -> Vec<(String, u64, u64)> {
+    sys.disks()
+        .iter()
+        .filter(|disk| disk.total_space() != 0)
+        .map(|disk| {
+            (
+                disk.mount_point().display().to_string(),
+                disk.total_space().saturating_sub(disk.available_space()),
+                disk.total_space(),
+            )
+        })
+        .collect()
+}
+
+// The colors of the memory breakdown parts: used, cache and buffers, free.
+const MEMORY_USED_COLOR: (f64, f64, f64) = (0.2, 0.4, 0.64);
+const MEMORY_CACHE_COLOR: (f64, f64, f64) = (0.45, 0.62, 0.81);
+const MEMORY_FREE_COLOR: (f64, f64, f64) = (0.73, 0.74, 0.71);
+
+/// Returns the parts of the RAM with their size (in KiB) and their color. The cache and the buffers
+/// are only distinguished on Linux, they're part of the used memory elsewhere.
+fn memory_breakdown(sys: &sysinfo::System) -> Vec<(&'static str, u64, (f64, f64, f64))> {
+    let total = sys.total_memory();
+    let used = sys.used_memory().min(total);
+    if cfg!(target_os = "linux") {
+        // The used memory doesn't include the cache and the buffers, which can be freed when
+        // needed.
+        let free = sys.free_memory().min(total - used);
+        vec![
+            ("used", used, MEMORY_USED_COLOR),
+            ("cache and buffers", total - used - free, MEMORY_CACHE_COLOR),
+            ("free", free, MEMORY_FREE_COLOR),
+        ]
+    } else {
+        vec![
+            ("used", used, MEMORY_USED_COLOR),
+            ("free", total - used, MEMORY_FREE_COLOR),
+        ]
+    }
+}
+
+/// Creates a bar split in parts, each one drawn with its color. They're given as fractions of
+/// the bar.
+fn create_segmented_bar(segments: &Rc<RefCell<Vec<(f64, (f64, f64, f64))>>>) -> gtk::DrawingArea {
+    let area = gtk::DrawingArea::new();
+    area.set_size_request(-1, 8);
+    area.set_margin_top(2);
+    area.connect_draw(
+        glib::clone!(@weak segments => @default-return Inhibit(false), move |area, c| {
+            let width = f64::from(area.allocated_width());
+            let height = f64::from(area.allocated_height());
+            let mut x = 0.;
+            for (fraction, (r, g, b)) in segments.borrow().iter() {
+                let segment_width = fraction * width;
+                c.set_source_rgb(*r, *g, *b);
+                c.rectangle(x, 0., segment_width, height);
+                let _ = c.fill();
+                x += segment_width;
+            }
+            Inhibit(false)
+        }),
+    );
+    area
+}
+
+struct BatteryUsage {
+    battery: battery::Battery,
+    // Charge level.
+    bar: gtk::ProgressBar,
+    // State and time remaining until it's full or empty.
+    state: gtk::Label,
+}
+
+fn format_battery_time(seconds: f32) -> String {
+    let minutes = (seconds / 60.) as u64;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// Returns the processors' brand with the number of cores, like "Intel(R) Core(TM) i7-8550U CPU @
+/// 1.80GHz (4 physical / 8 logical cores)".
+fn processors_description(sys: &sysinfo::System) -> String {
+    let logical = sys.processors().len();
+    let cores = match sys.physical_core_count() {
+        Some(physical) => format!("{} physical / {} logical cores", physical, logical),
+        None => format!("{} logical cores", logical),
+    };
+    // The brand is the same for all the processors. It isn't always available.
+    match sys.processors().first().map(|p| p.brand().trim()) {
+        Some(brand) if !brand.is_empty() => format!("{} ({})", brand, cores),
+        _ => cores,
+    }
+}
+
+/// Returns the number of bytes read and written by all the processes since they started.
+fn get_disk_io_totals(sys: &sysinfo::System) -> (u64, u64) {
+    sys.processes()
+        .values()
+        .map(|process| process.disk_usage())
+        .fold((0, 0), |(read, written), usage| {
+            (
+                read + usage.total_read_bytes,
+                written + usage.total_written_bytes,
+            )
+        })
+}
+
 #[allow(dead_code)]
 pub struct DisplaySysInfo {
     procs: Rc<RefCell<Vec<gtk::ProgressBar>>>,
+    total_cpu_label: gtk::Label,
+    // Load average isn't available on Windows.
+    #[cfg(not(windows))]
+    load_average: gtk::Label,
+    cpu_load_meter: Rc<CpuLoadMeter>,
     ram: gtk::ProgressBar,
+    // The used, cache and free parts of the RAM.
+    memory_breakdown: gtk::DrawingArea,
+    memory_segments: Rc<RefCell<Vec<(f64, (f64, f64, f64))>>>,
     swap: gtk::ProgressBar,
     vertical_layout: gtk::Box,
     components: Vec<gtk::Label>,
     cpu_usage_history: Rc<RefCell<Graph>>,
+    cpu_frequency_history: Rc<RefCell<Graph>>,
     // 0 = RAM
     // 1 = SWAP
     ram_usage_history: Rc<RefCell<Graph>>,
+    swap_usage_history: Rc<RefCell<Graph>>,
     temperature_usage_history: Rc<RefCell<Graph>>,
+    // Unit of the values in `temperature_usage_history`.
+    temperature_in_fahrenheit: bool,
+    // For each interface: 2 * i = received, 2 * i + 1 = transmitted
+    network_usage_history: Rc<RefCell<Graph>>,
+    networks: Vec<NetworkUsage>,
+    networks_last_update: Instant,
+    // 0 = read
+    // 1 = written
+    disk_io_history: Rc<RefCell<Graph>>,
+    disk_io_label: gtk::Label,
+    // Bytes read and written by all the processes at the last update.
+    disk_io_totals: (u64, u64),
+    disk_io_last_update: Instant,
+    // Both are empty if there is no battery.
+    battery_manager: Option<battery::Manager>,
+    batteries: Vec<BatteryUsage>,
+    disks_layout: gtk::Grid,
+    // Mount point of each disk with its bar.
+    disks: Vec<(String, gtk::ProgressBar)>,
     pub ram_check_box: gtk::CheckButton,
     pub swap_check_box: gtk::CheckButton,
     pub temperature_check_box: Option<gtk::CheckButton>,
+    pub network_check_box: Option<gtk::CheckButton>,
+    pub disk_io_check_box: gtk::CheckButton,
 }
 
 impl DisplaySysInfo {
     pub fn new(
         sys: &Arc<Mutex<sysinfo::System>>,
         note: &mut NoteBook,
-        settings: &Settings,
+        settings: &Rc<RefCell<Settings>>,
     ) -> DisplaySysInfo {
+        let bsettings = &*settings.borrow();
         let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
         let mut procs = Vec::new();
         let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
@@ -89,65 +359,64 @@ impl DisplaySysInfo {
 
         // CPU
         let mut cpu_usage_history = Graph::new(None, false);
-        cpu_usage_history.set_label_callbacks(Some(Box::new(|_| {
-            [
-                "100".to_string(),
-                "50".to_string(),
-                "0".to_string(),
-                "%".to_string(),
-            ]
-        })));
+        cpu_usage_history.set_label_callbacks(Some(Box::new(graph_label_percent)));
 
-        let sys = sys.lock().expect("failed to lock in DisplaySysInfo::new");
-        // RAM
-        let mut ram_usage_history = Graph::new(Some(sys.total_memory() as f64), true);
-        ram_usage_history.set_label_callbacks(Some(Box::new(|v| {
-            if v < 100_000. {
+        // CPU FREQUENCY (in MHz)
+        let mut cpu_frequency_history = Graph::new(Some(1.), false);
+        cpu_frequency_history.set_overhead(Some(10.));
+        cpu_frequency_history.set_label_callbacks(Some(Box::new(|v| {
+            if v < 1_000. {
                 [
-                    v.to_string(),
-                    format!("{}", v / 2.),
+                    format!("{:.0}", v),
+                    format!("{:.0}", v / 2.),
                     "0".to_string(),
-                    "kB".to_string(),
-                ]
-            } else if v < 10_000_000. {
-                [
-                    format!("{:.1}", v / 1_000f64),
-                    format!("{:.1}", v / 2_000f64),
-                    "0".to_string(),
-                    "MB".to_string(),
-                ]
-            } else if v < 10_000_000_000. {
-                [
-                    format!("{:.1}", v / 1_000_000f64),
-                    format!("{:.1}", v / 2_000_000f64),
-                    "0".to_string(),
-                    "GB".to_string(),
+                    "MHz".to_string(),
                 ]
             } else {
                 [
-                    format!("{:.1}", v / 1_000_000_000f64),
-                    format!("{:.1}", v / 2_000_000_000f64),
+                    format!("{:.1}", v / 1_000.),
+                    format!("{:.1}", v / 2_000.),
                     "0".to_string(),
-                    "TB".to_string(),
+                    "GHz".to_string(),
                 ]
             }
         })));
+        cpu_frequency_history.set_labels_width(70);
+
+        let sys = sys.lock().expect("failed to lock in DisplaySysInfo::new");
+        // RAM
+        let mut ram_usage_history = Graph::new(Some(sys.total_memory() as f64), true);
+        ram_usage_history.set_label_callbacks(Some(Box::new(|v| {
+            graph_label_units(kib_to_bytes(v as u64) as f64)
+        })));
         ram_usage_history.set_labels_width(70);
+        // SWAP, with its own scale so its usage is readable even when there is a lot of RAM.
+        let mut swap_usage_history = Graph::new(Some(sys.total_swap().max(1) as f64), true);
+        swap_usage_history.set_label_callbacks(Some(Box::new(|v| {
+            graph_label_units(kib_to_bytes(v as u64) as f64)
+        })));
+        swap_usage_history.set_labels_width(70);
 
         // TEMPERATURE
         let mut temperature_usage_history = Graph::new(Some(1.), false);
         temperature_usage_history.set_overhead(Some(20.));
-        temperature_usage_history.set_label_callbacks(Some(Box::new(|v| {
-            [
-                format!("{:.1}", v),
-                format!("{:.1}", v / 2.),
-                "0".to_string(),
-                "°C".to_string(),
-            ]
-        })));
+        // The values are converted to Fahrenheit when it's needed by `update_system_info`.
+        temperature_usage_history.set_label_callbacks(Some(temperature_graph_label(false)));
         temperature_usage_history.set_labels_width(70);
 
+        // NETWORK
+        let mut network_usage_history = Graph::new(Some(1.), false);
+        network_usage_history.set_label_callbacks(Some(Box::new(graph_label_units)));
+        network_usage_history.set_labels_width(70);
+
+        // DISK I/O
+        let mut disk_io_history = Graph::new(Some(1.), false);
+        disk_io_history.set_label_callbacks(Some(Box::new(graph_label_units)));
+        disk_io_history.set_labels_width(70);
+
         let mut check_box3 = None;
+        let mut check_box4 = None;
+        let mut networks = Vec::new();
 
         vertical_layout.set_spacing(5);
         vertical_layout.set_margin_top(10);
@@ -160,11 +429,39 @@ impl DisplaySysInfo {
         non_graph_layout2.set_column_homogeneous(true);
         non_graph_layout2.set_margin_start(5);
         let non_graph_layout3 = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let non_graph_layout4 = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let non_graph_layout5 = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+        //
+        // LOAD AVERAGE PART
+        //
+        #[cfg(not(windows))]
+        let load_average = {
+            let horizontal_layout = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+            let load_average = gtk::Label::new(None);
+            horizontal_layout.pack_start(
+                &gtk::Label::new(Some("Load average (1, 5, 15 minutes)")),
+                true,
+                false,
+                0,
+            );
+            horizontal_layout.pack_start(&load_average, true, false, 0);
+            horizontal_layout.set_homogeneous(true);
+            vertical_layout.add(&horizontal_layout);
+            load_average
+        };
 
         //
         // PROCESSOR PART
         //
-        vertical_layout.pack_start(&gtk::Label::new(Some("Total CPU usage")), false, false, 7);
+        vertical_layout.pack_start(
+            &gtk::Label::new(Some(&processors_description(&sys))),
+            false,
+            false,
+            7,
+        );
+        let total_cpu_label = gtk::Label::new(None);
+        vertical_layout.pack_start(&total_cpu_label, false, false, 7);
         procs.push(gtk::ProgressBar::new());
         {
             procs.push(gtk::ProgressBar::new());
@@ -173,12 +470,28 @@ impl DisplaySysInfo {
             p.set_margin_end(5);
             p.set_margin_start(5);
             p.set_show_text(true);
-            let processor = sys.global_processor_info();
-            p.set_text(Some(&format!("{:.1} %", processor.cpu_usage())));
-            p.set_fraction(f64::from(processor.cpu_usage() / 100.));
+            let provider = gtk::CssProvider::new();
+            provider
+                .load_from_data(CPU_LOAD_CSS.as_bytes())
+                .expect("failed to load CPU load CSS");
+            p.style_context()
+                .add_provider(&provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+            update_total_cpu_usage(p, &total_cpu_label, &sys, bsettings);
             vertical_layout.add(p);
         }
-        let check_box = create_header("Processors usage", &vertical_layout, settings.display_graph);
+        let cpu_load_meter = CpuLoadMeter::new(&vertical_layout);
+        let (check_box, cpu_header) = create_header(
+            "Processors usage",
+            &vertical_layout,
+            bsettings.display_cpu_graph,
+        );
+        // Only meaningful in graph view.
+        let stacked_check_box = gtk::CheckButton::with_label("Stacked");
+        stacked_check_box.set_active(bsettings.stacked_cpu_graph);
+        stacked_check_box.set_sensitive(bsettings.display_cpu_graph);
+        stacked_check_box.set_margin_end(5);
+        cpu_header.pack_start(&stacked_check_box, false, false, 0);
+        cpu_usage_history.set_stacked(bsettings.stacked_cpu_graph);
         for (i, pro) in sys.processors().iter().enumerate() {
             procs.push(gtk::ProgressBar::new());
             let p: &gtk::ProgressBar = &procs[i + 1];
@@ -189,48 +502,58 @@ impl DisplaySysInfo {
             p.set_fraction(f64::from(pro.cpu_usage()));
             non_graph_layout.attach(&l, 0, i as i32 - 1, 1, 1);
             non_graph_layout.attach(p, 1, i as i32 - 1, 11, 1);
-            cpu_usage_history.push(
-                RotateVec::new(iter::repeat(0f64).take(61).collect()),
-                &format!("processor {}", i),
-                None,
-            );
+            cpu_usage_history.push(new_history(), &format!("processor {}", i), None);
+            cpu_frequency_history.push(new_history(), &format!("processor {}", i), None);
         }
         vertical_layout.add(&non_graph_layout);
+        // There can be a lot of processors so their names are displayed in columns below.
+        cpu_usage_history.set_display_labels(false);
+        cpu_usage_history.set_display_legend(true);
         cpu_usage_history.attach_to(&vertical_layout);
+        let frequency_check_box = gtk::CheckButton::with_label("Display the processors frequency");
+        frequency_check_box.set_active(bsettings.display_cpu_frequency_graph);
+        frequency_check_box.set_margin_start(5);
+        vertical_layout.add(&frequency_check_box);
+        cpu_frequency_history.set_display_labels(false);
+        cpu_frequency_history.set_display_legend(true);
+        cpu_frequency_history.attach_to(&vertical_layout);
 
         //
         // MEMORY PART
         //
-        let check_box2 = create_header("Memory usage", &vertical_layout, settings.display_graph);
+        let (check_box2, _) = create_header(
+            "Memory usage",
+            &vertical_layout,
+            bsettings.display_memory_graph,
+        );
         let ram = create_progress_bar(&non_graph_layout2, 0, "RAM", "");
-        let swap = create_progress_bar(&non_graph_layout2, 1, "Swap", "");
+        let memory_segments = Rc::new(RefCell::new(Vec::new()));
+        let memory_breakdown = create_segmented_bar(&memory_segments);
+        non_graph_layout2.attach(&memory_breakdown, 1, 1, 11, 1);
+        let swap = create_progress_bar(&non_graph_layout2, 2, "Swap", "");
         vertical_layout.pack_start(&non_graph_layout2, false, false, 15);
         //vertical_layout.add(&non_graph_layout2);
-        ram_usage_history.push(
-            RotateVec::new(iter::repeat(0f64).take(61).collect()),
-            "RAM",
-            Some(4),
-        );
-        ram_usage_history.push(
-            RotateVec::new(iter::repeat(0f64).take(61).collect()),
-            "Swap",
-            Some(2),
-        );
+        ram_usage_history.push(new_history(), "RAM", Some(4));
         ram_usage_history.attach_to(&vertical_layout);
+        swap_usage_history.push(new_history(), "Swap", Some(2));
+        swap_usage_history.attach_to(&vertical_layout);
 
         //
         // TEMPERATURES PART
         //
         if !sys.components().is_empty() {
-            check_box3 = Some(create_header(
+            let (check_box, _) = create_header(
                 "Components' temperature",
                 &vertical_layout,
-                settings.display_graph,
-            ));
-            for component in sys.components() {
+                bsettings.display_temperature_graph,
+            );
+            check_box3 = Some(check_box);
+            for (pos, component) in sys.components().iter().enumerate() {
                 let horizontal_layout = gtk::Box::new(gtk::Orientation::Horizontal, 10);
-                // TODO: add max and critical temperatures as well
-                let temp = gtk::Label::new(Some(&format!("{:.1} °C", component.temperature())));
+                let temp = gtk::Label::new(Some(&format_temperature(
+                    component,
+                    bsettings.display_fahrenheit,
+                )));
                 horizontal_layout.pack_start(
                     &gtk::Label::new(Some(component.label())),
                     true,
@@ -241,22 +564,111 @@ impl DisplaySysInfo {
                 horizontal_layout.set_homogeneous(true);
                 non_graph_layout3.add(&horizontal_layout);
                 components.push(temp);
-                temperature_usage_history.push(
-                    RotateVec::new(iter::repeat(0f64).take(61).collect()),
-                    component.label(),
-                    None,
-                );
+                temperature_usage_history.push(new_history(), component.label(), None);
+                if let Some(critical) = component.critical() {
+                    temperature_usage_history.add_reference_line(pos, f64::from(critical));
+                }
             }
             vertical_layout.add(&non_graph_layout3);
             temperature_usage_history.attach_to(&vertical_layout);
         }
 
+        //
+        // NETWORK PART
+        //
+        if sys.networks().iter().next().is_some() {
+            let (check_box, _) = create_header(
+                "Network usage",
+                &vertical_layout,
+                bsettings.display_network_graph,
+            );
+            check_box4 = Some(check_box);
+            for (name, network) in sys.networks().iter() {
+                let horizontal_layout = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+                let label = gtk::Label::new(None);
+                horizontal_layout.pack_start(&gtk::Label::new(Some(name.as_str())), true, false, 0);
+                horizontal_layout.pack_start(&label, true, false, 0);
+                horizontal_layout.set_homogeneous(true);
+                non_graph_layout4.add(&horizontal_layout);
+                network_usage_history.push(new_history(), &format!("{} received", name), None);
+                network_usage_history.push(new_history(), &format!("{} transmitted", name), None);
+                networks.push(NetworkUsage {
+                    name: name.clone(),
+                    label,
+                    total_received: network.total_received(),
+                    total_transmitted: network.total_transmitted(),
+                });
+            }
+            vertical_layout.add(&non_graph_layout4);
+            network_usage_history.attach_to(&vertical_layout);
+        }
+
+        //
+        // DISK I/O PART
+        //
+        let (check_box5, _) = create_header(
+            "Disk I/O",
+            &vertical_layout,
+            bsettings.display_disk_io_graph,
+        );
+        let disk_io_label = gtk::Label::new(None);
+        non_graph_layout5.add(&disk_io_label);
+        disk_io_history.push(new_history(), "read", None);
+        disk_io_history.push(new_history(), "written", None);
+        vertical_layout.add(&non_graph_layout5);
+        disk_io_history.attach_to(&vertical_layout);
+
+        //
+        // BATTERY PART
+        //
+        let battery_manager = battery::Manager::new().ok();
+        let mut batteries = Vec::new();
+        let found_batteries = battery_manager
+            .as_ref()
+            .and_then(|manager| manager.batteries().ok())
+            .map(|found| found.filter_map(|battery| battery.ok()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        // The section isn't displayed at all without battery.
+        if !found_batteries.is_empty() {
+            vertical_layout.pack_start(&gtk::Label::new(Some("Battery")), false, false, 15);
+            let battery_layout = gtk::Grid::new();
+            battery_layout.set_column_homogeneous(true);
+            battery_layout.set_margin_start(5);
+            battery_layout.set_margin_end(5);
+            for (pos, battery) in found_batteries.into_iter().enumerate() {
+                let line = pos as i32 * 2;
+                let bar = create_progress_bar(&battery_layout, line, &pos.to_string(), "");
+                let state = gtk::Label::new(None);
+                battery_layout.attach(&state, 1, line + 1, 11, 1);
+                batteries.push(BatteryUsage {
+                    battery,
+                    bar,
+                    state,
+                });
+            }
+            vertical_layout.add(&battery_layout);
+        }
+
+        //
+        // DISKS PART
+        //
+        vertical_layout.pack_start(&gtk::Label::new(Some("Disks usage")), false, false, 15);
+        let disks_layout = gtk::Grid::new();
+        disks_layout.set_column_homogeneous(true);
+        disks_layout.set_margin_start(5);
+        disks_layout.set_margin_end(5);
+        vertical_layout.add(&disks_layout);
+
         //
         // Putting everyting into places now.
         //
         let cpu_usage_history = connect_graph(cpu_usage_history);
+        let cpu_frequency_history = connect_graph(cpu_frequency_history);
         let ram_usage_history = connect_graph(ram_usage_history);
+        let swap_usage_history = connect_graph(swap_usage_history);
         let temperature_usage_history = connect_graph(temperature_usage_history);
+        let network_usage_history = connect_graph(network_usage_history);
+        let disk_io_history = connect_graph(disk_io_history);
 
         scroll.add(&vertical_layout);
         note.create_tab("System usage", &scroll);
@@ -264,55 +676,135 @@ impl DisplaySysInfo {
         // It greatly improves the scrolling on the system information tab. No more clipping.
         let adjustment = scroll.vadjustment();
         adjustment.connect_value_changed(
-            glib::clone!(@weak cpu_usage_history, @weak ram_usage_history, @weak temperature_usage_history => move |_| {
-            cpu_usage_history.borrow().invalidate();
-            ram_usage_history.borrow().invalidate();
-            temperature_usage_history.borrow().invalidate();
-        }));
+            glib::clone!(@weak cpu_usage_history, @weak ram_usage_history, @weak swap_usage_history,
+                             @weak temperature_usage_history, @weak disk_io_history,
+                             @weak network_usage_history, @weak cpu_frequency_history => move |_| {
+                cpu_usage_history.borrow().invalidate();
+                cpu_frequency_history.borrow().invalidate();
+                ram_usage_history.borrow().invalidate();
+                swap_usage_history.borrow().invalidate();
+                temperature_usage_history.borrow().invalidate();
+                network_usage_history.borrow().invalidate();
+                disk_io_history.borrow().invalidate();
+            }),
+        );
 
         let mut tmp = DisplaySysInfo {
             procs: Rc::new(RefCell::new(procs)),
+            total_cpu_label,
+            #[cfg(not(windows))]
+            load_average,
+            cpu_load_meter,
             ram,
+            memory_breakdown,
+            memory_segments,
             swap,
             vertical_layout,
             components,
             cpu_usage_history: Rc::clone(&cpu_usage_history),
+            cpu_frequency_history: Rc::clone(&cpu_frequency_history),
             ram_usage_history: Rc::clone(&ram_usage_history),
+            swap_usage_history: Rc::clone(&swap_usage_history),
             ram_check_box: check_box.clone(),
             swap_check_box: check_box2.clone(),
             temperature_usage_history: Rc::clone(&temperature_usage_history),
+            temperature_in_fahrenheit: false,
             temperature_check_box: check_box3.clone(),
+            network_usage_history: Rc::clone(&network_usage_history),
+            networks,
+            networks_last_update: Instant::now(),
+            network_check_box: check_box4.clone(),
+            disk_io_history: Rc::clone(&disk_io_history),
+            disk_io_label,
+            disk_io_totals: get_disk_io_totals(&sys),
+            disk_io_last_update: Instant::now(),
+            disk_io_check_box: check_box5.clone(),
+            battery_manager,
+            batteries,
+            disks_layout,
+            disks: Vec::new(),
         };
-        tmp.update_system_info(&sys, settings.display_fahrenheit);
+        tmp.update_system_info(&sys, bsettings.display_fahrenheit);
+        tmp.apply_graph_colors(&bsettings.graph_colors);
 
         check_box.connect_toggled(
-            glib::clone!(@weak non_graph_layout, @weak cpu_usage_history => move |c| {
+            glib::clone!(@weak non_graph_layout, @weak cpu_usage_history, @weak stacked_check_box,
+                         @weak settings => move |c| {
                 show_if_necessary(c, &cpu_usage_history.borrow(), &non_graph_layout);
+                stacked_check_box.set_sensitive(c.is_active());
+                settings.borrow_mut().display_cpu_graph = c.is_active();
+                settings.borrow().save();
+            }),
+        );
+        stacked_check_box.connect_toggled(
+            glib::clone!(@weak cpu_usage_history, @weak settings => move |c| {
+                cpu_usage_history.borrow_mut().set_stacked(c.is_active());
+                settings.borrow_mut().stacked_cpu_graph = c.is_active();
+                settings.borrow().save();
+            }),
+        );
+        frequency_check_box.connect_toggled(
+            glib::clone!(@weak cpu_frequency_history, @weak settings => move |c| {
+                show_frequency_graph(c, &cpu_frequency_history.borrow());
+                settings.borrow_mut().display_cpu_frequency_graph = c.is_active();
+                settings.borrow().save();
             }),
         );
         check_box2.connect_toggled(
-            glib::clone!(@weak non_graph_layout2, @weak ram_usage_history => move |c| {
+            glib::clone!(@weak non_graph_layout2, @weak ram_usage_history, @weak swap_usage_history,
+                         @weak settings => move |c| {
                 show_if_necessary(c, &ram_usage_history.borrow(), &non_graph_layout2);
+                show_if_necessary(c, &swap_usage_history.borrow(), &non_graph_layout2);
+                settings.borrow_mut().display_memory_graph = c.is_active();
+                settings.borrow().save();
             }),
         );
         if let Some(ref check_box3) = check_box3 {
             check_box3.connect_toggled(
-                glib::clone!(@weak non_graph_layout3, @weak temperature_usage_history => move |c| {
+                glib::clone!(@weak non_graph_layout3, @weak temperature_usage_history, @weak settings => move |c| {
                     show_if_necessary(c, &temperature_usage_history.borrow(), &non_graph_layout3);
+                    settings.borrow_mut().display_temperature_graph = c.is_active();
+                    settings.borrow().save();
                 }),
             );
         }
+        if let Some(ref check_box4) = check_box4 {
+            check_box4.connect_toggled(
+                glib::clone!(@weak non_graph_layout4, @weak network_usage_history, @weak settings => move |c| {
+                    show_if_necessary(c, &network_usage_history.borrow(), &non_graph_layout4);
+                    settings.borrow_mut().display_network_graph = c.is_active();
+                    settings.borrow().save();
+                }),
+            );
+        }
+
+        check_box5.connect_toggled(
+            glib::clone!(@weak non_graph_layout5, @weak disk_io_history, @weak settings => move |c| {
+                show_if_necessary(c, &disk_io_history.borrow(), &non_graph_layout5);
+                settings.borrow_mut().display_disk_io_graph = c.is_active();
+                settings.borrow().save();
+            }),
+        );
 
         scroll.connect_show(
-            glib::clone!(@weak cpu_usage_history, @weak ram_usage_history => move |_| {
+            glib::clone!(@weak cpu_usage_history, @weak ram_usage_history, @weak swap_usage_history,
+                         @weak cpu_frequency_history, @weak disk_io_history => move |_| {
                 show_if_necessary(&check_box,
                                   &cpu_usage_history.borrow(), &non_graph_layout);
+                show_frequency_graph(&frequency_check_box, &cpu_frequency_history.borrow());
                 show_if_necessary(&check_box2,
                                   &ram_usage_history.borrow(), &non_graph_layout2);
+                show_if_necessary(&check_box2,
+                                  &swap_usage_history.borrow(), &non_graph_layout2);
                 if let Some(ref check_box3) = check_box3 {
                     show_if_necessary(check_box3,
                                       &temperature_usage_history.borrow(), &non_graph_layout3);
                 }
+                if let Some(ref check_box4) = check_box4 {
+                    show_if_necessary(check_box4,
+                                      &network_usage_history.borrow(), &non_graph_layout4);
+                }
+                show_if_necessary(&check_box5, &disk_io_history.borrow(), &non_graph_layout5);
             }),
         );
         tmp
@@ -323,14 +815,74 @@ impl DisplaySysInfo {
             .borrow()
             .area
             .set_size_request(width, height);
+        self.cpu_frequency_history
+            .borrow()
+            .area
+            .set_size_request(width, height);
         self.ram_usage_history
             .borrow()
             .area
             .set_size_request(width, height);
+        self.swap_usage_history
+            .borrow()
+            .area
+            .set_size_request(width, height);
         self.temperature_usage_history
             .borrow()
             .area
             .set_size_request(width, height);
+        self.network_usage_history
+            .borrow()
+            .area
+            .set_size_request(width, height);
+        self.disk_io_history
+            .borrow()
+            .area
+            .set_size_request(width, height);
+    }
+
+    /// Applies the colors chosen by the user to the CPU and memory graphs.
+    pub fn apply_graph_colors(&self, colors: &GraphColors) {
+        let mut cpu = self.cpu_usage_history.borrow_mut();
+        for index in 0..cpu.series_count() {
+            apply_series_color(&mut cpu, index, colors.cpu);
+        }
+        apply_series_color(&mut self.ram_usage_history.borrow_mut(), 0, colors.ram);
+        apply_series_color(&mut self.swap_usage_history.borrow_mut(), 0, colors.swap);
+    }
+
+    /// Returns the colors currently used by the CPU and memory graphs.
+    pub fn graph_colors(&self) -> GraphColors {
+        GraphColors {
+            cpu: self.cpu_usage_history.borrow().series_color(0),
+            ram: self.ram_usage_history.borrow().series_color(0),
+            swap: self.swap_usage_history.borrow().series_color(0),
+        }
+    }
+
+    /// Saves all the graphs of the tab into one PNG file.
+    pub fn save_graphs(&self, path: &Path) -> Result<(), String> {
+        let cpu = self.cpu_usage_history.borrow();
+        let cpu_frequency = self.cpu_frequency_history.borrow();
+        let ram = self.ram_usage_history.borrow();
+        let swap = self.swap_usage_history.borrow();
+        let temperature = self.temperature_usage_history.borrow();
+        let network = self.network_usage_history.borrow();
+        let disk_io = self.disk_io_history.borrow();
+        let mut graphs = vec![
+            ("Processors usage", &*cpu),
+            ("Processors frequency", &*cpu_frequency),
+            ("Memory usage", &*ram),
+            ("Swap usage", &*swap),
+        ];
+        if self.temperature_check_box.is_some() {
+            graphs.push(("Components' temperature", &*temperature));
+        }
+        if self.network_check_box.is_some() {
+            graphs.push(("Network usage", &*network));
+        }
+        graphs.push(("Disk I/O", &*disk_io));
+        save_graphs_to_png(&graphs, path)
     }
 
     pub fn set_checkboxes_state(&self, active: bool) {
@@ -339,14 +891,19 @@ impl DisplaySysInfo {
         if let Some(ref temperature_check_box) = self.temperature_check_box {
             temperature_check_box.set_active(active);
         }
+        if let Some(ref network_check_box) = self.network_check_box {
+            network_check_box.set_active(active);
+        }
+        self.disk_io_check_box.set_active(active);
     }
 
-    pub fn update_system_info(&mut self, sys: &sysinfo::System, display_fahrenheit: bool) {
+    /// Updates the texts and the fractions of the RAM and swap bars.
+    fn update_memory(&self, sys: &sysinfo::System) {
         let disp = |total, used| {
             format!(
                 "{} / {}",
-                format_number(used * 1_000),
-                format_number(total * 1_000) // We need to multiply to get the "right" unit.
+                format_number(kib_to_bytes(used)),
+                format_number(kib_to_bytes(total))
             )
         };
 
@@ -358,17 +915,28 @@ impl DisplaySysInfo {
         } else {
             self.ram.set_fraction(0.0);
         }
-        {
-            let mut r = self.ram_usage_history.borrow_mut();
-            r.data[0].move_start();
-            if let Some(p) = r.data[0].get_mut(0) {
-                *p = used as f64;
-            }
-        }
+        let breakdown = memory_breakdown(sys);
+        *self.memory_segments.borrow_mut() = breakdown
+            .iter()
+            .filter(|_| total_ram != 0)
+            .map(|(_, size, color)| (*size as f64 / total_ram as f64, *color))
+            .collect();
+        let mut tooltip = breakdown
+            .iter()
+            .map(|(name, size, _)| format!("{}: {}", name, format_number(kib_to_bytes(*size))))
+            .collect::<Vec<_>>();
+        tooltip.push(format!(
+            "available: {}",
+            format_number(kib_to_bytes(sys.available_memory()))
+        ));
+        let tooltip = tooltip.join("\n");
+        self.ram.set_tooltip_text(Some(&tooltip));
+        self.memory_breakdown.set_tooltip_text(Some(&tooltip));
+        self.memory_breakdown.queue_draw();
 
-        let total = ::std::cmp::max(sys.total_swap(), total_ram);
+        let total = sys.total_swap();
         let used = sys.used_swap();
-        self.swap.set_text(Some(&disp(sys.total_swap(), used)));
+        self.swap.set_text(Some(&disp(total, used)));
 
         let mut fraction = if total != 0 {
             used as f64 / total as f64
@@ -379,16 +947,54 @@ impl DisplaySysInfo {
             fraction = 0f64;
         }
         self.swap.set_fraction(fraction);
+    }
+
+    /// Updates all the sizes displayed as text, for example after the units were changed.
+    pub fn update_sizes(&mut self, sys: &sysinfo::System) {
+        self.update_memory(sys);
+        self.update_disks(sys);
+    }
+
+    pub fn update_system_info(&mut self, sys: &sysinfo::System, display_fahrenheit: bool) {
+        self.update_sizes(sys);
         {
             let mut r = self.ram_usage_history.borrow_mut();
-            r.data[1].move_start();
-            if let Some(p) = r.data[1].get_mut(0) {
-                *p = used as f64;
+            r.data[0].move_start();
+            if let Some(p) = r.data[0].get_mut(0) {
+                *p = sys.used_memory() as f64;
             }
         }
+        {
+            let mut r = self.swap_usage_history.borrow_mut();
+            r.data[0].move_start();
+            if let Some(p) = r.data[0].get_mut(0) {
+                *p = sys.used_swap() as f64;
+            }
+        }
+
+        #[cfg(not(windows))]
+        {
+            let load_average = sys.load_average();
+            self.load_average.set_text(&format!(
+                "{:.2}, {:.2}, {:.2}",
+                load_average.one, load_average.five, load_average.fifteen
+            ));
+        }
+        self.update_networks(sys);
+        self.update_disk_io(sys);
+        self.update_batteries();
 
         // temperature part
         let mut t = self.temperature_usage_history.borrow_mut();
+        if self.temperature_in_fahrenheit != display_fahrenheit {
+            if display_fahrenheit {
+                t.convert_values(celsius_to_fahrenheit);
+            } else {
+                t.convert_values(|v| (v - 32.) / 1.8);
+            }
+            t.set_label_callbacks(Some(temperature_graph_label(display_fahrenheit)));
+            self.temperature_in_fahrenheit = display_fahrenheit;
+        }
         for (pos, (component, label)) in sys
             .components()
             .iter()
@@ -397,29 +1003,156 @@ impl DisplaySysInfo {
         {
             t.data[pos].move_start();
             if let Some(t) = t.data[pos].get_mut(0) {
-                *t = f64::from(component.temperature());
+                *t = if display_fahrenheit {
+                    celsius_to_fahrenheit(f64::from(component.temperature()))
+                } else {
+                    f64::from(component.temperature())
+                };
             }
-            if let Some(t) = t.data[pos].get_mut(0) {
-                *t = f64::from(component.temperature());
+            label.set_text(&format_temperature(component, display_fahrenheit));
+        }
+    }
+
+    fn update_disks(&mut self, sys: &sysinfo::System) {
+        let disks = get_disks(sys);
+
+        // The bars are created again if a disk was mounted or unmounted since the last update.
+        if disks.len() != self.disks.len()
+            || disks
+                .iter()
+                .zip(self.disks.iter())
+                .any(|((mount_point, _, _), (known, _))| mount_point != known)
+        {
+            for child in self.disks_layout.children() {
+                self.disks_layout.remove(&child);
             }
-            if display_fahrenheit {
-                label.set_text(&format!("{:.1} °F", component.temperature() * 1.8 + 32.));
-            } else {
-                label.set_text(&format!("{:.1} °C", component.temperature()));
+            self.disks = disks
+                .iter()
+                .enumerate()
+                .map(|(line, (mount_point, _, _))| {
+                    let bar = create_progress_bar(&self.disks_layout, line as i32, mount_point, "");
+                    (mount_point.clone(), bar)
+                })
+                .collect();
+            self.disks_layout.show_all();
+        }
+
+        for ((_, used, total), (_, bar)) in disks.iter().zip(self.disks.iter()) {
+            bar.set_text(Some(&format!(
+                "{} / {}",
+                format_number(*used),
+                format_number(*total)
+            )));
+            bar.set_fraction(*used as f64 / *total as f64);
+        }
+    }
+
+    fn update_networks(&mut self, sys: &sysinfo::System) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.networks_last_update).as_secs_f64();
+        if elapsed <= 0. {
+            return;
+        }
+        self.networks_last_update = now;
+
+        let mut h = self.network_usage_history.borrow_mut();
+        for (pos, usage) in self.networks.iter_mut().enumerate() {
+            // Interfaces removed since the tab was created are displayed as idle.
+            let (total_received, total_transmitted) = sys
+                .networks()
+                .iter()
+                .find(|(name, _)| **name == usage.name)
+                .map_or(
+                    (usage.total_received, usage.total_transmitted),
+                    |(_, network)| (network.total_received(), network.total_transmitted()),
+                );
+            let received =
+                (total_received.saturating_sub(usage.total_received) as f64 / elapsed) as u64;
+            let transmitted =
+                (total_transmitted.saturating_sub(usage.total_transmitted) as f64 / elapsed) as u64;
+            usage.total_received = total_received;
+            usage.total_transmitted = total_transmitted;
+
+            for (index, value) in [(pos * 2, received), (pos * 2 + 1, transmitted)] {
+                h.data[index].move_start();
+                if let Some(v) = h.data[index].get_mut(0) {
+                    *v = value as f64;
+                }
+            }
+            usage.label.set_text(&format!(
+                "↓ {}/s ↑ {}/s",
+                format_number_bits(received),
+                format_number_bits(transmitted)
+            ));
+        }
+    }
+
+    fn update_batteries(&mut self) {
+        let manager = match self.battery_manager {
+            Some(ref manager) => manager,
+            None => return,
+        };
+        for usage in self.batteries.iter_mut() {
+            // The previous values are displayed if the battery cannot be refreshed.
+            let _ = manager.refresh(&mut usage.battery);
+            let battery = &usage.battery;
+            // The state of charge is between 0 and 1.
+            let charge = f64::from(battery.state_of_charge().value).clamp(0., 1.);
+            usage.bar.set_fraction(charge);
+            usage.bar.set_text(Some(&format!("{:.1} %", charge * 100.)));
+            // The times are in seconds.
+            let remaining = match battery.state() {
+                battery::State::Charging => battery
+                    .time_to_full()
+                    .map(|time| format!(", {} until full", format_battery_time(time.value))),
+                battery::State::Discharging => battery
+                    .time_to_empty()
+                    .map(|time| format!(", {} left", format_battery_time(time.value))),
+                _ => None,
+            };
+            usage.state.set_text(&format!(
+                "{}{}",
+                battery.state(),
+                remaining.unwrap_or_default()
+            ));
+        }
+    }
+
+    fn update_disk_io(&mut self, sys: &sysinfo::System) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.disk_io_last_update).as_secs_f64();
+        if elapsed <= 0. {
+            return;
+        }
+        self.disk_io_last_update = now;
+
+        let (total_read, total_written) = get_disk_io_totals(sys);
+        // The totals go down when processes end, it's then displayed as idle.
+        let read = (total_read.saturating_sub(self.disk_io_totals.0) as f64 / elapsed) as u64;
+        let written = (total_written.saturating_sub(self.disk_io_totals.1) as f64 / elapsed) as u64;
+        self.disk_io_totals = (total_read, total_written);
+
+        let mut h = self.disk_io_history.borrow_mut();
+        for (index, value) in [(0, read), (1, written)] {
+            h.data[index].move_start();
+            if let Some(v) = h.data[index].get_mut(0) {
+                *v = value as f64;
             }
         }
+        self.disk_io_label.set_text(&format!(
+            "read: {}/s, written: {}/s",
+            format_number(read),
+            format_number(written)
+        ));
     }
 
-    pub fn update_system_info_display(&mut self, sys: &sysinfo::System) {
+    pub fn update_system_info_display(&mut self, sys: &sysinfo::System, settings: &Settings) {
         let v = &*self.procs.borrow_mut();
         let h = &mut *self.cpu_usage_history.borrow_mut();
+        let f = &mut *self.cpu_frequency_history.borrow_mut();
 
-        v[0].set_text(Some(&format!(
-            "{:.1} %",
-            sys.global_processor_info().cpu_usage()
-        )));
+        update_total_cpu_usage(&v[0], &self.total_cpu_label, sys, settings);
         v[0].set_show_text(true);
-        v[0].set_fraction(f64::from(sys.global_processor_info().cpu_usage() / 100.));
         for (i, pro) in sys.processors().iter().enumerate() {
             let i = i + 1;
             v[i].set_text(Some(&format!("{:.1} %", pro.cpu_usage())));
@@ -429,10 +1162,68 @@ impl DisplaySysInfo {
             if let Some(h) = h.data[i - 1].get_mut(0) {
                 *h = f64::from(pro.cpu_usage() / 100.);
             }
+            f.data[i - 1].move_start();
+            if let Some(f) = f.data[i - 1].get_mut(0) {
+                *f = pro.frequency() as f64;
+            }
         }
+        self.cpu_load_meter
+            .update(sys.global_processor_info().cpu_usage(), h);
         h.invalidate();
+        f.invalidate();
         self.ram_usage_history.borrow().invalidate();
+        self.swap_usage_history.borrow().invalidate();
         self.temperature_usage_history.borrow().invalidate();
+        self.network_usage_history.borrow().invalidate();
+        self.disk_io_history.borrow().invalidate();
+    }
+}
+
+fn apply_series_color(graph: &mut Graph, index: usize, color: Option<(u8, u8, u8)>) {
+    match color {
+        Some(color) => graph.set_series_color(index, color),
+        None => graph.reset_series_color(index),
+    }
+}
+
+fn celsius_to_fahrenheit(celsius: f64) -> f64 {
+    celsius * 1.8 + 32.
+}
+
+fn temperature_graph_label(display_fahrenheit: bool) -> Box<dyn Fn(f64) -> [String; 4]> {
+    let unit = if display_fahrenheit { "°F" } else { "°C" };
+    Box::new(move |v| {
+        [
+            format!("{:.1}", v),
+            format!("{:.1}", v / 2.),
+            "0".to_string(),
+            unit.to_string(),
+        ]
+    })
+}
+
+/// Formats the current temperature of the component and the highest one it reached.
+fn format_temperature(component: &sysinfo::Component, display_fahrenheit: bool) -> String {
+    if display_fahrenheit {
+        format!(
+            "{:.1} °F (max: {:.1} °F)",
+            component.temperature() * 1.8 + 32.,
+            component.max() * 1.8 + 32.
+        )
+    } else {
+        format!(
+            "{:.1} °C (max: {:.1} °C)",
+            component.temperature(),
+            component.max()
+        )
+    }
+}
+
+fn show_frequency_graph(check_box: &gtk::CheckButton, graph: &Graph) {
+    if check_box.is_active() {
+        graph.show_all();
+    } else {
+        graph.hide();
     }
 }
 
@@ -0,0 +1,219 @@
+use gtk::prelude::{
+    BoxExt, ButtonExt, ContainerExt, DialogExt, EntryExt, GtkListStoreExtManual, GtkWindowExt,
+    LabelExt, TreeModelExt, TreeSelectionExt, TreeViewColumnExt, TreeViewExt, WidgetExt,
+};
+use gtk::{self, glib};
+use sysinfo::{Pid, PidExt, ProcessExt, SystemExt};
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::display_procs::{select_process, select_processes};
+
+/// Returns the pids of the processes which have the given file (or TCP/UDP port if `query` is a
+/// number) open. Only the processes whose file descriptors can be read are checked.
+#[cfg(target_os = "linux")]
+fn find_processes_using(query: &str) -> Vec<Pid> {
+    use std::fs;
+    use std::path::PathBuf;
+
+    let targets: Vec<PathBuf> = match query.parse::<u16>() {
+        Ok(port) => get_socket_inodes(port)
+            .into_iter()
+            .map(|inode| PathBuf::from(format!("socket:[{}]", inode)))
+            .collect(),
+        Err(_) => vec![fs::canonicalize(query).unwrap_or_else(|_| PathBuf::from(query))],
+    };
+    if targets.is_empty() {
+        return Vec::new();
+    }
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut pids = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| {
+            fs::read_dir(format!("/proc/{}/fd", pid))
+                .map(|fds| {
+                    fds.filter_map(|fd| fs::read_link(fd.ok()?.path()).ok())
+                        .any(|target| targets.contains(&target))
+                })
+                .unwrap_or(false)
+        })
+        .map(Pid::from_u32)
+        .collect::<Vec<_>>();
+    pids.sort();
+    pids
+}
+
+/// Returns the inodes of the sockets bound to the given local port.
+#[cfg(target_os = "linux")]
+fn get_socket_inodes(port: u16) -> Vec<u64> {
+    let mut inodes = Vec::new();
+    for file in &[
+        "/proc/net/tcp",
+        "/proc/net/tcp6",
+        "/proc/net/udp",
+        "/proc/net/udp6",
+    ] {
+        let content = match std::fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        // The first line contains the columns names.
+        for line in content.lines().skip(1) {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 10 {
+                continue;
+            }
+            // The local address is "address:port", both in hexadecimal.
+            let local_port = fields[1]
+                .rsplit(':')
+                .next()
+                .and_then(|p| u16::from_str_radix(p, 16).ok());
+            if local_port == Some(port) {
+                if let Ok(inode) = fields[9].parse() {
+                    inodes.push(inode);
+                }
+            }
+        }
+    }
+    inodes
+}
+
+#[cfg(not(target_os = "linux"))]
+fn find_processes_using(_query: &str) -> Vec<Pid> {
+    Vec::new()
+}
+
+fn append_column(tree: &gtk::TreeView, title: &str, pos: i32) {
+    let column = gtk::TreeViewColumn::new();
+    let cell = gtk::CellRendererText::new();
+
+    column.set_title(title);
+    column.pack_start(&cell, true);
+    column.add_attribute(&cell, "text", pos);
+    tree.append_column(&column);
+}
+
+/// Looks for the processes using the file or the port in a thread since it requires to go through
+/// the file descriptors of all the processes, then displays them.
+fn search(
+    query: &str,
+    list_store: &gtk::ListStore,
+    status: &gtk::Label,
+    search_button: &gtk::Button,
+    process_tree: &gtk::TreeView,
+    sys: &Arc<Mutex<sysinfo::System>>,
+) {
+    list_store.clear();
+    let query = query.trim().to_owned();
+    if query.is_empty() {
+        return;
+    }
+    status.set_text("Searching...");
+    search_button.set_sensitive(false);
+    let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    thread::spawn(move || {
+        // The dialog might have been closed in the meantime.
+        let _ = tx.send(find_processes_using(&query));
+    });
+    rx.attach(
+        None,
+        glib::clone!(@weak list_store, @weak status, @weak search_button, @weak process_tree,
+                     @weak sys => @default-return glib::Continue(false), move |pids: Vec<Pid>| {
+            search_button.set_sensitive(true);
+            display_results(&pids, &list_store, &status, &process_tree, &sys);
+            glib::Continue(false)
+        }),
+    );
+}
+
+fn display_results(
+    pids: &[Pid],
+    list_store: &gtk::ListStore,
+    status: &gtk::Label,
+    process_tree: &gtk::TreeView,
+    sys: &Arc<Mutex<sysinfo::System>>,
+) {
+    let sys = sys
+        .lock()
+        .expect("failed to lock to find processes using a file");
+    for pid in pids {
+        let name = sys.process(*pid).map(|p| p.name()).unwrap_or("");
+        list_store.insert_with_values(None, &[(0, &pid.as_u32()), (1, &name)]);
+    }
+    if cfg!(not(target_os = "linux")) {
+        status.set_text("This search is only supported on Linux");
+    } else if pids.is_empty() {
+        status.set_text("No process found (or not allowed to inspect them)");
+    } else {
+        status.set_text(&format!("{} process(es) found", pids.len()));
+        select_processes(process_tree, pids);
+    }
+}
+
+pub fn show_file_users_dialog(
+    window: &gtk::ApplicationWindow,
+    process_tree: &gtk::TreeView,
+    sys: &Arc<Mutex<sysinfo::System>>,
+) {
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Find processes using a file or port"),
+        Some(window),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("Close", gtk::ResponseType::Close)],
+    );
+
+    let input = gtk::Entry::new();
+    input.set_placeholder_text(Some("File path or port number"));
+    input.set_hexpand(true);
+    let search_button = gtk::Button::with_label("Search");
+    let status = gtk::Label::new(None);
+
+    let list_store = gtk::ListStore::new(&[glib::Type::U32, glib::Type::STRING]);
+    let results = gtk::TreeView::with_model(&list_store);
+    append_column(&results, "pid", 0);
+    append_column(&results, "process name", 1);
+    let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    scroll.add(&results);
+
+    let search_layout = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    search_layout.pack_start(&input, true, true, 0);
+    search_layout.pack_start(&search_button, false, false, 0);
+
+    let content_area = dialog.content_area();
+    content_area.set_spacing(5);
+    content_area.pack_start(&search_layout, false, false, 0);
+    content_area.pack_start(&status, false, false, 0);
+    content_area.pack_start(&scroll, true, true, 0);
+    content_area.set_border_width(10);
+
+    search_button.connect_clicked(
+        glib::clone!(@weak input, @weak list_store, @weak status, @weak process_tree, @weak sys
+                     => move |search_button| {
+            search(&input.text(), &list_store, &status, search_button, &process_tree, &sys);
+        }),
+    );
+    input.connect_activate(glib::clone!(@weak search_button => move |_| {
+        // The button is insensitive while a search is running.
+        if search_button.is_sensitive() {
+            search_button.clicked();
+        }
+    }));
+    // Selecting a result selects the process in the process list.
+    results.connect_cursor_changed(glib::clone!(@weak process_tree => move |tree_view| {
+        if let Some((model, iter)) = tree_view.selection().selected() {
+            if let Ok(pid) = model.value(&iter, 0).get::<u32>() {
+                select_process(&process_tree, Pid::from_u32(pid));
+            }
+        }
+    }));
+    dialog.connect_response(|dialog, _| {
+        dialog.close();
+    });
+
+    dialog.set_size_request(400, 300);
+    dialog.show_all();
+}
@@ -0,0 +1,203 @@
+//! Desktop-wide shortcut raising the main window, registered through the
+//! `org.freedesktop.portal.GlobalShortcuts` portal. Nothing happens if the desktop doesn't provide
+//! it.
+
+use gtk::gio::{self, DBusCallFlags, DBusConnection, DBusSignalFlags};
+use gtk::glib::translate::{from_glib_none, ToGlibPtr};
+use gtk::glib::{self, ToVariant, Variant};
+use gtk::prelude::{ApplicationExt, GtkWindowExt};
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+const PORTAL_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const SESSION_INTERFACE: &str = "org.freedesktop.portal.Session";
+const SHORTCUT_ID: &str = "raise-window";
+const SESSION_TOKEN: &str = "process_viewer";
+const CREATE_SESSION_TOKEN: &str = "process_viewer_create_session";
+const BIND_SHORTCUTS_TOKEN: &str = "process_viewer_bind_shortcuts";
+
+/// The portal session of the registered shortcut, closed when the shortcut is changed.
+struct Registration {
+    connection: DBusConnection,
+    session_path: String,
+    subscriptions: Vec<gio::SignalSubscriptionId>,
+}
+
+thread_local! {
+    static REGISTRATION: RefCell<Option<Registration>> = RefCell::new(None);
+    // Each registration uses its own session, so it doesn't conflict with the one being closed.
+    static SESSIONS_COUNT: Cell<u32> = Cell::new(0);
+}
+
+// glib-rs doesn't provide a way to create object path variants.
+fn object_path(path: &str) -> Variant {
+    unsafe { from_glib_none(glib::ffi::g_variant_new_object_path(path.to_glib_none().0)) }
+}
+
+fn call_portal(connection: &DBusConnection, method: &'static str, parameters: &Variant) {
+    connection.call(
+        Some(PORTAL_NAME),
+        PORTAL_PATH,
+        PORTAL_INTERFACE,
+        method,
+        Some(parameters),
+        None,
+        DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+        move |result| {
+            if let Err(e) = result {
+                eprintln!("<global_shortcut> {} failed: {}", method, e);
+            }
+        },
+    );
+}
+
+fn bind_shortcut(connection: &DBusConnection, session_path: &str, trigger: &str) {
+    let mut shortcut = HashMap::new();
+    shortcut.insert(
+        "description".to_owned(),
+        "Raise the process viewer window".to_variant(),
+    );
+    shortcut.insert("preferred_trigger".to_owned(), trigger.to_variant());
+    let mut options = HashMap::new();
+    options.insert("handle_token".to_owned(), BIND_SHORTCUTS_TOKEN.to_variant());
+
+    let parameters = Variant::tuple_from_iter([
+        object_path(session_path),
+        vec![(SHORTCUT_ID.to_owned(), shortcut)].to_variant(),
+        // No parent window.
+        "".to_variant(),
+        options.to_variant(),
+    ]);
+    call_portal(connection, "BindShortcuts", &parameters);
+}
+
+/// Returns `true` if `trigger` uses the format of the shortcuts specification (for example
+/// "CTRL+ALT+P") with a known key.
+pub fn is_valid_trigger(trigger: &str) -> bool {
+    let mut parts = trigger.split('+').collect::<Vec<_>>();
+    let key = match parts.pop() {
+        Some(key) if !key.is_empty() => key,
+        _ => return false,
+    };
+    // The trigger is converted to the GTK format to be checked by GTK.
+    let mut accelerator = String::new();
+    for modifier in parts {
+        accelerator.push_str(match modifier {
+            "CTRL" => "<Control>",
+            "ALT" => "<Alt>",
+            "SHIFT" => "<Shift>",
+            "NUM" => "<Mod2>",
+            "LOGO" => "<Super>",
+            _ => return false,
+        });
+    }
+    accelerator.push_str(key);
+    gtk::accelerator_parse(&accelerator).0 != 0
+}
+
+/// Closes the portal session of the registered shortcut, if any.
+fn unregister() {
+    let registration = match REGISTRATION.with(|registration| registration.borrow_mut().take()) {
+        Some(registration) => registration,
+        None => return,
+    };
+    for subscription in registration.subscriptions {
+        registration.connection.signal_unsubscribe(subscription);
+    }
+    registration.connection.call(
+        Some(PORTAL_NAME),
+        &registration.session_path,
+        SESSION_INTERFACE,
+        "Close",
+        None,
+        None,
+        DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+        |result| {
+            if let Err(e) = result {
+                eprintln!("<global_shortcut> Close failed: {}", e);
+            }
+        },
+    );
+}
+
+/// `trigger` uses the format of the shortcuts specification, for example "CTRL+ALT+P". The
+/// desktop might ask the user to confirm it (or to pick another one). The previously registered
+/// shortcut is removed, so an empty `trigger` only does that.
+pub fn register(application: &gtk::Application, window: &gtk::Window, trigger: &str) {
+    unregister();
+    if trigger.is_empty() {
+        return;
+    }
+    let connection = match application.dbus_connection() {
+        Some(connection) => connection,
+        None => return,
+    };
+    // The portal builds the path of its objects from our unique name and the tokens we give it.
+    let sender = match connection.unique_name() {
+        Some(name) => name.trim_start_matches(':').replace('.', "_"),
+        None => return,
+    };
+    let count = SESSIONS_COUNT.with(|count| count.replace(count.get() + 1));
+    let session_token = format!("{}_{}", SESSION_TOKEN, count);
+    let create_session_token = format!("{}_{}", CREATE_SESSION_TOKEN, count);
+    let session_path = format!("{}/session/{}/{}", PORTAL_PATH, sender, session_token);
+    let request_path = format!(
+        "{}/request/{}/{}",
+        PORTAL_PATH, sender, create_session_token
+    );
+
+    // The shortcut can only be bound once the session has been created.
+    let trigger = trigger.to_owned();
+    let response_subscription = connection.signal_subscribe(
+        Some(PORTAL_NAME),
+        Some("org.freedesktop.portal.Request"),
+        Some("Response"),
+        Some(&request_path),
+        None,
+        DBusSignalFlags::NONE,
+        glib::clone!(@strong session_path => move |connection, _, _, _, _, parameters| {
+            // 0 means that the request succeeded.
+            if parameters.child_value(0).get::<u32>() == Some(0) {
+                bind_shortcut(connection, &session_path, &trigger);
+            }
+        }),
+    );
+    let activated_subscription = connection.signal_subscribe(
+        Some(PORTAL_NAME),
+        Some(PORTAL_INTERFACE),
+        Some("Activated"),
+        Some(PORTAL_PATH),
+        None,
+        DBusSignalFlags::NONE,
+        glib::clone!(@weak window, @strong session_path => move |_, _, _, _, _, parameters| {
+            if parameters.child_value(0).str() == Some(session_path.as_str())
+                && parameters.child_value(1).str() == Some(SHORTCUT_ID)
+            {
+                window.present();
+            }
+        }),
+    );
+
+    let mut options = HashMap::new();
+    options.insert("handle_token".to_owned(), create_session_token.to_variant());
+    options.insert(
+        "session_handle_token".to_owned(),
+        session_token.to_variant(),
+    );
+    call_portal(&connection, "CreateSession", &(options,).to_variant());
+
+    REGISTRATION.with(|registration| {
+        *registration.borrow_mut() = Some(Registration {
+            connection,
+            session_path,
+            subscriptions: vec![response_subscription, activated_subscription],
+        });
+    });
+}
@@ -1,33 +1,227 @@
 use gtk::gdk;
-use gtk::prelude::{BoxExt, ContainerExt, LabelExt, ScrolledWindowExt, WidgetExt};
-use gtk::{self, cairo, DrawingArea};
-use std::cell::RefCell;
+use gtk::prelude::{
+    BoxExt, ContainerExt, FlowBoxExt, LabelExt, ScrolledWindowExt, StyleContextExt, WidgetExt,
+};
+use gtk::{self, cairo, pango, DrawingArea};
+use serde_derive::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
 
-use std::rc::Rc;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use std::time::Instant;
 
 use crate::color::Color;
 use crate::utils::RotateVec;
 
 const LEFT_WIDTH: f64 = 31.;
+// The font size (in pixels) for which `LEFT_WIDTH` was computed.
+const LABEL_FONT_SIZE: f64 = 8.;
+// The default GTK font size (in points), used when the theme doesn't provide one.
+const DEFAULT_THEME_FONT_SIZE: f64 = 10.;
+// Number of decades displayed with the logarithmic scale: the bottom of the graph is at
+// `max / 1000`.
+const LOG_SCALE_DECADES: f64 = 3.;
+/// Default number of samples kept by the graphs.
+pub const DEFAULT_HISTORY_LEN: usize = 61;
+
+/// Display options shared by all graphs.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct GraphStyle {
+    pub display_grid: bool,
+    // When there are more samples than pixels, only draw the min/max of each pixel column.
+    pub decimate: bool,
+    // Multiplies the size of the labels font, which follows the GTK font size.
+    pub label_font_scale: f64,
+    // Width of the lines of the series, larger ones are easier to see on HiDPI displays.
+    pub line_width: f64,
+    pub antialias: bool,
+}
+
+impl Default for GraphStyle {
+    fn default() -> GraphStyle {
+        GraphStyle {
+            display_grid: true,
+            decimate: true,
+            label_font_scale: 1.,
+            line_width: 1.,
+            antialias: true,
+        }
+    }
+}
+
+/// Colors chosen by the user for some series, the automatic ones are used otherwise.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct GraphColors {
+    // Used for all the processors.
+    pub cpu: Option<(u8, u8, u8)>,
+    pub ram: Option<(u8, u8, u8)>,
+    pub swap: Option<(u8, u8, u8)>,
+}
+
+thread_local! {
+    // The style used by newly created graphs.
+    static DEFAULT_STYLE: RefCell<GraphStyle> = RefCell::new(GraphStyle::default());
+    // The number of samples of newly created graphs.
+    static HISTORY_LEN: Cell<usize> = Cell::new(DEFAULT_HISTORY_LEN);
+    // All the graphs which were connected, to be able to update their style.
+    static GRAPHS: RefCell<Vec<Weak<RefCell<Graph>>>> = RefCell::new(Vec::new());
+}
+
+pub fn register_graph(graph: &Rc<RefCell<Graph>>) {
+    GRAPHS.with(|graphs| graphs.borrow_mut().push(Rc::downgrade(graph)));
+}
+
+/// Applies the given style to all existing graphs and to the ones which will be created.
+pub fn apply_style_to_all_graphs(style: &GraphStyle) {
+    DEFAULT_STYLE.with(|default_style| *default_style.borrow_mut() = style.clone());
+    GRAPHS.with(|graphs| {
+        graphs.borrow_mut().retain(|graph| match graph.upgrade() {
+            Some(graph) => {
+                graph.borrow_mut().set_style(style.clone());
+                true
+            }
+            None => false,
+        });
+    });
+}
+
+/// Changes the number of samples of all existing graphs and of the ones which will be created.
+pub fn apply_history_len_to_all_graphs(len: usize) {
+    HISTORY_LEN.with(|history_len| history_len.set(len));
+    GRAPHS.with(|graphs| {
+        graphs.borrow_mut().retain(|graph| match graph.upgrade() {
+            Some(graph) => {
+                graph.borrow_mut().set_history_len(len);
+                true
+            }
+            None => false,
+        });
+    });
+}
+
+/// Redraws all existing graphs, for example when the units of their labels changed.
+pub fn invalidate_all_graphs() {
+    GRAPHS.with(|graphs| {
+        graphs.borrow_mut().retain(|graph| match graph.upgrade() {
+            Some(graph) => {
+                graph.borrow().invalidate();
+                true
+            }
+            None => false,
+        });
+    });
+}
+
+/// Returns an empty history (filled with zeros) with the current number of samples.
+pub fn new_history() -> RotateVec<f64> {
+    RotateVec::new(vec![0.; HISTORY_LEN.with(Cell::get)])
+}
+
+/// Renders the given graphs one below the other, each with its title, into a PNG file.
+pub fn save_graphs_to_png(graphs: &[(&str, &Graph)], path: &Path) -> Result<(), String> {
+    const WIDTH: i32 = 600;
+    const GRAPH_HEIGHT: i32 = 200;
+    const TITLE_HEIGHT: i32 = 30;
+
+    if graphs.is_empty() {
+        return Err("There is no graph to save".to_owned());
+    }
+    let height = graphs.len() as i32 * (GRAPH_HEIGHT + TITLE_HEIGHT);
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, WIDTH, height)
+        .map_err(|e| format!("Failed to create the image: {}", e))?;
+    {
+        let c = cairo::Context::new(&surface)
+            .map_err(|e| format!("Failed to create the image: {}", e))?;
+        let (foreground, background) = graphs[0].1.theme_colors();
+        c.set_source_rgb(background.red(), background.green(), background.blue());
+        let _ = c.paint();
+        for (pos, (title, graph)) in graphs.iter().enumerate() {
+            let y = f64::from(pos as i32 * (GRAPH_HEIGHT + TITLE_HEIGHT));
+            c.set_source_rgb(foreground.red(), foreground.green(), foreground.blue());
+            c.set_font_size(16.);
+            c.move_to(5., y + 20.);
+            let _ = c.show_text(title);
+            // The graph drawing changes the context state (rotation for the labels).
+            let _ = c.save();
+            c.translate(0., y + f64::from(TITLE_HEIGHT));
+            graph.draw(&c, f64::from(WIDTH), f64::from(GRAPH_HEIGHT));
+            let _ = c.restore();
+        }
+    }
+    write_png(&surface, WIDTH, height, path)
+}
+
+fn write_png(
+    surface: &cairo::ImageSurface,
+    width: i32,
+    height: i32,
+    path: &Path,
+) -> Result<(), String> {
+    let pixbuf = gdk::pixbuf_get_from_surface(surface, 0, 0, width, height)
+        .ok_or_else(|| "Failed to convert the image".to_owned())?;
+    pixbuf
+        .savev(path, "png", &[])
+        .map_err(|e| format!("Failed to save '{}': {}", path.display(), e))
+}
+
+/// Formats the labels of the logarithmic scale, which can be much smaller than the top one.
+fn format_log_label(value: f64) -> String {
+    if value >= 10. {
+        format!("{:.0}", value)
+    } else if value >= 1. {
+        format!("{:.1}", value)
+    } else {
+        // Enough decimals to display the first significant digit.
+        let decimals = (-value.log10()).ceil().max(0.) as usize + 1;
+        format!("{:.*}", decimals, value)
+    }
+}
+
+fn series_markup(name: &str, (r, g, b): (u8, u8, u8)) -> String {
+    format!(
+        "<span foreground='#{:02X}{:02X}{:02X}'>{}</span>",
+        r, g, b, name
+    )
+}
 
 pub struct Graph {
     colors: Vec<Color>,
+    // The automatically assigned color of each series, to go back to it after an override.
+    default_colors: Vec<(u8, u8, u8)>,
+    // The labels displaying the name of each series: on the right side and in the legend.
+    series_labels: Vec<(gtk::Label, gtk::Label)>,
     pub data: Vec<RotateVec<f64>>,
+    // Name of each data series, displayed in the tooltip.
+    names: Vec<String>,
     vertical_layout: gtk::Box,
     scroll_layout: gtk::ScrolledWindow,
     horizontal_layout: gtk::Box,
+    // Contains the graph and its legend below it.
+    layout: gtk::Box,
+    legend: gtk::FlowBox,
     pub area: DrawingArea,
     max: Option<RefCell<f64>>,
     keep_max: bool,
     display_labels: RefCell<bool>,
+    display_legend: RefCell<bool>,
     initial_diff: Option<i32>,
     label_callbacks: Option<Box<dyn Fn(f64) -> [String; 4]>>,
+    // Formats the values in the tooltip. If not set, the label callbacks are used instead.
+    value_formatter: Option<Box<dyn Fn(f64) -> String>>,
     labels_layout_width: i32,
     /// `minimum` is used only if `max` is set: it'll be the minimum that the `max` value will
     /// be able to go down.
     minimum: Option<f64>,
     // In %, from 0 to whatever
     overhead: Option<f64>,
+    // Horizontal dashed lines (series index and value), drawn with the color of their series.
+    reference_lines: Vec<(usize, f64)>,
+    // Each series is drawn as an area on top of the previous ones, so the top shows their total.
+    stacked: bool,
+    log_scale: bool,
+    style: GraphStyle,
 }
 
 impl Graph {
@@ -38,29 +232,47 @@ impl Graph {
     pub fn new(max: Option<f64>, keep_max: bool) -> Graph {
         let g = Graph {
             colors: vec![],
+            default_colors: vec![],
+            series_labels: vec![],
             data: vec![],
+            names: vec![],
             vertical_layout: gtk::Box::new(gtk::Orientation::Vertical, 0),
             scroll_layout: gtk::ScrolledWindow::new(
                 None::<&gtk::Adjustment>,
                 None::<&gtk::Adjustment>,
             ),
             horizontal_layout: gtk::Box::new(gtk::Orientation::Horizontal, 0),
+            layout: gtk::Box::new(gtk::Orientation::Vertical, 0),
+            legend: gtk::FlowBox::new(),
             area: DrawingArea::new(),
             max: max.map(RefCell::new),
             keep_max,
             display_labels: RefCell::new(true),
+            display_legend: RefCell::new(false),
             initial_diff: None,
             label_callbacks: None,
+            value_formatter: None,
             labels_layout_width: 80,
             minimum: None,
             overhead: None,
+            reference_lines: Vec::new(),
+            stacked: false,
+            log_scale: false,
+            style: DEFAULT_STYLE.with(|style| style.borrow().clone()),
         };
-        g.scroll_layout.set_min_content_width(g.labels_layout_width);
+        g.update_labels_width();
         g.scroll_layout.add(&g.vertical_layout);
         g.horizontal_layout.pack_start(&g.area, true, true, 0);
         g.horizontal_layout
             .pack_start(&g.scroll_layout, false, true, 10);
         g.horizontal_layout.set_margin_start(5);
+        // The legend is split in columns when there are a lot of series (like processors).
+        g.legend.set_selection_mode(gtk::SelectionMode::None);
+        g.legend.set_homogeneous(true);
+        g.legend.set_max_children_per_line(8);
+        g.legend.set_margin_start(5);
+        g.layout.pack_start(&g.horizontal_layout, true, true, 0);
+        g.layout.pack_start(&g.legend, false, false, 5);
         g
     }
 
@@ -68,18 +280,80 @@ impl Graph {
         self.minimum = minimum;
     }
 
+    pub fn minimum(&self) -> Option<f64> {
+        self.minimum
+    }
+
+    /// Leaves some space above the highest value, `overhead` being in percent of it. It's ignored
+    /// if a `minimum` is set. Values above 100% are allowed: with 300%, the highest value is drawn
+    /// at a quarter of the graph height. The labels follow the resulting top of the graph.
     pub fn set_overhead(&mut self, overhead: Option<f64>) {
         if let Some(o) = overhead {
             assert!(o >= 0.);
         }
         self.overhead = overhead;
+        self.invalidate();
+    }
+
+    pub fn overhead(&self) -> Option<f64> {
+        self.overhead
+    }
+
+    /// Returns `max` with the overhead added to it, unless there's a `minimum`.
+    fn with_overhead(&self, max: f64) -> f64 {
+        match self.overhead {
+            Some(over) if self.minimum.is_none() => max + max * over / 100.,
+            _ => max,
+        }
+    }
+
+    pub fn set_style(&mut self, style: GraphStyle) {
+        self.style = style;
+        self.update_labels_width();
+        self.invalidate();
+    }
+
+    pub fn set_line_width(&mut self, line_width: f64) {
+        self.style.line_width = line_width;
+        self.invalidate();
+    }
+
+    pub fn set_antialias(&mut self, antialias: bool) {
+        self.style.antialias = antialias;
+        self.invalidate();
+    }
+
+    /// The most recent samples are kept and the new ones are filled with zeros.
+    pub fn set_history_len(&mut self, len: usize) {
+        for data in self.data.iter_mut() {
+            data.resize(len, 0.);
+        }
+        self.invalidate();
     }
 
-    /// Changes the size of the layout containing labels (the one on the right).
+    /// Changes the size of the layout containing labels (the one on the right). It is scaled
+    /// by the labels font scale.
     pub fn set_labels_width(&mut self, labels_layout_width: u32) {
-        self.scroll_layout
-            .set_min_content_width(labels_layout_width as i32);
         self.labels_layout_width = labels_layout_width as i32;
+        self.update_labels_width();
+    }
+
+    fn update_labels_width(&self) {
+        self.scroll_layout.set_min_content_width(
+            (f64::from(self.labels_layout_width) * self.style.label_font_scale) as i32,
+        );
+    }
+
+    /// The font size of the labels follows the one of the GTK theme.
+    fn label_font_size(&self) -> f64 {
+        let theme_font_size = self
+            .area
+            .pango_context()
+            .font_description()
+            .map(|font| f64::from(font.size()) / f64::from(pango::SCALE))
+            .filter(|size| *size > 0.)
+            .unwrap_or(DEFAULT_THEME_FONT_SIZE);
+        LABEL_FONT_SIZE * theme_font_size / DEFAULT_THEME_FONT_SIZE * self.style.label_font_scale
     }
 
     pub fn set_label_callbacks(
@@ -89,6 +363,10 @@ impl Graph {
         self.label_callbacks = label_callbacks;
     }
 
+    pub fn set_value_formatter(&mut self, value_formatter: Option<Box<dyn Fn(f64) -> String>>) {
+        self.value_formatter = value_formatter;
+    }
+
     pub fn set_display_labels(&self, display_labels: bool) {
         *self.display_labels.borrow_mut() = display_labels;
         if display_labels {
@@ -99,19 +377,33 @@ impl Graph {
         self.invalidate();
     }
 
+    /// Displays the name of each series in its color below the graph. It's mostly useful when
+    /// the labels on the right side aren't displayed.
+    pub fn set_display_legend(&self, display_legend: bool) {
+        *self.display_legend.borrow_mut() = display_legend;
+        if display_legend {
+            self.legend.show_all();
+        } else {
+            self.legend.hide();
+        }
+    }
+
     pub fn hide(&self) {
-        self.horizontal_layout.hide();
+        self.layout.hide();
     }
 
     pub fn show_all(&self) {
-        self.horizontal_layout.show_all();
+        self.layout.show_all();
         if !*self.display_labels.borrow() {
             self.scroll_layout.hide();
         }
+        if !*self.display_legend.borrow() {
+            self.legend.hide();
+        }
     }
 
     pub fn attach_to(&self, to: &gtk::Box) {
-        to.add(&self.horizontal_layout);
+        to.add(&self.layout);
     }
 
     pub fn push(&mut self, d: RotateVec<f64>, s: &str, override_color: Option<usize>) {
@@ -120,48 +412,402 @@ impl Graph {
         } else {
             Color::generate(self.data.len() + 11)
         };
-        let l = gtk::Label::new(None);
-        l.set_markup(&format!(
-            "<span foreground='#{:02X}{:02X}{:02X}'>{}</span>",
-            r, g, b, s
-        ));
-        self.vertical_layout.add(&l);
+        let markup = series_markup(s, (r, g, b));
+        let label = gtk::Label::new(None);
+        label.set_markup(&markup);
+        self.vertical_layout.add(&label);
+        let legend_label = gtk::Label::new(None);
+        legend_label.set_markup(&markup);
+        legend_label.set_halign(gtk::Align::Start);
+        self.legend.add(&legend_label);
+        self.series_labels.push((label, legend_label));
         self.colors.push(c);
+        self.default_colors.push((r, g, b));
         self.data.push(d);
+        self.names.push(s.to_owned());
     }
 
-    fn draw_labels(&self, c: &cairo::Context, max: f64, height: f64) {
+    /// Overrides the color of the series at `index`, in the graph and in its labels.
+    pub fn set_series_color(&mut self, index: usize, (r, g, b): (u8, u8, u8)) {
+        if index >= self.colors.len() {
+            return;
+        }
+        self.colors[index] = Color::new(r, g, b);
+        let markup = series_markup(&self.names[index], (r, g, b));
+        let (label, legend_label) = &self.series_labels[index];
+        label.set_markup(&markup);
+        legend_label.set_markup(&markup);
+        self.invalidate();
+    }
+
+    /// Goes back to the automatically assigned color of the series at `index`.
+    pub fn reset_series_color(&mut self, index: usize) {
+        if let Some(color) = self.default_colors.get(index).copied() {
+            self.set_series_color(index, color);
+        }
+    }
+
+    /// Returns the color of the series at `index`.
+    pub fn series_color(&self, index: usize) -> Option<(u8, u8, u8)> {
+        self.colors.get(index).map(|color| {
+            (
+                (color.r * 255.).round() as u8,
+                (color.g * 255.).round() as u8,
+                (color.b * 255.).round() as u8,
+            )
+        })
+    }
+
+    pub fn series_count(&self) -> usize {
+        self.data.len()
+    }
+
+    /// In stacked mode, the values of the series are accumulated before being plotted and the
+    /// area below each of them is filled. Without `max`, the top of the graph then represents all
+    /// the series at 1.
+    pub fn set_stacked(&mut self, stacked: bool) {
+        self.stacked = stacked;
+        self.invalidate();
+    }
+
+    /// With a logarithmic scale, small values remain visible next to the big spikes. The values
+    /// below `max / 1000` are drawn at the bottom.
+    pub fn set_log_scale(&mut self, log_scale: bool) {
+        self.log_scale = log_scale;
+        self.invalidate();
+    }
+
+    pub fn log_scale(&self) -> bool {
+        self.log_scale
+    }
+
+    /// Returns the vertical position of `value` when `max` is at the top of the graph.
+    fn y_position(&self, value: f64, max: f64, height: f64) -> f64 {
+        let ratio = if self.log_scale {
+            // The logarithm isn't defined for zero and negative values, so they're clamped to
+            // the bottom of the scale.
+            let lowest = (max * 10f64.powf(-LOG_SCALE_DECADES)).max(f64::EPSILON);
+            (value.max(lowest) / max.max(lowest)).log10() / LOG_SCALE_DECADES + 1.
+        } else {
+            value / max
+        };
+        height - ratio * (height - 1.0)
+    }
+
+    /// Returns the value of the series at `series` for the sample at `index`, as it's plotted.
+    fn plotted_value(&self, series: usize, index: usize) -> f64 {
+        if self.stacked {
+            self.data[..=series].iter().map(|entry| entry[index]).sum()
+        } else {
+            self.data[series][index]
+        }
+    }
+
+    /// Adds a horizontal line at `value` for the series at index `series`, like a threshold. The
+    /// vertical scale is extended so it's always visible. It's only drawn if the graph has a `max`.
+    pub fn add_reference_line(&mut self, series: usize, value: f64) {
+        self.reference_lines.push((series, value));
+    }
+
+    /// Applies `convert` to all the values of the graph and to its reference lines, like when
+    /// their unit changes.
+    pub fn convert_values<F: Fn(f64) -> f64>(&mut self, convert: F) {
+        for series in self.data.iter_mut() {
+            for value in series.iter_mut() {
+                *value = convert(*value);
+            }
+        }
+        for (_, value) in self.reference_lines.iter_mut() {
+            *value = convert(*value);
+        }
+    }
+
+    fn draw_reference_lines(
+        &self,
+        c: &cairo::Context,
+        x_start: f64,
+        width: f64,
+        height: f64,
+        max: f64,
+    ) {
+        if self.reference_lines.is_empty() {
+            return;
+        }
+        c.set_dash(&[4., 4.], 0.);
+        for (series, value) in &self.reference_lines {
+            if let Some(color) = self.colors.get(*series) {
+                let y = self.y_position(*value, max, height);
+                c.set_source_rgb(color.r, color.g, color.b);
+                c.move_to(x_start, y);
+                c.line_to(width, y);
+                let _ = c.stroke();
+            }
+        }
+        c.set_dash(&[], 0.);
+    }
+
+    /// Width of the part on the left where the labels are drawn.
+    fn x_start(&self) -> f64 {
+        if self.label_callbacks.is_some() {
+            LEFT_WIDTH * self.label_font_size() / LABEL_FONT_SIZE
+        } else {
+            0.
+        }
+    }
+
+    /// Returns the index of the sample drawn the closest to `x`.
+    fn sample_at(&self, x: f64, width: f64) -> Option<usize> {
+        let len = self.data.first()?.len().checked_sub(1)?;
+        let x_start = self.x_start();
+        if x < x_start {
+            return None;
+        }
+        if len == 0 {
+            return Some(0);
+        }
+        let positions = self.time_positions(x_start, width).unwrap_or_else(|| {
+            let step = (width - 2.0 - x_start) / len as f64;
+            (0..=len)
+                .map(|index| x_start + 1.0 + (len - index) as f64 * step)
+                .collect()
+        });
+        positions
+            .iter()
+            .map(|position| (position - x).abs())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+
+    fn format_value(&self, value: f64) -> String {
+        if let Some(ref formatter) = self.value_formatter {
+            return formatter(value);
+        }
+        match (&self.max, &self.label_callbacks) {
+            // Without maximum, the values are between 0 and 1.
+            (None, _) => format!("{:.1} %", value * 100.),
+            (Some(_), Some(call)) => {
+                let entries = call(value);
+                format!("{} {}", entries[0], entries[3])
+            }
+            (Some(_), None) => format!("{:.1}", value),
+        }
+    }
+
+    /// Returns the values of all the series at the horizontal position `x`, one per line.
+    pub fn tooltip_text(&self, x: f64, width: f64) -> Option<String> {
+        let index = self.sample_at(x, width)?;
+        Some(
+            self.names
+                .iter()
+                .zip(self.data.iter())
+                .map(|(name, data)| {
+                    let value = self.format_value(data[index]);
+                    if name.is_empty() {
+                        value
+                    } else {
+                        format!("{}: {}", name, value)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+
+    fn draw_labels(
+        &self,
+        c: &cairo::Context,
+        max: f64,
+        height: f64,
+        color: &gdk::RGBA,
+        font_size: f64,
+    ) {
         if let Some(ref call) = self.label_callbacks {
-            let entries = call(max);
-            let font_size = 8.;
+            let mut entries = call(max);
+            if self.log_scale {
+                // The middle and bottom labels are computed from the top one so they use the
+                // same unit.
+                if let Ok(top) = entries[0].parse::<f64>() {
+                    entries[1] = format_log_label(top * 10f64.powf(-LOG_SCALE_DECADES / 2.));
+                    entries[2] = format_log_label(top * 10f64.powf(-LOG_SCALE_DECADES));
+                }
+            }
+            let left_width = LEFT_WIDTH * font_size / LABEL_FONT_SIZE;
+            // An approximation of the width of a character.
+            let char_width = font_size / 2.;
 
-            c.set_source_rgb(0., 0., 0.);
+            c.set_source_rgb(color.red(), color.green(), color.blue());
             c.set_font_size(font_size);
 
-            c.move_to(LEFT_WIDTH - 4. - entries[0].len() as f64 * 4., font_size);
+            c.move_to(
+                left_width - 4. - entries[0].len() as f64 * char_width,
+                font_size,
+            );
             let _ = c.show_text(entries[0].as_str());
 
-            c.move_to(LEFT_WIDTH - 4. - entries[1].len() as f64 * 4., height / 2.);
+            c.move_to(
+                left_width - 4. - entries[1].len() as f64 * char_width,
+                height / 2.,
+            );
             let _ = c.show_text(entries[1].as_str());
 
-            c.move_to(LEFT_WIDTH - 4. - entries[2].len() as f64 * 4., height - 2.);
+            c.move_to(
+                left_width - 4. - entries[2].len() as f64 * char_width,
+                height - 2.,
+            );
             let _ = c.show_text(entries[2].as_str());
 
             c.move_to(
                 font_size - 1.,
-                height / 2. + 4. * (entries[3].len() >> 1) as f64,
+                height / 2. + char_width * (entries[3].len() >> 1) as f64,
             );
             c.rotate(-::std::f64::consts::FRAC_PI_2);
             let _ = c.show_text(entries[3].as_str());
         }
     }
 
+    fn draw_data(&self, c: &cairo::Context, x_start: f64, width: f64, height: f64, max: f64) {
+        if self.data.is_empty() || self.data[0].is_empty() {
+            return;
+        }
+        let len = self.data[0].len() - 1;
+        let columns = (width - 2.0 - x_start).max(1.) as usize;
+        let step = (width - 2.0 - x_start) / len as f64;
+        // The most recent sample (index 0) is on the right.
+        let positions = self.time_positions(x_start, width).unwrap_or_else(|| {
+            (0..=len)
+                .map(|index| x_start + 1.0 + (len - index) as f64 * step)
+                .collect()
+        });
+        if self.stacked {
+            self.draw_stacked_data(c, height, max, &positions);
+            return;
+        }
+        if self.style.decimate && len > columns {
+            self.draw_decimated_data(c, x_start, height, max, columns, &positions);
+            return;
+        }
+        let position = |index: usize| positions[index];
+        for index in (1..=len).rev() {
+            for (entry, color) in self.data.iter().zip(self.colors.iter()) {
+                c.set_source_rgb(color.r, color.g, color.b);
+                c.move_to(
+                    position(index - 1),
+                    self.y_position(entry[index - 1], max, height),
+                );
+                c.line_to(position(index), self.y_position(entry[index], max, height));
+                let _ = c.stroke();
+            }
+        }
+    }
+
+    /// Fills the area between each series and the previous one, then draws its top line. Samples
+    /// aren't decimated since the areas hide the overlapping parts anyway.
+    fn draw_stacked_data(&self, c: &cairo::Context, height: f64, max: f64, positions: &[f64]) {
+        let len = self.data[0].len() - 1;
+        let position = |index: usize| positions[index];
+        let y = |value: f64| self.y_position(value, max, height);
+        let mut bottom = vec![0.; len + 1];
+        for (series, color) in self.colors.iter().enumerate().take(self.data.len()) {
+            let top = (0..=len)
+                .map(|index| self.plotted_value(series, index))
+                .collect::<Vec<_>>();
+            c.move_to(position(len), y(top[len]));
+            for index in (0..len).rev() {
+                c.line_to(position(index), y(top[index]));
+            }
+            for index in 0..=len {
+                c.line_to(position(index), y(bottom[index]));
+            }
+            c.close_path();
+            c.set_source_rgba(color.r, color.g, color.b, 0.5);
+            let _ = c.fill();
+
+            c.set_source_rgb(color.r, color.g, color.b);
+            c.move_to(position(len), y(top[len]));
+            for index in (0..len).rev() {
+                c.line_to(position(index), y(top[index]));
+            }
+            let _ = c.stroke();
+            bottom = top;
+        }
+    }
+
+    /// Returns the horizontal positions of the samples based on when they were taken, so irregular
+    /// refresh intervals don't distort the graph. Returns `None` if there aren't enough timestamps.
+    fn time_positions(&self, x_start: f64, width: f64) -> Option<Vec<f64>> {
+        let data = self.data.first()?;
+        let timestamps = (0..data.len())
+            .map(|index| data.timestamp(index))
+            .collect::<Vec<_>>();
+        let ages = sample_ages(&timestamps)?;
+        let newest = ages[0];
+        let oldest = ages[ages.len() - 1];
+        Some(
+            ages.iter()
+                .map(|age| {
+                    x_start + 1.0 + (oldest - age) / (oldest - newest) * (width - 2.0 - x_start)
+                })
+                .collect(),
+        )
+    }
+
+    /// Draws a vertical line between the minimum and the maximum of the samples drawn in each
+    /// pixel column, so spikes remain visible without drawing every sample.
+    fn draw_decimated_data(
+        &self,
+        c: &cairo::Context,
+        x_start: f64,
+        height: f64,
+        max: f64,
+        columns: usize,
+        positions: &[f64],
+    ) {
+        let column_of =
+            |index: usize| ((positions[index] - x_start - 1.0).max(0.) as usize).min(columns - 1);
+        for (entry, color) in self.data.iter().zip(self.colors.iter()) {
+            c.set_source_rgb(color.r, color.g, color.b);
+            let mut ranges = vec![None; columns];
+            for index in 0..entry.len() {
+                // The range of a column also covers the following sample, to keep the line
+                // continuous when the samples are spread over several columns.
+                let mut values = vec![entry[index]];
+                if index > 0 {
+                    values.push(entry[index - 1]);
+                }
+                for value in values {
+                    let (low, high) = ranges[column_of(index)].get_or_insert((value, value));
+                    *low = f64::min(*low, value);
+                    *high = f64::max(*high, value);
+                }
+            }
+            for (column, range) in ranges.into_iter().enumerate() {
+                if let Some((low, high)) = range {
+                    let x = x_start + 1.5 + column as f64;
+                    let top = self.y_position(high, max, height);
+                    let bottom = self.y_position(low, max, height);
+                    c.move_to(x, top);
+                    c.line_to(x, bottom.max(top + 1.));
+                }
+            }
+            let _ = c.stroke();
+        }
+    }
+
+    /// Returns the foreground and background colors of the current GTK theme, which are used so
+    /// the graphs look native.
+    fn theme_colors(&self) -> (gdk::RGBA, gdk::RGBA) {
+        let style_context = self.area.style_context();
+        let foreground = style_context.color(gtk::StateFlags::NORMAL);
+        let background = style_context
+            .lookup_color("theme_base_color")
+            .unwrap_or(gdk::RGBA::BLACK);
+        (foreground, background)
+    }
+
     pub fn draw(&self, c: &cairo::Context, width: f64, height: f64) {
-        let x_start = if self.label_callbacks.is_some() {
-            LEFT_WIDTH
-        } else {
-            0.
-        };
+        let font_size = self.label_font_size();
+        let x_start = self.x_start();
 
         // to limit line "fuzziness"
         #[inline]
@@ -174,10 +820,20 @@ impl Graph {
             }
         }
 
-        c.set_source_rgb(0., 0., 0.);
+        let (foreground, background) = self.theme_colors();
+
+        if !self.style.antialias {
+            c.set_antialias(cairo::Antialias::None);
+        }
+        c.set_source_rgb(background.red(), background.green(), background.blue());
         c.rectangle(x_start, 0., width, height);
         let _ = c.fill();
-        c.set_source_rgb(0.5, 0.5, 0.5);
+        // The grid is drawn halfway between the foreground and the background colors.
+        c.set_source_rgb(
+            (foreground.red() + background.red()) / 2.,
+            (foreground.green() + background.green()) / 2.,
+            (foreground.blue() + background.blue()) / 2.,
+        );
         c.set_line_width(0.5);
 
         // We always draw 10 lines (12 if we count the borders).
@@ -188,21 +844,23 @@ impl Graph {
             return;
         }
 
-        while current > x_start {
-            c.move_to(rounder(current), 0.0);
-            c.line_to(rounder(current), height);
-            current -= x_step;
-        }
-        let step = height / 10.0;
-        current = step - 1.0;
-        while current < height - 1. {
-            c.move_to(x_start, rounder(current));
-            c.line_to(width, rounder(current));
-            current += step;
+        if self.style.display_grid {
+            while current > x_start {
+                c.move_to(rounder(current), 0.0);
+                c.line_to(rounder(current), height);
+                current -= x_step;
+            }
+            let step = height / 10.0;
+            current = step - 1.0;
+            while current < height - 1. {
+                c.move_to(x_start, rounder(current));
+                c.line_to(width, rounder(current));
+                current += step;
+            }
+            let _ = c.stroke();
         }
-        let _ = c.stroke();
 
-        c.set_line_width(1.);
+        c.set_line_width(self.style.line_width);
 
         if let Some(ref self_max) = self.max {
             let mut max = if self.keep_max {
@@ -212,60 +870,59 @@ impl Graph {
             };
             let len = self.data[0].len() - 1;
             for x in 0..len {
-                for entry in &self.data {
-                    if entry[x] > max {
-                        max = entry[x];
+                for series in 0..self.data.len() {
+                    let value = self.plotted_value(series, x);
+                    if value > max {
+                        max = value;
                     }
                 }
             }
+            for (_, value) in &self.reference_lines {
+                if *value > max {
+                    max = *value;
+                }
+            }
             if let Some(min) = self.minimum {
                 if min > max {
                     max = min;
                 }
-            } else if let Some(over) = self.overhead {
-                max = max + max * over / 100.;
-            }
-            if !self.data.is_empty() && !self.data[0].is_empty() {
-                let len = self.data[0].len() - 1;
-                let step = (width - 2.0 - x_start) / len as f64;
-                current = x_start + 1.0;
-                let mut index = len;
-                while current > x_start && index > 0 {
-                    for (entry, color) in self.data.iter().zip(self.colors.iter()) {
-                        c.set_source_rgb(color.r, color.g, color.b);
-                        c.move_to(
-                            current + step,
-                            height - entry[index - 1] / max * (height - 1.0),
-                        );
-                        c.line_to(current, height - entry[index] / max * (height - 1.0));
-                        let _ = c.stroke();
-                    }
-                    current += step;
-                    index -= 1;
-                }
             }
+            // The overhead isn't kept, otherwise it'd be added again at each draw.
             if max > *self_max.borrow() || !self.keep_max {
                 *self_max.borrow_mut() = max;
             }
-            self.draw_labels(c, max, height);
+            let max = self.with_overhead(max);
+            self.draw_data(c, x_start, width, height, max);
+            self.draw_reference_lines(c, x_start, width, height, max);
+            self.draw_labels(c, max, height, &foreground, font_size);
         } else if !self.data.is_empty() && !self.data[0].is_empty() {
-            let len = self.data[0].len() - 1;
-            let step = (width - 2.0 - x_start) / (len as f64);
-            current = x_start + 1.0;
-            let mut index = len;
-            while current > x_start && index > 0 {
-                for (entry, color) in self.data.iter().zip(self.colors.iter()) {
-                    c.set_source_rgb(color.r, color.g, color.b);
-                    c.move_to(current + step, height - entry[index - 1] * (height - 1.0));
-                    c.line_to(current, height - entry[index] * (height - 1.0));
-                    let _ = c.stroke();
-                }
-                current += step;
-                index -= 1;
-            }
+            let max = if self.stacked {
+                self.data.len() as f64
+            } else {
+                1.
+            };
+            self.draw_data(c, x_start, width, height, self.with_overhead(max));
             // To be called in last to avoid having to restore state (rotation).
-            self.draw_labels(c, 100., height);
+            self.draw_labels(c, self.with_overhead(100.), height, &foreground, font_size);
+        }
+    }
+
+    /// Renders the graph as it's currently displayed, with its labels, into a PNG file.
+    pub fn save_to_png(&self, path: &Path) -> Result<(), String> {
+        let width = self.area.allocated_width().max(1);
+        let height = self.area.allocated_height().max(1);
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)
+            .map_err(|e| format!("Failed to create the image: {}", e))?;
+        {
+            let c = cairo::Context::new(&surface)
+                .map_err(|e| format!("Failed to create the image: {}", e))?;
+            // The graph itself doesn't draw the background behind the labels.
+            let (_, background) = self.theme_colors();
+            c.set_source_rgb(background.red(), background.green(), background.blue());
+            let _ = c.paint();
+            self.draw(&c, f64::from(width), f64::from(height));
         }
+        write_png(&surface, width, height, path)
     }
 
     pub fn invalidate(&self) {
@@ -350,3 +1007,99 @@ impl Connecter for Rc<RefCell<Graph>> {
         }
     }
 }
+
+/// Returns how long (in seconds) before the most recent timestamped sample each sample was taken,
+/// from the timestamps of the samples, the most recent first. The samples without timestamp (like
+/// the initial values) are placed by interpolating between their timestamped neighbours, or by
+/// extrapolating with the average interval. Returns `None` if there aren't enough timestamps to
+/// know the interval.
+fn sample_ages(timestamps: &[Option<Instant>]) -> Option<Vec<f64>> {
+    let known = timestamps
+        .iter()
+        .enumerate()
+        .filter_map(|(index, timestamp)| timestamp.map(|timestamp| (index, timestamp)))
+        .collect::<Vec<_>>();
+    let (first, newest) = *known.first()?;
+    let (last, oldest) = *known.last()?;
+    let span = newest.duration_since(oldest).as_secs_f64();
+    if last == first || span <= 0. {
+        return None;
+    }
+    let interval = span / (last - first) as f64;
+    let mut ages = vec![0.; timestamps.len()];
+    for (index, age) in ages.iter_mut().enumerate().take(first) {
+        *age = -((first - index) as f64) * interval;
+    }
+    for pair in known.windows(2) {
+        let (from, from_timestamp) = pair[0];
+        let (to, to_timestamp) = pair[1];
+        let from_age = newest.duration_since(from_timestamp).as_secs_f64();
+        let to_age = newest.duration_since(to_timestamp).as_secs_f64();
+        for (index, age) in ages.iter_mut().enumerate().take(to + 1).skip(from) {
+            *age = from_age + (to_age - from_age) * (index - from) as f64 / (to - from) as f64;
+        }
+    }
+    for (index, age) in ages.iter_mut().enumerate().skip(last + 1) {
+        *age = span + (index - last) as f64 * interval;
+    }
+    Some(ages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sample_ages;
+    use std::time::{Duration, Instant};
+
+    // Returns timestamps `seconds` after a common origin, so the bigger the more recent.
+    fn timestamps(seconds: &[Option<u64>]) -> Vec<Option<Instant>> {
+        let origin = Instant::now();
+        seconds
+            .iter()
+            .map(|s| s.map(|s| origin + Duration::from_secs(s)))
+            .collect()
+    }
+
+    #[test]
+    fn check_sample_ages_without_enough_timestamps() {
+        assert_eq!(sample_ages(&timestamps(&[None, None, None])), None);
+        assert_eq!(sample_ages(&timestamps(&[Some(10), None, None])), None);
+        // All the samples were taken at the same time.
+        assert_eq!(sample_ages(&timestamps(&[Some(10), Some(10), None])), None);
+    }
+
+    #[test]
+    fn check_sample_ages_with_all_timestamps() {
+        assert_eq!(
+            sample_ages(&timestamps(&[Some(10), Some(9), Some(8)])),
+            Some(vec![0., 1., 2.])
+        );
+        // Irregular intervals are kept.
+        assert_eq!(
+            sample_ages(&timestamps(&[Some(10), Some(9), Some(5), Some(4)])),
+            Some(vec![0., 1., 5., 6.])
+        );
+    }
+
+    #[test]
+    fn check_sample_ages_with_partial_timestamps() {
+        // The oldest samples are extrapolated with the average interval.
+        assert_eq!(
+            sample_ages(&timestamps(&[Some(10), Some(9), None, None])),
+            Some(vec![0., 1., 2., 3.])
+        );
+        assert_eq!(
+            sample_ages(&timestamps(&[Some(10), Some(6), None])),
+            Some(vec![0., 4., 8.])
+        );
+        // The missing samples between two timestamped ones are interpolated.
+        assert_eq!(
+            sample_ages(&timestamps(&[Some(10), None, None, Some(4)])),
+            Some(vec![0., 2., 4., 6.])
+        );
+        // Same for the most recent ones, which are before the first timestamp.
+        assert_eq!(
+            sample_ages(&timestamps(&[None, Some(10), Some(8)])),
+            Some(vec![-2., 0., 2.])
+        );
+    }
+}
@@ -14,97 +14,253 @@ use gtk::gio::MemoryInputStream;
 use gtk::glib::{Bytes, Cast, IsA, ToVariant};
 use gtk::prelude::{
     AboutDialogExt, BoxExt, ButtonBoxExt, ButtonExt, ContainerExt, DialogExt, EntryExt,
-    GtkApplicationExt, GtkListStoreExt, GtkListStoreExtManual, GtkWindowExt, NotebookExtManual,
-    SearchBarExt, TreeModelExt, TreeSortableExtManual, TreeViewExt, WidgetExt, WidgetExtManual,
+    FileChooserExt, GtkApplicationExt, GtkMenuItemExt, GtkWindowExt, NotebookExt,
+    NotebookExtManual, SearchBarExt, ToggleButtonExt, TreeModelExt, TreeSortableExtManual,
+    TreeStoreExt, TreeStoreExtManual, TreeViewExt, WidgetExt, WidgetExtManual,
 };
 use gtk::{gdk, gio, glib};
 use gtk::{AboutDialog, Dialog, EditableSignals, Entry, Inhibit, MessageDialog};
 
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+mod actions;
 mod color;
 mod display_disk;
+mod display_gpu;
 #[macro_use]
 mod display_sysinfo;
 mod display_network;
 mod display_procs;
+mod file_users_dialog;
+mod global_shortcut;
 mod graph;
+mod mini_view;
 mod network_dialog;
 mod notebook;
 mod process_dialog;
+mod replay;
 mod settings;
 mod utils;
 
+use display_gpu::{DisplayGpu, GpuInfo};
 use display_network::Network;
-use display_procs::{create_and_fill_model, Procs};
+use display_procs::{
+    cpu_change_highlight, cpu_severity_color, create_and_fill_model, create_group_row,
+    highlight_process_changes, is_group_row, memory_change_highlight, memory_percent,
+    memory_severity_color, process_priority, process_thread_count, process_user_name,
+    update_group_row, CpuPreview, LeakDetector, PinnedProcesses, ProcessChanges, ProcessLayout,
+    Procs,
+};
 use display_sysinfo::DisplaySysInfo;
+use mini_view::MiniView;
 use notebook::NoteBook;
 use settings::Settings;
-use utils::format_number;
+use utils::{format_number, format_time, kib_to_bytes};
 
 pub const APPLICATION_NAME: &str = "fr.guillaume_gomez.ProcessViewer";
+const WINDOW_TITLE: &str = "Process viewer";
+// Offset (in pixels) applied to a new process dialog compared to the last opened one.
+const DIALOG_CASCADE_OFFSET: i32 = 30;
+
+// Processes without any I/O have an empty cell to make the active ones easier to spot.
+fn format_disk_cell(bytes: u64) -> String {
+    if bytes > 0 {
+        format_number(bytes)
+    } else {
+        String::new()
+    }
+}
 
-fn update_window(list: &gtk::ListStore, entries: &HashMap<Pid, sysinfo::Process>) {
-    let mut seen: HashSet<Pid> = HashSet::new();
-
-    if let Some(iter) = list.iter_first() {
-        let mut valid = true;
-        while valid {
-            let pid = match list.value(&iter, 0).get::<u32>() {
-                Ok(pid) => Pid::from_u32(pid),
-                _ => {
-                    valid = list.iter_next(&iter);
-                    continue;
-                }
-            };
-            if let Some(p) = entries.get(&(pid)) {
-                let disk_usage = p.disk_usage();
-                let disk_usage = disk_usage.written_bytes + disk_usage.read_bytes;
-                let memory = p.memory() * 1_000;
-                list.set(
-                    &iter,
-                    &[
-                        (2, &format!("{:.1}", p.cpu_usage())),
-                        (3, &format_number(memory)),
-                        (
-                            4,
-                            &if disk_usage > 0 {
-                                format_number(disk_usage)
-                            } else {
-                                String::new()
-                            },
-                        ),
-                        (6, &p.cpu_usage()),
-                        (7, &memory),
-                        (8, &disk_usage),
-                    ],
-                );
+/// Updates the rows under `parent` (and their children), removing the ones of the processes which
+/// are gone, unless they're still highlighted by `changes`. The updated rows are added into `rows`.
+fn update_rows(
+    list: &gtk::TreeStore,
+    parent: Option<&gtk::TreeIter>,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    leak_detector: &LeakDetector,
+    changes: &ProcessChanges,
+    total_memory: u64,
+    rows: &mut HashMap<Pid, gtk::TreeIter>,
+) {
+    let iter = match list.iter_children(parent) {
+        Some(iter) => iter,
+        None => return,
+    };
+    let mut valid = true;
+    while valid {
+        if is_group_row(list, &iter) {
+            // Empty groups are removed by `update_window`.
+            update_rows(
+                list,
+                Some(&iter),
+                entries,
+                leak_detector,
+                changes,
+                total_memory,
+                rows,
+            );
+            valid = list.iter_next(&iter);
+            continue;
+        }
+        let pid = match list.value(&iter, 0).get::<u32>() {
+            Ok(pid) => Pid::from_u32(pid),
+            _ => {
                 valid = list.iter_next(&iter);
-                seen.insert(pid);
-            } else {
-                valid = list.remove(&iter);
+                continue;
             }
+        };
+        if let Some(p) = entries.get(&(pid)) {
+            let disk_usage = p.disk_usage();
+            let memory = kib_to_bytes(p.memory());
+            let memory_percent = memory_percent(p, total_memory);
+            // The highlight only lasts until the next refresh.
+            let cpu_highlight = list
+                .value(&iter, 6)
+                .get::<f32>()
+                .ok()
+                .and_then(|previous| cpu_change_highlight(previous, p.cpu_usage()));
+            let memory_highlight = list
+                .value(&iter, 7)
+                .get::<u64>()
+                .ok()
+                .and_then(|previous| memory_change_highlight(previous, memory));
+            list.set(
+                &iter,
+                &[
+                    (2, &format!("{:.1}", p.cpu_usage())),
+                    (3, &format_number(memory)),
+                    (4, &format_disk_cell(disk_usage.read_bytes)),
+                    (17, &format_disk_cell(disk_usage.written_bytes)),
+                    (6, &p.cpu_usage()),
+                    (7, &memory),
+                    (8, &disk_usage.read_bytes),
+                    (18, &disk_usage.written_bytes),
+                    (14, &process_priority(pid)),
+                    (15, &cpu_highlight),
+                    (16, &memory_highlight),
+                    (21, &cpu_severity_color(p.cpu_usage())),
+                    (22, &memory_severity_color(memory)),
+                    (23, &format!("{:.1}", memory_percent)),
+                    (24, &memory_percent),
+                    (27, &process_thread_count(p)),
+                    (30, &format_time(p.run_time())),
+                    (31, &p.run_time()),
+                    (
+                        12,
+                        &if leak_detector.is_suspected(pid) {
+                            Some("dialog-warning")
+                        } else {
+                            None
+                        },
+                    ),
+                ],
+            );
+            update_rows(
+                list,
+                Some(&iter),
+                entries,
+                leak_detector,
+                changes,
+                total_memory,
+                rows,
+            );
+            rows.insert(pid, iter.clone());
+            valid = list.iter_next(&iter);
+        } else if changes.is_ending(pid) && !list.iter_has_child(&iter) {
+            // The row is removed once its highlight faded out.
+            valid = list.iter_next(&iter);
+        } else {
+            // The children are removed as well, they're added back at the root if they're still
+            // running.
+            valid = list.remove(&iter);
         }
     }
+}
 
-    for (pid, pro) in entries.iter() {
-        if !seen.contains(pid) {
-            create_and_fill_model(
-                list,
-                pid.as_u32(),
-                pro.cmd(),
-                pro.name(),
-                pro.cpu_usage(),
-                pro.memory() * 1_000,
-            );
+/// In tree layout, the new processes are added under their parent when it is displayed. In grouped
+/// layout, they're added under the row of their executable name.
+#[allow(clippy::too_many_arguments)]
+fn update_window(
+    list: &gtk::TreeStore,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    users: &[sysinfo::User],
+    total_memory: u64,
+    leak_detector: &LeakDetector,
+    changes: &ProcessChanges,
+    annotations: &HashMap<Pid, String>,
+    pinned: &PinnedProcesses,
+    layout: ProcessLayout,
+) {
+    let mut rows = HashMap::new();
+    update_rows(
+        list,
+        None,
+        entries,
+        leak_detector,
+        changes,
+        total_memory,
+        &mut rows,
+    );
+
+    let mut new_processes = entries
+        .values()
+        .filter(|p| !rows.contains_key(&p.pid()))
+        .collect::<Vec<_>>();
+    // Parents are started before their children, so they're added first.
+    new_processes.sort_by_key(|p| (p.start_time(), p.pid()));
+    let mut groups = HashMap::new();
+    if layout == ProcessLayout::Grouped {
+        if let Some(iter) = list.iter_children(None) {
+            loop {
+                if is_group_row(list, &iter) {
+                    let name = list.value(&iter, 13).get::<String>().unwrap_or_default();
+                    groups.insert(name, iter.clone());
+                }
+                if !list.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+    }
+    for pro in new_processes {
+        let parent = match layout {
+            ProcessLayout::Flat => None,
+            ProcessLayout::Tree => pro.parent().and_then(|pid| rows.get(&pid)).cloned(),
+            ProcessLayout::Grouped => Some(
+                groups
+                    .entry(pro.name().to_owned())
+                    .or_insert_with(|| create_group_row(list, pro.name()))
+                    .clone(),
+            ),
+        };
+        let annotation = annotations.get(&pro.pid()).map(|a| a.as_str());
+        if let Some(iter) = create_and_fill_model(
+            list,
+            parent.as_ref(),
+            pro,
+            pro.name(),
+            annotation,
+            pinned.contains(pro.pid()),
+            users,
+            total_memory,
+        ) {
+            rows.insert(pro.pid(), iter);
+        }
+    }
+    for iter in groups.values() {
+        if list.iter_has_child(iter) {
+            update_group_row(list, iter, total_memory);
+        } else {
+            list.remove(iter);
         }
     }
 }
@@ -200,10 +356,45 @@ fn run_command<T: IsA<gtk::Window>>(input: &Entry, window: &T, d: &Dialog) {
     m.show_all();
 }
 
+fn show_protected_process_warning<T: IsA<gtk::Window>>(window: &T, name: &str) {
+    let m = MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Warning,
+        gtk::ButtonsType::Ok,
+        &format!(
+            "\"{}\" is a protected process and cannot be killed. You can change the protected \
+             processes in the settings.",
+            name
+        ),
+    );
+    m.set_modal(true);
+    m.connect_response(|dialog, _| {
+        dialog.close();
+    });
+    m.show_all();
+}
+
+fn show_error<T: IsA<gtk::Window>>(window: &T, text: &str) {
+    let m = MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Ok,
+        text,
+    );
+    m.set_modal(true);
+    m.connect_response(|dialog, _| {
+        dialog.close();
+    });
+    m.show_all();
+}
+
 fn create_new_proc_diag(
     process_dialogs: &Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
     pid: Pid,
-    sys: &sysinfo::System,
+    sys: &Arc<Mutex<sysinfo::System>>,
+    settings: &Rc<RefCell<Settings>>,
 ) {
     if let Some(proc_diag) = process_dialogs
         .borrow()
@@ -214,82 +405,245 @@ fn create_new_proc_diag(
         proc_diag.popup.present();
         return;
     }
-    let total_memory = sys.total_memory();
-    if let Some(process) = sys.process(pid) {
-        process_dialogs
-            .borrow_mut()
-            .push(process_dialog::create_process_dialog(process, total_memory));
+    let sys_lock = sys
+        .lock()
+        .expect("failed to lock to create new proc dialog");
+    let total_memory = sys_lock.total_memory();
+    if let Some(process) = sys_lock.process(pid) {
+        // We cascade the new dialog from the last opened one so they don't all stack at the
+        // same position.
+        let last_position = process_dialogs
+            .borrow()
+            .iter()
+            .rev()
+            .find(|x| !x.need_remove())
+            .map(|x| x.popup.position());
+        let user = process_user_name(process, sys_lock.users());
+        let dialog = process_dialog::create_process_dialog(process, &user, total_memory, settings);
+        if let Some((x, y)) = last_position {
+            dialog
+                .popup
+                .move_(x + DIALOG_CASCADE_OFFSET, y + DIALOG_CASCADE_OFFSET);
+        }
+        #[cfg(not(windows))]
+        connect_send_signal(&dialog, process_dialogs, sys, settings);
+        process_dialogs.borrow_mut().push(dialog);
     }
 }
 
+/// Sends the signal selected in the dialog to its process. The dialog is marked as dead once the
+/// process has been asked to end.
+#[cfg(not(windows))]
+fn connect_send_signal(
+    dialog: &process_dialog::ProcDialog,
+    process_dialogs: &Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
+    sys: &Arc<Mutex<sysinfo::System>>,
+    settings: &Rc<RefCell<Settings>>,
+) {
+    use gtk::prelude::ComboBoxExtManual;
+
+    let pid = dialog.pid;
+    dialog.send_signal_button.connect_clicked(
+        glib::clone!(@weak process_dialogs, @weak sys, @weak settings,
+                     @weak dialog.signal_combo as combo,
+                     @weak dialog.popup as popup => move |_| {
+            let (name, signal) = match combo.active() {
+                Some(index) => display_procs::SIGNALS[index as usize],
+                None => return,
+            };
+            let sent = {
+                let sys = sys.lock().expect("failed to lock to send a signal");
+                let process = match sys.process(pid) {
+                    Some(process) => process,
+                    None => return,
+                };
+                if settings.borrow().is_protected(process.pid(), process.name()) {
+                    show_protected_process_warning(&popup, process.name());
+                    return;
+                }
+                let action = format!("send {} to", name);
+                actions::run_process_action(&popup, &action, process.name(), || {
+                    actions::send_signal(process, signal)
+                })
+            };
+            if sent && matches!(signal, Signal::Kill | Signal::Term) {
+                if let Some(dialog) = process_dialogs
+                    .borrow_mut()
+                    .iter_mut()
+                    .find(|x| !x.is_dead && x.pid == pid)
+                {
+                    dialog.set_dead();
+                }
+            }
+        }),
+    );
+}
+
 pub struct RequiredForSettings {
-    process_refresh_timeout: Arc<Mutex<u32>>,
-    network_refresh_timeout: Arc<Mutex<u32>>,
-    system_refresh_timeout: Arc<Mutex<u32>>,
+    process_refresh_timeout: Arc<RefreshTimeout>,
+    network_refresh_timeout: Arc<RefreshTimeout>,
+    system_refresh_timeout: Arc<RefreshTimeout>,
     sys: Arc<Mutex<sysinfo::System>>,
     process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
-    list_store: gtk::ListStore,
+    list_store: gtk::TreeStore,
+    filter_model: gtk::TreeModelFilter,
+    layout: Rc<Cell<ProcessLayout>>,
+    annotations: Rc<RefCell<HashMap<Pid, String>>>,
+    selected_pids: Rc<RefCell<Vec<Pid>>>,
+    cpu_preview: Rc<RefCell<CpuPreview>>,
+    leak_detector: Rc<RefCell<LeakDetector>>,
+    process_changes: Rc<RefCell<ProcessChanges>>,
+    pinned: PinnedProcesses,
     display_tab: Rc<RefCell<DisplaySysInfo>>,
     network_tab: Rc<RefCell<Network>>,
+    mini_view: Option<Rc<MiniView>>,
+    // Only created if a GPU was found.
+    gpu_tab: Option<Rc<DisplayGpu>>,
+    // While set, the refreshed data isn't displayed.
+    paused: Rc<Cell<bool>>,
+    // While set, the process list displays replayed processes instead of the refreshed ones.
+    replaying: Rc<Cell<bool>>,
+}
+
+/// The time (in milliseconds) a refresh thread waits between two refreshes. The waiting threads
+/// are woken up when it's changed from the settings dialog, so the new value is applied to the
+/// current wait too, not only to the next one.
+pub struct RefreshTimeout {
+    ms: Mutex<u32>,
+    changed: Condvar,
 }
 
-fn setup_timeout(rfs: &Rc<RefCell<RequiredForSettings>>) {
+impl RefreshTimeout {
+    fn new(ms: u32) -> Arc<RefreshTimeout> {
+        Arc::new(RefreshTimeout {
+            ms: Mutex::new(ms),
+            changed: Condvar::new(),
+        })
+    }
+
+    pub fn set(&self, ms: u32) {
+        *self.ms.lock().expect("failed to lock refresh timeout") = ms;
+        self.changed.notify_all();
+    }
+
+    /// Sleeps until the timeout elapsed.
+    fn wait(&self, kind: &str) {
+        let start = Instant::now();
+        let mut ms = self
+            .ms
+            .lock()
+            .unwrap_or_else(|_| panic!("failed to lock {} refresh mutex", kind));
+        loop {
+            let duration = Duration::from_millis(*ms as _);
+            let elapsed = start.elapsed();
+            if elapsed >= duration {
+                break;
+            }
+            ms = self
+                .changed
+                .wait_timeout(ms, duration - elapsed)
+                .unwrap_or_else(|_| panic!("failed to wait for {} refresh", kind))
+                .0;
+        }
+    }
+}
+
+/// Returns the sender used to update the process list. Sending `true` updates it even when paused.
+fn setup_timeout(rfs: &Rc<RefCell<RequiredForSettings>>) -> glib::Sender<bool> {
     let (ready_tx, ready_rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
     let rfs = rfs.borrow();
 
     let sys = &rfs.sys;
     let process_dialogs = &rfs.process_dialogs;
     let list_store = &rfs.list_store;
+    let selected_pids = &rfs.selected_pids;
+    let cpu_preview = &rfs.cpu_preview;
+    let leak_detector = &rfs.leak_detector;
+    let process_changes = &rfs.process_changes;
+    let pinned = rfs.pinned.clone();
+    let annotations = &rfs.annotations;
+    let layout = &rfs.layout;
+    let paused = &rfs.paused;
+    let replaying = &rfs.replaying;
     let process_refresh_timeout = &rfs.process_refresh_timeout;
 
     thread::spawn(
         glib::clone!(@weak sys, @strong ready_tx, @weak process_refresh_timeout => move || {
             loop {
-                let sleep_dur = Duration::from_millis(
-                    *process_refresh_timeout.lock().expect("failed to lock process refresh mutex") as _);
-                thread::sleep(sleep_dur);
+                process_refresh_timeout.wait("process");
                 sys.lock().expect("failed to lock to refresh processes").refresh_processes();
                 ready_tx.send(false).expect("failed to send data through process refresh channel");
             }
         }),
     );
 
-    ready_rx.attach(None,
-        glib::clone!(@weak sys, @weak list_store, @weak process_dialogs => @default-return glib::Continue(true), move |_: bool| {
-        // first part, deactivate sorting
-        let sorted = TreeSortableExtManual::sort_column_id(&list_store);
-        list_store.set_unsorted();
-
-        let mut to_remove = 0;
-        let mut dialogs = process_dialogs.borrow_mut();
-
-        if let Ok(sys) = sys.lock() {
-            // we update the tree view
-            update_window(&list_store, sys.processes());
-
-            // we re-enable the sorting
-            if let Some((col, order)) = sorted {
-                list_store.set_sort_column_id(col, order);
-            }
-            for dialog in dialogs.iter_mut().filter(|x| !x.is_dead) {
-                // TODO: check if the process name matches the PID too!
-                if let Some(process) = sys.processes().get(&dialog.pid) {
-                    dialog.update(process);
-                } else {
-                    dialog.set_dead();
+    ready_rx.attach(
+        None,
+        glib::clone!(@weak sys, @weak list_store, @weak process_dialogs, @weak selected_pids,
+                         @weak cpu_preview, @weak leak_detector, @weak process_changes,
+                         @weak annotations, @weak layout, @weak paused, @weak replaying,
+                         @strong pinned => @default-return glib::Continue(true), move |forced: bool| {
+            if (paused.get() && !forced) || replaying.get() {
+                return glib::Continue(true);
+            }
+            // first part, deactivate sorting
+            let sorted = TreeSortableExtManual::sort_column_id(&list_store);
+            list_store.set_unsorted();
+
+            let mut to_remove = 0;
+            let mut dialogs = process_dialogs.borrow_mut();
+
+            if let Ok(sys) = sys.lock() {
+                // we update the tree view
+                let mut leak_detector = leak_detector.borrow_mut();
+                leak_detector.update(sys.processes());
+                process_changes.borrow_mut().update(sys.processes());
+                // A pid can be reused, the new process isn't pinned.
+                pinned
+                    .pids
+                    .borrow_mut()
+                    .retain(|pid| sys.processes().contains_key(pid));
+                update_window(
+                    &list_store,
+                    sys.processes(),
+                    sys.users(),
+                    sys.total_memory(),
+                    &leak_detector,
+                    &process_changes.borrow(),
+                    &annotations.borrow(),
+                    &pinned,
+                    layout.get(),
+                );
+                highlight_process_changes(&process_changes, &list_store);
+                cpu_preview
+                    .borrow_mut()
+                    .update(sys.processes(), selected_pids.borrow().first().copied());
+
+                // we re-enable the sorting
+                if let Some((col, order)) = sorted {
+                    list_store.set_sort_column_id(col, order);
                 }
-                if dialog.need_remove() {
-                    to_remove += 1;
+                for dialog in dialogs.iter_mut().filter(|x| !x.is_dead) {
+                    // TODO: check if the process name matches the PID too!
+                    if let Some(process) = sys.processes().get(&dialog.pid) {
+                        dialog.update(process);
+                    } else {
+                        dialog.set_dead();
+                    }
+                    if dialog.need_remove() {
+                        to_remove += 1;
+                    }
                 }
+            } else {
+                panic!("failed to lock sys to refresh UI");
             }
-        } else {
-            panic!("failed to lock sys to refresh UI");
-        }
-        if to_remove > 0 {
-            dialogs.retain(|x| !x.need_remove());
-        }
-        glib::Continue(true)
-    }));
+            if to_remove > 0 {
+                dialogs.retain(|x| !x.need_remove());
+            }
+            glib::Continue(true)
+        }),
+    );
+    ready_tx
 }
 
 fn setup_network_timeout(rfs: &Rc<RefCell<RequiredForSettings>>) {
@@ -299,13 +653,12 @@ fn setup_network_timeout(rfs: &Rc<RefCell<RequiredForSettings>>) {
     let network_refresh_timeout = &rfs.network_refresh_timeout;
     let network_tab = &rfs.network_tab;
     let sys = &rfs.sys;
+    let paused = &rfs.paused;
 
     thread::spawn(
         glib::clone!(@weak sys, @strong ready_tx, @weak network_refresh_timeout => move || {
             loop {
-                let sleep_dur = Duration::from_millis(
-                    *network_refresh_timeout.lock().expect("failed to lock networks refresh mutex") as _);
-                thread::sleep(sleep_dur);
+                network_refresh_timeout.wait("networks");
                 sys.lock().expect("failed to lock to refresh networks").refresh_networks();
                 ready_tx.send(false).expect("failed to send data through networks refresh channel");
             }
@@ -313,28 +666,75 @@ fn setup_network_timeout(rfs: &Rc<RefCell<RequiredForSettings>>) {
     );
 
     ready_rx.attach(None,
-        glib::clone!(@weak sys, @weak network_tab => @default-panic, move |_: bool| {
+        glib::clone!(@weak sys, @weak network_tab, @weak paused => @default-panic, move |_: bool| {
+            if paused.get() {
+                return glib::Continue(true);
+            }
             network_tab.borrow_mut().update_networks(&*sys.lock().expect("failed to lock to update networks"));
             glib::Continue(true)
         })
     );
 }
 
-fn setup_system_timeout(rfs: &Rc<RefCell<RequiredForSettings>>, settings: &Rc<RefCell<Settings>>) {
+fn setup_gpu_timeout(rfs: &Rc<RefCell<RequiredForSettings>>) {
+    let rfs = rfs.borrow();
+    let gpu_tab = match rfs.gpu_tab {
+        Some(ref gpu_tab) => gpu_tab,
+        None => return,
+    };
+    let (ready_tx, ready_rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+    let system_refresh_timeout = &rfs.system_refresh_timeout;
+    let paused = &rfs.paused;
+
+    // `nvidia-smi` can take some time to answer so it's not run in the main thread.
+    thread::spawn(
+        glib::clone!(@strong ready_tx, @weak system_refresh_timeout => move || {
+            loop {
+                system_refresh_timeout.wait("gpu");
+                ready_tx
+                    .send(display_gpu::query_gpus())
+                    .expect("failed to send data through gpu refresh channel");
+            }
+        }),
+    );
+
+    ready_rx.attach(
+        None,
+        glib::clone!(@weak gpu_tab, @weak paused => @default-panic, move |gpus: Vec<GpuInfo>| {
+            if !paused.get() {
+                gpu_tab.update(&gpus);
+            }
+            glib::Continue(true)
+        }),
+    );
+}
+
+/// Returns the sender used to update the system information. Sending `true` updates it even when
+/// paused.
+fn setup_system_timeout(
+    rfs: &Rc<RefCell<RequiredForSettings>>,
+    settings: &Rc<RefCell<Settings>>,
+) -> glib::Sender<bool> {
     let (ready_tx, ready_rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
     let rfs = rfs.borrow();
 
     let system_refresh_timeout = &rfs.system_refresh_timeout;
     let sys = &rfs.sys;
     let display_tab = &rfs.display_tab;
+    let mini_view = rfs.mini_view.clone();
+    let paused = &rfs.paused;
 
     thread::spawn(
         glib::clone!(@weak sys, @strong ready_tx, @weak system_refresh_timeout => move || {
             loop {
-                let sleep_dur = Duration::from_millis(
-                    *system_refresh_timeout.lock().expect("failed to lock system refresh mutex") as _);
-                thread::sleep(sleep_dur);
-                sys.lock().expect("failed to lock to refresh system").refresh_system();
+                system_refresh_timeout.wait("system");
+                {
+                    let mut sys = sys.lock().expect("failed to lock to refresh system");
+                    sys.refresh_system();
+                    // The whole list is reloaded to take into account the (un)mounted disks.
+                    sys.refresh_disks_list();
+                }
                 ready_tx.send(false).expect("failed to send data through system refresh channel");
             }
         }),
@@ -342,20 +742,81 @@ fn setup_system_timeout(rfs: &Rc<RefCell<RequiredForSettings>>, settings: &Rc<Re
 
     ready_rx.attach(
         None,
-        glib::clone!(@weak sys, @weak display_tab, @weak settings => @default-panic, move |_: bool| {
+        glib::clone!(@weak sys, @weak display_tab, @weak settings, @weak paused
+                     => @default-panic, move |forced: bool| {
+            if paused.get() && !forced {
+                return glib::Continue(true);
+            }
             let mut info = display_tab.borrow_mut();
             let sys = sys.lock().expect("failed to lock to update system");
-            let display_fahrenheit = settings.borrow().display_fahrenheit;
+            let settings = settings.borrow();
 
-            info.update_system_info(&*sys, display_fahrenheit);
-            info.update_system_info_display(&*sys);
+            info.update_system_info(&*sys, settings.display_fahrenheit);
+            info.update_system_info_display(&*sys, &settings);
+            update_window_title(&*sys, &settings);
+            if let Some(ref mini_view) = mini_view {
+                mini_view.update(&*sys, settings.display_fahrenheit);
+            }
             glib::Continue(true)
         }),
     );
+    ready_tx
+}
+
+/// Displays the CPU and memory usage in the title of the main window, so they're visible in the
+/// taskbar, unless it's disabled in the settings.
+pub fn update_window_title(sys: &sysinfo::System, settings: &Settings) {
+    let window = match utils::get_main_window() {
+        Some(window) => window,
+        None => return,
+    };
+    if !settings.usage_in_window_title {
+        window.set_title(WINDOW_TITLE);
+        return;
+    }
+    window.set_title(&format!(
+        "{} — CPU {:.0}% RAM {} / {}",
+        WINDOW_TITLE,
+        sys.global_processor_info().cpu_usage(),
+        format_number(kib_to_bytes(sys.used_memory())),
+        format_number(kib_to_bytes(sys.total_memory())),
+    ));
+}
+
+// The refresh intervals cannot be shorter than this (in milliseconds).
+const MIN_REFRESH_INTERVAL: u32 = 500;
+
+#[derive(Clone, Default)]
+struct CommandLineOptions {
+    // Iconify the window on startup.
+    start_minimized: bool,
+    // Only display a compact summary of the system.
+    mini: bool,
+    // Filter applied to the process list on startup.
+    filter: Option<String>,
+    // Refresh interval (in milliseconds) used instead of the ones from the settings, which aren't
+    // modified.
+    refresh_ms: Option<u32>,
+    // Process lists exported to CSV, displayed one at a time instead of the live processes.
+    replay: Vec<PathBuf>,
 }
 
-fn build_ui(application: &gtk::Application) {
-    let settings = Settings::load();
+fn save_window_geometry(window: &gtk::ApplicationWindow, settings: &mut Settings) {
+    let (width, height) = window.size();
+    let (x, y) = window.position();
+    settings.window_width = width;
+    settings.window_height = height;
+    settings.window_x = x;
+    settings.window_y = y;
+    settings.save();
+}
+
+fn build_ui(application: &gtk::Application, options: CommandLineOptions) {
+    let settings = Rc::new(RefCell::new(Settings::load()));
+    let start_minimized = options.start_minimized || settings.borrow().start_minimized;
+    graph::apply_style_to_all_graphs(&settings.borrow().graph_style);
+    graph::apply_history_len_to_all_graphs(settings.borrow().graph_history_len.max(1));
+    utils::set_use_binary_units(settings.borrow().use_binary_units);
 
     let menu = gio::Menu::new();
     let menu_bar = gio::Menu::new();
@@ -363,11 +824,13 @@ fn build_ui(application: &gtk::Application) {
     let settings_menu = gio::Menu::new();
 
     menu.append(Some("Launch new executable"), Some("app.new-task"));
+    menu.append(
+        Some("Find processes using a file or port"),
+        Some("app.find-file-users"),
+    );
+    menu.append(Some("Save all graphs"), Some("app.save-graphs"));
     menu.append(Some("Quit"), Some("app.quit"));
     let quit = gio::SimpleAction::new("quit", None);
-    quit.connect_activate(glib::clone!(@weak application => move |_,_| {
-        application.quit();
-    }));
     application.set_accels_for_action("app.quit", &["<Primary>Q"]);
 
     settings_menu.append(Some("Display temperature in °F"), Some("app.temperature"));
@@ -386,43 +849,211 @@ fn build_ui(application: &gtk::Application) {
     let mut sys =
         sysinfo::System::new_with_specifics(RefreshKind::everything().without_users_list());
     let mut note = NoteBook::new();
-    let procs = Procs::new(sys.processes(), &mut note, &window);
-    let current_pid = Rc::clone(&procs.current_pid);
+    // The users list is only loaded once, the processes of the users created later display their
+    // uid instead of their name.
+    sys.refresh_users_list();
+    let procs = Procs::new(
+        sys.processes(),
+        sys.users(),
+        sys.total_memory(),
+        &mut note,
+        &window,
+        &settings,
+    );
+    let selected_pids = Rc::clone(&procs.selected_pids);
     let info_button = procs.info_button.clone();
 
-    window.set_title("Process viewer");
-    window.set_position(gtk::WindowPosition::Center);
-    // To silence the annying warning:
-    // "(.:2257): Gtk-WARNING **: Allocating size to GtkWindow 0x7f8a31038290 without
-    // calling gtk_widget_get_preferred_width/height(). How does the code know the size to
-    // allocate?"
-    window.preferred_width();
-    window.set_default_size(630, 700);
+    window.set_title(WINDOW_TITLE);
+    {
+        let settings = settings.borrow();
+        if settings.window_x >= 0 && settings.window_y >= 0 {
+            window.move_(settings.window_x, settings.window_y);
+        } else {
+            window.set_position(gtk::WindowPosition::Center);
+        }
+        // To silence the annying warning:
+        // "(.:2257): Gtk-WARNING **: Allocating size to GtkWindow 0x7f8a31038290 without
+        // calling gtk_widget_get_preferred_width/height(). How does the code know the size to
+        // allocate?"
+        window.preferred_width();
+        window.set_default_size(settings.window_width, settings.window_height);
+    }
 
     sys.refresh_all();
     let sys = Arc::new(Mutex::new(sys));
-    procs
-        .kill_button
-        .connect_clicked(glib::clone!(@weak current_pid, @weak sys => move |_| {
+    let display_tab = DisplaySysInfo::new(&sys, &mut note, &settings);
+
+    procs.kill_button.connect_clicked(
+        glib::clone!(@weak selected_pids, @weak sys, @weak settings, @weak window => move |_| {
             let sys = sys.lock().expect("failed to lock to kill a process");
-            if let Some(process) = current_pid.get().and_then(|pid| sys.process(pid)) {
-                process.kill();
+            let processes = selected_pids
+                .borrow()
+                .iter()
+                .filter_map(|pid| sys.process(*pid))
+                .collect::<Vec<_>>();
+            // Nothing is ended if one of the selected processes is protected.
+            if let Some(process) = processes
+                .iter()
+                .find(|process| settings.borrow().is_protected(process.pid(), process.name()))
+            {
+                show_protected_process_warning(&window, process.name());
+                return;
             }
-        }));
+            let question = match processes.as_slice() {
+                [] => return,
+                [process] => format!(
+                    "Do you really want to end \"{}\" ({})?",
+                    process.name(),
+                    process.pid()
+                ),
+                _ => format!("Do you really want to end these {} processes?", processes.len()),
+            };
+            let pids = processes.iter().map(|process| process.pid()).collect::<Vec<_>>();
+            actions::confirm_process_action(
+                &window,
+                &question,
+                glib::clone!(@weak sys, @weak window => move || {
+                    let sys = sys.lock().expect("failed to lock to kill a process");
+                    // The processes might have ended while the user was answering.
+                    for process in pids.iter().filter_map(|pid| sys.process(*pid)) {
+                        actions::run_process_action(&window, "end", process.name(), || {
+                            actions::kill(process)
+                        });
+                    }
+                }),
+            );
+        }),
+    );
 
-    let display_tab = DisplaySysInfo::new(&sys, &mut note, &settings);
+    procs.end_task_item.connect_activate(
+        glib::clone!(@weak procs.kill_button as kill_button => move |_| {
+            kill_button.clicked();
+        }),
+    );
+    #[cfg(not(windows))]
+    {
+        for (item, signal) in &procs.signal_items {
+            let signal = *signal;
+            let action = format!("send {} to", item.label().unwrap_or_default());
+            item.connect_activate(
+                glib::clone!(@weak selected_pids, @weak sys, @weak settings, @weak window => move |_| {
+                    let sys = sys.lock().expect("failed to lock to send a signal");
+                    for pid in selected_pids.borrow().iter() {
+                        let process = match sys.process(*pid) {
+                            Some(process) => process,
+                            None => continue,
+                        };
+                        if settings.borrow().is_protected(process.pid(), process.name()) {
+                            show_protected_process_warning(&window, process.name());
+                            continue;
+                        }
+                        actions::run_process_action(&window, &action, process.name(), || {
+                            actions::send_signal(process, signal)
+                        });
+                    }
+                }),
+            );
+        }
+    }
+
+    // Switching between the layouts rebuilds the whole process list. When going from the tree
+    // layout to the grouped one (or the other way around), it's only rebuilt once the new one is
+    // set.
+    for button in [&procs.tree_button, &procs.group_button] {
+        button.connect_toggled(
+            glib::clone!(@weak sys, @weak procs.list_store as list_store,
+                         @weak procs.left_tree as left_tree,
+                         @weak procs.leak_detector as leak_detector,
+                         @weak procs.process_changes as process_changes,
+                         @weak procs.annotations as annotations,
+                         @weak procs.layout as layout,
+                         @strong procs.pinned as pinned => move |button| {
+                if !button.is_active() && layout.get() != ProcessLayout::Flat {
+                    return;
+                }
+                let sys = sys.lock().expect("failed to lock to rebuild the process list");
+                list_store.clear();
+                update_window(
+                    &list_store,
+                    sys.processes(),
+                    sys.users(),
+                    sys.total_memory(),
+                    &leak_detector.borrow(),
+                    &process_changes.borrow(),
+                    &annotations.borrow(),
+                    &pinned,
+                    layout.get(),
+                );
+                // In grouped layout, the groups stay collapsed so only the executables are listed.
+                if layout.get() == ProcessLayout::Tree {
+                    left_tree.expand_all();
+                }
+            }),
+        );
+    }
 
-    let settings = Rc::new(RefCell::new(settings));
     let network_tab = Rc::new(RefCell::new(Network::new(&mut note, &window, &sys)));
+    let gpus = display_gpu::query_gpus();
+    let gpu_tab = if gpus.is_empty() {
+        None
+    } else {
+        Some(Rc::new(DisplayGpu::new(&gpus, &mut note)))
+    };
     display_disk::create_disk_info(&sys, &mut note);
 
     let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
     let display_tab = Rc::new(RefCell::new(display_tab));
 
+    // The data keeps being refreshed while paused so the display is up to date when resuming.
+    let paused = Rc::new(Cell::new(false));
+    let pause_button = gtk::ToggleButton::with_label("Pause");
+    pause_button.set_tooltip_text(Some("Stop updating the displayed data"));
+    pause_button.connect_toggled(glib::clone!(@weak paused => move |button| {
+        paused.set(button.is_active());
+    }));
+    pause_button.show();
+    if !options.replay.is_empty() {
+        match replay::create_replay_bar(&options.replay, &procs.list_store) {
+            Ok(replay_bar) => {
+                procs
+                    .vertical_layout
+                    .pack_start(&replay_bar, false, false, 0);
+                procs.vertical_layout.reorder_child(&replay_bar, 0);
+                // The other tabs keep displaying the live data.
+                procs.start_replay(replay::UNAVAILABLE_COLUMNS);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    note.notebook
+        .set_action_widget(&pause_button, gtk::PackType::End);
+    note.set_reorderable(true);
+    note.set_detachable(true);
+    // Connected once all the tabs were added so adding them doesn't overwrite the saved tab.
+    note.notebook
+        .connect_switch_page(glib::clone!(@weak settings => move |tabs, page, _| {
+            if let Some(title) = notebook::page_title(tabs, page) {
+                let mut settings = settings.borrow_mut();
+                settings.main_window_tab_title = title;
+                settings.save();
+            }
+        }));
+
     // I think it's now useless to have this one...
     v_box.pack_start(&note.notebook, true, true, 0);
 
+    let mini_view = if options.mini {
+        let mini_view = MiniView::new();
+        v_box.pack_start(&mini_view.grid, true, true, 0);
+        note.notebook.set_no_show_all(true);
+        note.notebook.hide();
+        window.set_default_size(220, 150);
+        Some(Rc::new(mini_view))
+    } else {
+        None
+    };
+
     window.add(&v_box);
 
     let process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>> =
@@ -430,19 +1061,61 @@ fn build_ui(application: &gtk::Application) {
     let list_store = procs.list_store.clone();
 
     let rfs = Rc::new(RefCell::new(RequiredForSettings {
-        process_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_processes_rate)),
-        network_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_network_rate)),
-        system_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_system_rate)),
+        process_refresh_timeout: RefreshTimeout::new(
+            options
+                .refresh_ms
+                .unwrap_or(settings.borrow().refresh_processes_rate),
+        ),
+        network_refresh_timeout: RefreshTimeout::new(
+            options
+                .refresh_ms
+                .unwrap_or(settings.borrow().refresh_network_rate),
+        ),
+        system_refresh_timeout: RefreshTimeout::new(
+            options
+                .refresh_ms
+                .unwrap_or(settings.borrow().refresh_system_rate),
+        ),
         sys: sys.clone(),
         process_dialogs: process_dialogs.clone(),
         list_store,
+        filter_model: procs.filter_model.clone(),
+        layout: procs.layout.clone(),
+        annotations: procs.annotations.clone(),
+        selected_pids: selected_pids.clone(),
+        cpu_preview: procs.cpu_preview.clone(),
+        leak_detector: procs.leak_detector.clone(),
+        process_changes: procs.process_changes.clone(),
+        pinned: procs.pinned.clone(),
         display_tab,
         network_tab: network_tab.clone(),
+        mini_view,
+        gpu_tab,
+        paused,
+        replaying: procs.replaying.clone(),
     }));
 
-    setup_timeout(&rfs);
+    let process_ready_tx = setup_timeout(&rfs);
     setup_network_timeout(&rfs);
-    setup_system_timeout(&rfs, &settings);
+    let system_ready_tx = setup_system_timeout(&rfs, &settings);
+    setup_gpu_timeout(&rfs);
+
+    // The refresh threads aren't involved so their cadence doesn't change.
+    procs
+        .refresh_button
+        .connect_clicked(glib::clone!(@weak sys => move |_| {
+            {
+                let mut sys = sys.lock().expect("failed to lock to refresh now");
+                sys.refresh_processes();
+                sys.refresh_system();
+            }
+            process_ready_tx
+                .send(true)
+                .expect("failed to send data through process refresh channel");
+            system_ready_tx
+                .send(true)
+                .expect("failed to send data through system refresh channel");
+        }));
 
     let settings_action = gio::SimpleAction::new("settings", None);
     settings_action.connect_activate(glib::clone!(@weak settings, @weak rfs => move |_, _| {
@@ -450,25 +1123,43 @@ fn build_ui(application: &gtk::Application) {
     }));
 
     info_button.connect_clicked(
-        glib::clone!(@weak current_pid, @weak process_dialogs, @weak sys => move |_| {
-                if let Some(pid) = current_pid.get() {
-                    create_new_proc_diag(&process_dialogs, pid, &*sys.lock().expect("failed to lock to create new proc dialog"));
+        glib::clone!(@weak selected_pids, @weak process_dialogs, @weak sys, @weak settings => move |_| {
+                for pid in selected_pids.borrow().iter() {
+                    create_new_proc_diag(&process_dialogs, *pid, &sys, &settings);
                 }
             }
         ),
     );
 
-    procs
-        .left_tree
-        .connect_row_activated(glib::clone!(@weak sys => move |tree_view, path, _| {
+    procs.left_tree.connect_row_activated(
+        glib::clone!(@weak sys, @weak settings, @weak procs.replaying as replaying
+                     => move |tree_view, path, _| {
                 let model = tree_view.model().expect("couldn't get model");
                 let iter = model.iter(path).expect("couldn't get iter");
+                if is_group_row(&model, &iter) {
+                    if tree_view.row_expanded(path) {
+                        tree_view.collapse_row(path);
+                    } else {
+                        tree_view.expand_row(path, false);
+                    }
+                    return;
+                }
+                // The replayed processes aren't the running ones.
+                if replaying.get() {
+                    return;
+                }
                 let pid = model.value(&iter, 0)
                                .get::<u32>()
                                .expect("Model::get failed");
-                create_new_proc_diag(&process_dialogs, Pid::from_u32(pid), &*sys.lock().expect("failed to lock to create new proc dialog (from tree)"));
+                create_new_proc_diag(
+                    &process_dialogs,
+                    Pid::from_u32(pid),
+                    &sys,
+                    &settings,
+                );
             }
-        ));
+        ),
+    );
 
     let about = gio::SimpleAction::new("about", None);
     about.connect_activate(glib::clone!(@weak window => move |_, _| {
@@ -497,6 +1188,62 @@ fn build_ui(application: &gtk::Application) {
         p.show_all();
     }));
 
+    let find_file_users = gio::SimpleAction::new("find-file-users", None);
+    find_file_users.connect_activate(
+        glib::clone!(@weak window, @weak procs.left_tree as process_tree, @weak sys => move |_, _| {
+            file_users_dialog::show_file_users_dialog(&window, &process_tree, &sys);
+        }),
+    );
+
+    let save_graphs = gio::SimpleAction::new("save-graphs", None);
+    save_graphs.connect_activate(glib::clone!(@weak window, @weak rfs => move |_, _| {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Save all graphs"),
+            Some(&window),
+            gtk::FileChooserAction::Save,
+            &[("Cancel", gtk::ResponseType::Cancel), ("Save", gtk::ResponseType::Accept)],
+        );
+        dialog.set_current_name("graphs.png");
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.connect_response(glib::clone!(@weak window, @weak rfs => move |dialog, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(path) = dialog.filename() {
+                    if let Err(e) = rfs.borrow().display_tab.borrow().save_graphs(&path) {
+                        show_error(&window, &e);
+                    }
+                }
+            }
+            dialog.close();
+        }));
+        dialog.show_all();
+    }));
+
+    procs.export_button.connect_clicked(
+        glib::clone!(@weak window, @weak procs.left_tree as left_tree => move |_| {
+            let dialog = gtk::FileChooserDialog::with_buttons(
+                Some("Export the process list"),
+                Some(&window),
+                gtk::FileChooserAction::Save,
+                &[("Cancel", gtk::ResponseType::Cancel), ("Save", gtk::ResponseType::Accept)],
+            );
+            dialog.set_current_name("processes.csv");
+            dialog.set_do_overwrite_confirmation(true);
+            dialog.connect_response(
+                glib::clone!(@weak window, @weak left_tree => move |dialog, response| {
+                    if response == gtk::ResponseType::Accept {
+                        if let (Some(path), Some(model)) = (dialog.filename(), left_tree.model()) {
+                            if let Err(e) = display_procs::export_to_csv(&model, &path) {
+                                show_error(&window, &e);
+                            }
+                        }
+                    }
+                    dialog.close();
+                }),
+            );
+            dialog.show_all();
+        }),
+    );
+
     let new_task = gio::SimpleAction::new("new-task", None);
     new_task.connect_activate(glib::clone!(@weak window => move |_, _| {
         let dialog = gtk::Dialog::with_buttons(
@@ -597,10 +1344,32 @@ fn build_ui(application: &gtk::Application) {
     application.add_action(&temperature);
     application.add_action(&settings_action);
     application.add_action(&new_task);
+    application.add_action(&find_file_users);
+    application.add_action(&save_graphs);
     application.add_action(&quit);
 
     window.set_widget_name(utils::MAIN_WINDOW_NAME);
 
+    // The mini view has its own size, which shouldn't replace the one of the normal window.
+    let save_geometry = !options.mini;
+    window.connect_delete_event(
+        glib::clone!(@weak settings => @default-return Inhibit(false), move |window, _| {
+            if save_geometry {
+                save_window_geometry(window, &mut settings.borrow_mut());
+            }
+            Inhibit(false)
+        }),
+    );
+    // Quitting doesn't close the window so its geometry has to be saved here too.
+    quit.connect_activate(
+        glib::clone!(@weak application, @weak window, @weak settings => move |_, _| {
+            if save_geometry {
+                save_window_geometry(&window, &mut settings.borrow_mut());
+            }
+            application.quit();
+        }),
+    );
+
     window.add_events(gdk::EventMask::STRUCTURE_MASK);
     // TODO: ugly way to resize drawing area, I should find a better way
     window.connect_configure_event(move |w, _| {
@@ -615,61 +1384,159 @@ fn build_ui(application: &gtk::Application) {
         false
     });
 
-    application.connect_activate(glib::clone!(@weak procs.filter_entry as filter_entry, @weak network_tab, @weak window => move |_| {
-        window.show_all();
-        filter_entry.hide();
-        network_tab.borrow().filter_entry.hide();
-        window.present();
-    }));
+    {
+        let global_shortcut = &settings.borrow().global_shortcut;
+        if !global_shortcut.is_empty() {
+            global_shortcut::register(application, window.upcast_ref(), global_shortcut);
+        }
+    }
 
+    // Only the first activation can start minimized, the next ones are explicit requests to show
+    // the window.
+    let start_minimized = Cell::new(start_minimized);
+    // The tab used last is selected on the first activation, once the pages are visible.
+    let main_window_tab = Cell::new(Some(settings.borrow().main_window_tab_title.clone()));
+    // Same for the filter given on the command line.
+    let initial_filter = Cell::new(options.filter);
+    let note = Rc::new(note);
+    application.connect_activate(glib::clone!(
+        @weak procs.filter_entry as filter_entry, @weak procs.search_bar as search_bar,
+        @weak network_tab, @weak window, @strong note => move |_| {
+            window.show_all();
+            filter_entry.hide();
+            network_tab.borrow().filter_entry.hide();
+            if let Some(tab) = main_window_tab.take() {
+                note.set_current_page_by_title(&tab);
+            }
+            if let Some(filter) = initial_filter.take() {
+                filter_entry.set_text(&filter);
+                filter_entry.show_all();
+                search_bar.set_search_mode(true);
+            }
+            window.present();
+            if start_minimized.replace(false) {
+                // The updates keep running while the window is iconified.
+                window.iconify();
+            }
+        }
+    ));
+
+    // The shortcuts depend on the displayed page, which can also be in its own window once its
+    // tab is detached.
+    let procs = Rc::new(procs);
     window.connect_key_press_event(
-        glib::clone!(@weak note.notebook as notebook => @default-return Inhibit(false), move |win, key| {
-            let current_page = notebook.current_page();
-            if current_page == Some(0) || current_page == Some(2) {
-                // the process list
-                if key.keyval() == gdk::keys::constants::Escape {
-                    if current_page == Some(0) {
-                        procs.hide_filter();
-                    } else {
-                        network_tab.borrow().hide_filter();
-                    }
-                } else if current_page == Some(0) {
-                    let ret = procs.search_bar.handle_event(key);
-                    if !procs.filter_entry.text().is_empty() {
-                        procs.filter_entry.show_all();
-                        if win.focused_widget()
-                            != Some(procs.filter_entry.clone().upcast::<gtk::Widget>())
-                        {
-                            win.set_focus(Some(&procs.filter_entry));
-                        }
-                    }
-                    return Inhibit(ret);
-                } else {
-                    let network = network_tab.borrow();
-                    let ret = network.search_bar.handle_event(key);
-                    if !network.filter_entry.text().is_empty() {
-                        network.filter_entry.show_all();
-                        if win.focused_widget()
-                            != Some(network.filter_entry.clone().upcast::<gtk::Widget>())
-                        {
-                            win.set_focus(Some(&network.filter_entry));
-                        }
-                    }
-                    return Inhibit(ret);
-                }
+        glib::clone!(@weak note, @strong procs, @weak network_tab => @default-return Inhibit(false),
+                     move |win, key| {
+            let page = note.current_page().and_then(|page| note.notebook.nth_page(Some(page)));
+            match page {
+                Some(page) => handle_list_shortcuts(win, &page, key, &procs, &network_tab.borrow()),
+                None => Inhibit(false),
             }
-            Inhibit(false)
         }),
     );
+    note.connect_tab_detached(
+        glib::clone!(@strong procs, @weak network_tab => move |window, page| {
+            let page = page.clone();
+            window.connect_key_press_event(
+                glib::clone!(@strong procs, @weak network_tab, @strong page
+                             => @default-return Inhibit(false), move |win, key| {
+                    handle_list_shortcuts(win, &page, key, &procs, &network_tab.borrow())
+                }),
+            );
+        }),
+    );
+}
+
+/// Handles the shortcuts of the process list and of the network list. `page` is the notebook page
+/// displayed in `win`.
+fn handle_list_shortcuts<W: IsA<gtk::Window>>(
+    win: &W,
+    page: &gtk::Widget,
+    key: &gdk::EventKey,
+    procs: &Procs,
+    network: &Network,
+) -> Inhibit {
+    let is_procs_page = *page == procs.vertical_layout.clone().upcast::<gtk::Widget>();
+    let is_network_page = *page == network.page.clone().upcast::<gtk::Widget>();
+    if is_procs_page
+        && key.state().contains(gdk::ModifierType::CONTROL_MASK)
+        && key.keyval().to_lower() == gdk::keys::constants::f
+    {
+        // Ctrl+F reveals and focuses the filter entry, or hides it if it already has the
+        // focus.
+        if procs.filter_entry.has_focus() {
+            procs.hide_filter();
+        } else {
+            procs.filter_entry.show_all();
+            win.set_focus(Some(&procs.filter_entry));
+        }
+        return Inhibit(true);
+    }
+    if is_procs_page || is_network_page {
+        // the process and the network lists
+        if key.keyval() == gdk::keys::constants::Escape {
+            if is_procs_page {
+                procs.hide_filter();
+            } else {
+                network.hide_filter();
+            }
+        } else if is_procs_page {
+            let ret = procs.search_bar.handle_event(key);
+            if !procs.filter_entry.text().is_empty() {
+                procs.filter_entry.show_all();
+                if win.focused_widget() != Some(procs.filter_entry.clone().upcast::<gtk::Widget>())
+                {
+                    win.set_focus(Some(&procs.filter_entry));
+                }
+            }
+            return Inhibit(ret);
+        } else {
+            let ret = network.search_bar.handle_event(key);
+            if !network.filter_entry.text().is_empty() {
+                network.filter_entry.show_all();
+                if win.focused_widget()
+                    != Some(network.filter_entry.clone().upcast::<gtk::Widget>())
+                {
+                    win.set_focus(Some(&network.filter_entry));
+                }
+            }
+            return Inhibit(ret);
+        }
+    }
+    Inhibit(false)
 }
 
 fn main() {
     let application = gtk::Application::new(Some(APPLICATION_NAME), gio::ApplicationFlags::empty());
 
+    // Our options are handled here since `GApplication` would reject them as unknown options.
+    let mut options = CommandLineOptions::default();
+    let mut args = Vec::new();
+    let mut env_args = std::env::args();
+    while let Some(arg) = env_args.next() {
+        match arg.as_str() {
+            "--minimized" => options.start_minimized = true,
+            "--mini" => options.mini = true,
+            "--filter" => options.filter = env_args.next(),
+            "--replay" => match env_args.next() {
+                Some(path) => options.replay.push(PathBuf::from(path)),
+                None => eprintln!("Missing file for `--replay`"),
+            },
+            "--refresh-ms" => {
+                let value = env_args.next().unwrap_or_default();
+                match value.parse::<u32>() {
+                    Ok(ms) => options.refresh_ms = Some(ms.max(MIN_REFRESH_INTERVAL)),
+                    Err(_) => eprintln!("Invalid value for `--refresh-ms`: {:?}", value),
+                }
+            }
+            _ => args.push(arg),
+        }
+    }
+
     application.connect_startup(move |app| {
-        build_ui(app);
+        build_ui(app, options.clone());
     });
 
     glib::set_application_name("process-viewer");
-    application.run();
+    application.run_with_args(&args);
 }
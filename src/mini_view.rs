@@ -0,0 +1,74 @@
+use gtk::prelude::{GridExt, LabelExt, WidgetExt};
+use sysinfo::{self, ComponentExt, ProcessorExt, SystemExt};
+
+/// A compact numeric-only summary of the system, used instead of the notebook with `--mini`.
+pub struct MiniView {
+    pub grid: gtk::Grid,
+    cpu: gtk::Label,
+    ram: gtk::Label,
+    swap: gtk::Label,
+    temperature: gtk::Label,
+    processes: gtk::Label,
+}
+
+fn add_line(grid: &gtk::Grid, line: i32, label: &str) -> gtk::Label {
+    let name = gtk::Label::new(Some(label));
+    let value = gtk::Label::new(None);
+    name.set_halign(gtk::Align::Start);
+    value.set_halign(gtk::Align::End);
+    value.set_hexpand(true);
+    grid.attach(&name, 0, line, 1, 1);
+    grid.attach(&value, 1, line, 1, 1);
+    value
+}
+
+fn percent(used: u64, total: u64) -> String {
+    if total == 0 {
+        "0.0 %".to_owned()
+    } else {
+        format!("{:.1} %", used as f64 * 100. / total as f64)
+    }
+}
+
+impl MiniView {
+    pub fn new() -> MiniView {
+        let grid = gtk::Grid::new();
+        grid.set_column_spacing(10);
+        grid.set_row_spacing(4);
+        grid.set_margin_top(10);
+        grid.set_margin_bottom(10);
+        grid.set_margin_start(10);
+        grid.set_margin_end(10);
+
+        MiniView {
+            cpu: add_line(&grid, 0, "CPU"),
+            ram: add_line(&grid, 1, "RAM"),
+            swap: add_line(&grid, 2, "Swap"),
+            temperature: add_line(&grid, 3, "Temperature"),
+            processes: add_line(&grid, 4, "Processes"),
+            grid,
+        }
+    }
+
+    pub fn update(&self, sys: &sysinfo::System, display_fahrenheit: bool) {
+        self.cpu
+            .set_text(&format!("{:.1} %", sys.global_processor_info().cpu_usage()));
+        self.ram
+            .set_text(&percent(sys.used_memory(), sys.total_memory()));
+        self.swap
+            .set_text(&percent(sys.used_swap(), sys.total_swap()));
+        let hottest = sys
+            .components()
+            .iter()
+            .map(|c| c.temperature())
+            .fold(None, |max: Option<f32>, t| {
+                Some(max.map_or(t, |max| max.max(t)))
+            });
+        self.temperature.set_text(&match hottest {
+            Some(t) if display_fahrenheit => format!("{:.1} °F", t * 1.8 + 32.),
+            Some(t) => format!("{:.1} °C", t),
+            None => "-".to_owned(),
+        });
+        self.processes.set_text(&sys.processes().len().to_string());
+    }
+}
@@ -7,15 +7,14 @@ use gtk::{self, glib};
 
 use sysinfo::{self, NetworkExt};
 
-use crate::graph::{Connecter, Graph};
+use crate::graph::{new_history, Connecter, Graph};
 use crate::notebook::NoteBook;
 use crate::utils::{
     connect_graph, format_number, format_number_full, get_main_window, graph_label,
-    graph_label_units, RotateVec,
+    graph_label_units,
 };
 
 use std::cell::RefCell;
-use std::iter;
 use std::rc::Rc;
 
 pub struct NetworkDialog {
@@ -190,16 +189,8 @@ pub fn create_network_dialog(
     let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
     let mut in_out_history = Graph::new(Some(1.), false);
 
-    in_out_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "received",
-        None,
-    );
-    in_out_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "transmitted",
-        None,
-    );
+    in_out_history.push(new_history(), "received", None);
+    in_out_history.push(new_history(), "transmitted", None);
     in_out_history.set_label_callbacks(Some(Box::new(graph_label_units)));
     let label = gtk::Label::new(None);
     label.set_markup("<b>Network usage</b>");
@@ -211,26 +202,10 @@ pub fn create_network_dialog(
 
     let mut packets_errors_history = Graph::new(Some(1.), false);
 
-    packets_errors_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "received packets",
-        None,
-    );
-    packets_errors_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "transmitted packets",
-        None,
-    );
-    packets_errors_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "errors on received",
-        None,
-    );
-    packets_errors_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "errors on transmitted",
-        None,
-    );
+    packets_errors_history.push(new_history(), "received packets", None);
+    packets_errors_history.push(new_history(), "transmitted packets", None);
+    packets_errors_history.push(new_history(), "errors on received", None);
+    packets_errors_history.push(new_history(), "errors on transmitted", None);
     packets_errors_history.set_label_callbacks(Some(Box::new(graph_label)));
     packets_errors_history.set_labels_width(120);
     let label = gtk::Label::new(None);
@@ -1,10 +1,23 @@
-use gtk::glib::IsA;
-use gtk::prelude::{BoxExt, NotebookExtManual, WidgetExt};
-use gtk::{Box, Label, Notebook, Orientation, Widget};
+use gtk::glib::{self, Cast, IsA};
+use gtk::prelude::{
+    BoxExt, ButtonExt, ContainerExt, GtkWindowExt, Inhibit, LabelExt, NotebookExt,
+    NotebookExtManual, WidgetExt,
+};
+use gtk::{Box, Button, Label, Notebook, Orientation, Widget};
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+type DetachedCallback = std::boxed::Box<dyn Fn(&gtk::Window, &Widget)>;
 
 pub struct NoteBook {
     pub notebook: Notebook,
     pub tabs: Vec<Box>,
+    // Buttons in the tabs moving their content into their own window.
+    detach_buttons: Vec<Button>,
+    reorderable: Rc<Cell<bool>>,
+    // Called with the new window and the page it contains when a tab is detached.
+    detached_callbacks: Rc<RefCell<Vec<DetachedCallback>>>,
 }
 
 impl NoteBook {
@@ -12,6 +25,9 @@ impl NoteBook {
         NoteBook {
             notebook: Notebook::new(),
             tabs: Vec::new(),
+            detach_buttons: Vec::new(),
+            reorderable: Rc::new(Cell::new(false)),
+            detached_callbacks: Rc::new(RefCell::new(Vec::new())),
         }
     }
 
@@ -19,11 +35,121 @@ impl NoteBook {
         let label = Label::new(Some(title));
         let tab = Box::new(Orientation::Horizontal, 0);
 
+        let detach_button = Button::from_icon_name(Some("window-new"), gtk::IconSize::Menu);
+        detach_button.set_relief(gtk::ReliefStyle::None);
+        detach_button.set_tooltip_text(Some("Display in a separate window"));
+        detach_button.set_no_show_all(true);
+        let widget = widget.clone().upcast::<Widget>();
+        let title = title.to_owned();
+        detach_button.connect_clicked(glib::clone!(
+            @weak self.notebook as notebook, @weak tab, @strong widget,
+            @strong self.reorderable as reorderable,
+            @strong self.detached_callbacks as detached_callbacks => move |_| {
+                detach_tab(&notebook, &tab, &widget, &title, &reorderable, &detached_callbacks);
+            }
+        ));
+
         tab.pack_start(&label, true, true, 0);
+        tab.pack_end(&detach_button, false, false, 0);
         tab.show_all();
 
-        let index = self.notebook.append_page(widget, Some(&tab));
+        let index = self.notebook.append_page(&widget, Some(&tab));
+        self.notebook
+            .set_tab_reorderable(&widget, self.reorderable.get());
         self.tabs.push(tab);
+        self.detach_buttons.push(detach_button);
         Some(index)
     }
+
+    pub fn current_page(&self) -> Option<u32> {
+        self.notebook.current_page()
+    }
+
+    /// Selects the page whose tab has `title`, which (unlike its position) doesn't change when the
+    /// tabs are reordered. Nothing happens if there is no such page, or if it is detached.
+    pub fn set_current_page_by_title(&self, title: &str) {
+        for page in 0..self.notebook.n_pages() {
+            let child = match self.notebook.nth_page(Some(page)) {
+                Some(child) => child,
+                None => continue,
+            };
+            if page_title(&self.notebook, &child).as_deref() == Some(title) {
+                self.notebook.set_current_page(Some(page));
+                return;
+            }
+        }
+    }
+
+    /// Allows to reorder the tabs by dragging them.
+    pub fn set_reorderable(&self, reorderable: bool) {
+        self.reorderable.set(reorderable);
+        for page in 0..self.notebook.n_pages() {
+            if let Some(child) = self.notebook.nth_page(Some(page)) {
+                self.notebook.set_tab_reorderable(&child, reorderable);
+            }
+        }
+    }
+
+    /// Calls `f` with the window created when a tab is detached and the page it displays, for
+    /// example to handle its keyboard shortcuts.
+    pub fn connect_tab_detached<F: Fn(&gtk::Window, &Widget) + 'static>(&self, f: F) {
+        self.detached_callbacks
+            .borrow_mut()
+            .push(std::boxed::Box::new(f));
+    }
+
+    /// Displays a button in each tab to move its content into a separate window. Closing the
+    /// window puts the tab back where it was.
+    pub fn set_detachable(&self, detachable: bool) {
+        for button in &self.detach_buttons {
+            button.set_visible(detachable);
+        }
+    }
+}
+
+/// Returns the title of the tab of `page`.
+pub fn page_title(notebook: &Notebook, page: &Widget) -> Option<String> {
+    let tab = notebook.tab_label(page)?.downcast::<Box>().ok()?;
+    tab.children()
+        .into_iter()
+        .find_map(|child| child.downcast::<Label>().ok())
+        .map(|label| label.text().to_string())
+}
+
+fn detach_tab(
+    notebook: &Notebook,
+    tab: &Box,
+    widget: &Widget,
+    title: &str,
+    reorderable: &Rc<Cell<bool>>,
+    detached_callbacks: &RefCell<Vec<DetachedCallback>>,
+) {
+    let position = match notebook.page_num(widget) {
+        Some(position) => position,
+        None => return,
+    };
+    let width = widget.allocated_width();
+    let height = widget.allocated_height();
+    notebook.remove_page(Some(position));
+
+    let window = gtk::Window::new(gtk::WindowType::Toplevel);
+    window.set_title(title);
+    window.set_default_size(width, height);
+    window.add(widget);
+    // The tab is kept alive by the window since it isn't in the notebook anymore.
+    window.connect_delete_event(glib::clone!(
+        @weak notebook, @strong tab, @strong widget, @strong reorderable => @default-return Inhibit(false),
+        move |window, _| {
+            window.remove(&widget);
+            let position = position.min(notebook.n_pages());
+            notebook.insert_page(&widget, Some(&tab), Some(position));
+            notebook.set_tab_reorderable(&widget, reorderable.get());
+            notebook.set_current_page(Some(position));
+            Inhibit(false)
+        }
+    ));
+    for callback in detached_callbacks.borrow().iter() {
+        callback(&window, widget);
+    }
+    window.show_all();
 }
@@ -1,21 +1,37 @@
+use gtk::glib::Cast;
+#[cfg(target_os = "linux")]
+use gtk::prelude::TreeSortableExtManual;
 use gtk::prelude::{
     AdjustmentExt, BoxExt, ButtonExt, ContainerExt, Inhibit, LabelExt, ScrolledWindowExt,
 };
 use gtk::prelude::{
-    CellRendererTextExt, GtkListStoreExtManual, GtkWindowExt, TreeViewColumnExt, TreeViewExt,
-    WidgetExt,
+    CellRendererTextExt, EntryExt, GtkListStoreExtManual, GtkWindowExt, NotebookExt,
+    NotebookExtManual, SearchEntryExt, ToggleButtonExt, TreeModelExt, TreeModelFilterExt,
+    TreeViewColumnExt, TreeViewExt, WidgetExt,
 };
-use gtk::{glib, pango};
+use gtk::{gio, glib, pango};
 use sysinfo::{self, Pid, ProcessExt};
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
-use std::iter;
 use std::rc::Rc;
+#[cfg(target_os = "linux")]
+use std::time::Instant;
 
-use crate::graph::{Connecter, Graph};
+#[cfg(not(windows))]
+use crate::display_procs::{process_priority, SIGNALS};
+use crate::graph::{new_history, Connecter, Graph};
 use crate::notebook::NoteBook;
-use crate::utils::{connect_graph, format_number, get_main_window, graph_label_units, RotateVec};
+use crate::settings::Settings;
+use crate::utils::{
+    connect_graph, format_number, format_time, format_timestamp, get_main_window,
+    graph_label_units, kib_to_bytes,
+};
+
+const ENV_ADDED_COLOR: &str = "green";
+const ENV_CHANGED_COLOR: &str = "orange";
+const ENV_REMOVED_COLOR: &str = "red";
 
 #[allow(dead_code)]
 pub struct ProcDialog {
@@ -34,6 +50,37 @@ pub struct ProcDialog {
     memory_peak_label: gtk::Label,
     disk_peak: RefCell<u64>,
     disk_peak_label: gtk::Label,
+    snapshot_button: gtk::ToggleButton,
+    // The signal to send to the process and the button sending it. The button is connected by
+    // the caller since it needs the system information.
+    #[cfg(not(windows))]
+    pub signal_combo: gtk::ComboBoxText,
+    #[cfg(not(windows))]
+    pub send_signal_button: gtk::Button,
+    // The nice value of the process (its priority class on Windows).
+    priority: gtk::Label,
+    #[cfg(target_os = "linux")]
+    voluntary_switches: gtk::Label,
+    #[cfg(target_os = "linux")]
+    nonvoluntary_switches: gtk::Label,
+    // Voluntary and nonvoluntary context switches at the previous update.
+    #[cfg(target_os = "linux")]
+    context_switches: RefCell<Option<(u64, u64, Instant)>>,
+    #[cfg(target_os = "linux")]
+    open_sockets: gtk::Label,
+    #[cfg(target_os = "linux")]
+    swap_usage: gtk::Label,
+    #[cfg(target_os = "linux")]
+    threads_count: gtk::Label,
+    #[cfg(target_os = "linux")]
+    threads_list: gtk::ListStore,
+    #[cfg(target_os = "linux")]
+    files_list: gtk::ListStore,
+    #[cfg(target_os = "linux")]
+    connections_list: gtk::ListStore,
+    // The connections are only updated while their tab is displayed.
+    #[cfg(target_os = "linux")]
+    connections_page: gtk::ScrolledWindow,
     pub is_dead: bool,
     pub to_be_removed: Rc<RefCell<bool>>,
 }
@@ -46,12 +93,13 @@ impl fmt::Debug for ProcDialog {
 
 impl ProcDialog {
     pub fn update(&self, process: &sysinfo::Process) {
-        if self.is_dead {
+        // When a snapshot is active, we keep displaying the values from when it was taken.
+        if self.is_dead || self.snapshot_button.is_active() {
             return;
         }
         self.working_directory
             .set_text(&process.cwd().display().to_string());
-        let memory = process.memory() * 1_000; // It returns in kB so we have to convert it to B
+        let memory = kib_to_bytes(process.memory());
         let memory_s = format_number(memory);
         self.memory_usage.set_text(&memory_s);
         if memory > *self.memory_peak.borrow() {
@@ -59,16 +107,42 @@ impl ProcDialog {
             self.memory_peak_label.set_text(&memory_s);
         }
         let disk_usage = process.disk_usage();
+        self.disk_usage.set_text(&format_disk_usage(&disk_usage));
         let disk_usage = disk_usage.written_bytes + disk_usage.read_bytes;
-        let disk_usage_s = format_number(disk_usage);
-        self.disk_usage.set_text(&disk_usage_s);
         if disk_usage > *self.disk_peak.borrow() {
             *self.disk_peak.borrow_mut() = disk_usage;
-            self.disk_peak_label.set_text(&disk_usage_s);
+            self.disk_peak_label.set_text(&format_number(disk_usage));
         }
         self.cpu_usage
             .set_text(&format!("{:.1}%", process.cpu_usage()));
         self.run_time.set_text(&format_time(process.run_time()));
+        self.priority.set_text(&priority_text(self.pid));
+        #[cfg(target_os = "linux")]
+        {
+            // The files are read once and shared by the values computed from them.
+            let status = std::fs::read_to_string(format!("/proc/{}/status", self.pid)).ok();
+            let file_descriptors = get_file_descriptors(self.pid);
+            self.update_context_switches(status.as_deref());
+            if let Some(ref file_descriptors) = file_descriptors {
+                self.open_sockets
+                    .set_text(&get_open_sockets_count(file_descriptors).to_string());
+            }
+            if let Some(swap) = status.as_deref().and_then(get_swap_usage) {
+                self.swap_usage.set_text(&format_number(swap));
+            }
+            self.threads_count
+                .set_text(&process.tasks.len().to_string());
+            fill_threads_list(&self.threads_list, process);
+            let file_descriptors = file_descriptors.unwrap_or_default();
+            fill_files_list(&self.files_list, &file_descriptors);
+            // Reading "/proc/net" is costly, so it's only done when the connections are displayed.
+            let notebook = &self.notebook.notebook;
+            if notebook.current_page().is_some()
+                && notebook.current_page() == notebook.page_num(&self.connections_page)
+            {
+                fill_connections_list(&self.connections_list, &file_descriptors);
+            }
+        }
 
         let mut t = self.ram_usage_history.borrow_mut();
         t.data[0].move_start();
@@ -84,6 +158,33 @@ impl ProcDialog {
         t.invalidate();
     }
 
+    #[cfg(target_os = "linux")]
+    fn update_context_switches(&self, status: Option<&str>) {
+        let (voluntary, nonvoluntary) = match status.and_then(get_context_switches) {
+            Some(switches) => switches,
+            None => return,
+        };
+        let now = Instant::now();
+        let previous = self
+            .context_switches
+            .replace(Some((voluntary, nonvoluntary, now)));
+        if let Some((old_voluntary, old_nonvoluntary, old_time)) = previous {
+            let elapsed = now.duration_since(old_time).as_secs_f64();
+            if elapsed > 0. {
+                self.voluntary_switches.set_text(&format!(
+                    "{:.1}/s ({} total)",
+                    voluntary.saturating_sub(old_voluntary) as f64 / elapsed,
+                    voluntary,
+                ));
+                self.nonvoluntary_switches.set_text(&format!(
+                    "{:.1}/s ({} total)",
+                    nonvoluntary.saturating_sub(old_nonvoluntary) as f64 / elapsed,
+                    nonvoluntary,
+                ));
+            }
+        }
+    }
+
     pub fn need_remove(&self) -> bool {
         *self.to_be_removed.borrow()
     }
@@ -99,38 +200,259 @@ impl ProcDialog {
         let time = self.run_time.text();
         let s = format!("Ran for {}", if time.is_empty() { "0s" } else { &time },);
         self.run_time.set_text(&s);
+        #[cfg(not(windows))]
+        self.send_signal_button.set_sensitive(false);
     }
 }
 
-fn format_time(t: u64) -> String {
-    format!(
-        "{}{}{}{}s",
-        {
-            let days = t / 86_400;
-            if days > 0 {
-                format!("{}d ", days)
+/// Returns the number of voluntary and nonvoluntary context switches of the process from the
+/// content of its "/proc/<pid>/status" file.
+#[cfg(target_os = "linux")]
+fn get_context_switches(status: &str) -> Option<(u64, u64)> {
+    let mut voluntary = None;
+    let mut nonvoluntary = None;
+    for line in status.lines() {
+        if let Some(value) = line.strip_prefix("voluntary_ctxt_switches:") {
+            voluntary = value.trim().parse().ok();
+        } else if let Some(value) = line.strip_prefix("nonvoluntary_ctxt_switches:") {
+            nonvoluntary = value.trim().parse().ok();
+        }
+    }
+    Some((voluntary?, nonvoluntary?))
+}
+
+/// Returns the amount of memory of the process which is swapped out, in bytes, from the content
+/// of its "/proc/<pid>/status" file.
+#[cfg(target_os = "linux")]
+fn get_swap_usage(status: &str) -> Option<u64> {
+    status.lines().find_map(|line| {
+        // The value is in KiB.
+        let value = line.strip_prefix("VmSwap:")?.trim().strip_suffix("kB")?;
+        value.trim().parse::<u64>().ok().map(kib_to_bytes)
+    })
+}
+
+/// Fills the threads list with the tasks of the process. The rows of the threads which are still
+/// running are updated in place so the selection and the scroll position are kept.
+#[cfg(target_os = "linux")]
+fn fill_threads_list(list_store: &gtk::ListStore, process: &sysinfo::Process) {
+    use sysinfo::PidExt;
+
+    let mut tasks = process
+        .tasks
+        .iter()
+        .map(|(pid, task)| (pid.as_u32(), task))
+        .collect::<HashMap<_, _>>();
+    if let Some(iter) = list_store.iter_first() {
+        let mut valid = true;
+        while valid {
+            let tid = list_store.value(&iter, 0).get::<u32>().unwrap_or(0);
+            if let Some(task) = tasks.remove(&tid) {
+                list_store.set(
+                    &iter,
+                    &[(1, &task.name()), (2, &format!("{:.1}%", task.cpu_usage()))],
+                );
+                valid = list_store.iter_next(&iter);
             } else {
-                "".to_owned()
+                valid = list_store.remove(&iter);
             }
-        },
-        {
-            let hours = t / 3_600 % 24;
-            if hours > 0 {
-                format!("{}h ", hours)
+        }
+    }
+    for (tid, task) in tasks {
+        list_store.insert_with_values(
+            None,
+            &[
+                (0, &tid),
+                (1, &task.name()),
+                (2, &format!("{:.1}%", task.cpu_usage())),
+            ],
+        );
+    }
+}
+
+/// Returns the file descriptors of the process and what they point to. It requires to be allowed
+/// to read them, so it'll fail for other users' processes.
+#[cfg(target_os = "linux")]
+fn get_file_descriptors(pid: Pid) -> Option<HashMap<u32, String>> {
+    let entries = std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    Some(
+        entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let fd = entry.file_name().to_str()?.parse::<u32>().ok()?;
+                let target = std::fs::read_link(entry.path()).ok()?;
+                Some((fd, target.display().to_string()))
+            })
+            .collect(),
+    )
+}
+
+/// Fills the files list with the file descriptors of the process and what they point to. It stays
+/// empty if we're not allowed to read them. Like the threads list, the rows are updated in place.
+#[cfg(target_os = "linux")]
+fn fill_files_list(list_store: &gtk::ListStore, file_descriptors: &HashMap<u32, String>) {
+    let mut files = file_descriptors.clone();
+    if let Some(iter) = list_store.iter_first() {
+        let mut valid = true;
+        while valid {
+            let fd = list_store.value(&iter, 0).get::<u32>().unwrap_or(0);
+            if let Some(target) = files.remove(&fd) {
+                list_store.set(&iter, &[(1, &target)]);
+                valid = list_store.iter_next(&iter);
             } else {
-                "".to_owned()
+                valid = list_store.remove(&iter);
             }
-        },
-        {
-            let minutes = t / 60 % 60;
-            if minutes > 0 {
-                format!("{}m ", minutes)
+        }
+    }
+    for (fd, target) in files {
+        list_store.insert_with_values(None, &[(0, &fd), (1, &target)]);
+    }
+}
+
+/// Returns the address in the format used by "/proc/net/*" ("address:port" in hexadecimal, the
+/// address being made of 32 bits words in the host byte order) as a string.
+#[cfg(target_os = "linux")]
+fn parse_socket_address(address: &str) -> Option<String> {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    let (ip, port) = address.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let mut bytes = Vec::with_capacity(16);
+    for pos in (0..ip.len()).step_by(8) {
+        let word = u32::from_str_radix(ip.get(pos..pos + 8)?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    let ip = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+        16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&bytes);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port).to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn tcp_state(state: &str) -> &'static str {
+    match state {
+        "01" => "established",
+        "02" => "SYN sent",
+        "03" => "SYN received",
+        "04" => "FIN wait 1",
+        "05" => "FIN wait 2",
+        "06" => "time wait",
+        "07" => "close",
+        "08" => "close wait",
+        "09" => "last ACK",
+        "0A" => "listen",
+        "0B" => "closing",
+        _ => "unknown",
+    }
+}
+
+/// Fills the connections list with the TCP and UDP sockets of the process. Like the files list, it
+/// stays empty if we're not allowed to read its file descriptors and its rows are updated in place.
+#[cfg(target_os = "linux")]
+fn fill_connections_list(list_store: &gtk::ListStore, file_descriptors: &HashMap<u32, String>) {
+    use std::collections::HashSet;
+
+    let inodes = file_descriptors
+        .values()
+        .filter_map(|target| {
+            target
+                .strip_prefix("socket:[")?
+                .strip_suffix(']')?
+                .parse::<u64>()
+                .ok()
+        })
+        .collect::<HashSet<_>>();
+    // The connections are identified by their protocol and their addresses.
+    let mut connections = HashMap::new();
+    for (file, protocol) in &[
+        ("/proc/net/tcp", "TCP"),
+        ("/proc/net/tcp6", "TCP"),
+        ("/proc/net/udp", "UDP"),
+        ("/proc/net/udp6", "UDP"),
+    ] {
+        // No need to read the sockets if the process doesn't have any (or we can't know it).
+        if inodes.is_empty() {
+            break;
+        }
+        let content = match std::fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        // The first line contains the columns names.
+        for line in content.lines().skip(1) {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 10
+                || !fields[9]
+                    .parse::<u64>()
+                    .map_or(false, |i| inodes.contains(&i))
+            {
+                continue;
+            }
+            let local = parse_socket_address(fields[1]).unwrap_or_default();
+            let remote = parse_socket_address(fields[2]).unwrap_or_default();
+            // UDP sockets don't have a state, except if they're connected to a remote address.
+            let state = match *protocol {
+                "TCP" => tcp_state(fields[3]),
+                _ if fields[3] == "01" => "connected",
+                _ => "",
+            };
+            connections.insert((protocol.to_string(), local, remote), state);
+        }
+    }
+    if let Some(iter) = list_store.iter_first() {
+        let mut valid = true;
+        while valid {
+            let text = |column| {
+                list_store
+                    .value(&iter, column)
+                    .get::<String>()
+                    .unwrap_or_default()
+            };
+            let key = (text(0), text(1), text(2));
+            if let Some(state) = connections.remove(&key) {
+                list_store.set(&iter, &[(3, &state)]);
+                valid = list_store.iter_next(&iter);
             } else {
-                "".to_owned()
+                valid = list_store.remove(&iter);
             }
-        },
-        t % 60
-    )
+        }
+    }
+    for ((protocol, local, remote), state) in connections {
+        list_store.insert_with_values(
+            None,
+            &[(0, &protocol), (1, &local), (2, &remote), (3, &state)],
+        );
+    }
+}
+
+/// Returns the number of file descriptors of the process which are sockets.
+#[cfg(target_os = "linux")]
+fn get_open_sockets_count(file_descriptors: &HashMap<u32, String>) -> usize {
+    file_descriptors
+        .values()
+        .filter(|target| target.starts_with("socket:"))
+        .count()
+}
+
+#[cfg(not(windows))]
+fn priority_text(pid: Pid) -> String {
+    process_priority(pid).to_string()
+}
+
+#[cfg(windows)]
+fn priority_text(pid: Pid) -> String {
+    let class = crate::actions::priority_class(pid);
+    crate::actions::PRIORITY_CLASSES
+        .iter()
+        .find(|(_, c)| Some(*c) == class)
+        .map_or("unknown", |(name, _)| name)
+        .to_owned()
 }
 
 fn create_and_add_new_label(scroll: &gtk::Box, title: &str, text: &str) -> gtk::Label {
@@ -157,12 +479,156 @@ fn create_and_add_new_label(scroll: &gtk::Box, title: &str, text: &str) -> gtk::
     text
 }
 
+/// Adds the current priority of the process and the widgets to change it.
+fn create_priority_controls(
+    labels: &gtk::Box,
+    popup: &gtk::Window,
+    process: &sysinfo::Process,
+) -> gtk::Label {
+    let pid = process.pid();
+    let name = process.name().to_owned();
+    let title = if cfg!(windows) {
+        "priority class"
+    } else {
+        "nice"
+    };
+    let priority = create_and_add_new_label(labels, title, &priority_text(pid));
+
+    let layout = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    layout.set_margin_top(5);
+    layout.set_margin_bottom(5);
+    layout.set_margin_end(5);
+    layout.set_margin_start(5);
+    let apply_button = gtk::Button::with_label("Change priority");
+
+    #[cfg(not(windows))]
+    {
+        use gtk::prelude::SpinButtonExt;
+
+        let spin = gtk::SpinButton::with_range(-20., 19., 1.);
+        spin.set_value(f64::from(process_priority(pid)));
+        layout.pack_start(&spin, false, false, 0);
+        apply_button.connect_clicked(
+            glib::clone!(@weak spin, @weak priority, @weak popup => move |_| {
+                let nice = spin.value_as_int();
+                let changed = crate::actions::run_process_action(
+                    &popup,
+                    "change the priority of",
+                    &name,
+                    || crate::actions::set_priority(pid, nice),
+                );
+                if changed {
+                    priority.set_text(&priority_text(pid));
+                }
+            }),
+        );
+    }
+    #[cfg(windows)]
+    {
+        use crate::actions::PRIORITY_CLASSES;
+        use gtk::prelude::{ComboBoxExtManual, ComboBoxTextExt};
+
+        let combo = gtk::ComboBoxText::new();
+        for (class_name, _) in PRIORITY_CLASSES {
+            combo.append_text(class_name);
+        }
+        let class = crate::actions::priority_class(pid);
+        combo.set_active(
+            PRIORITY_CLASSES
+                .iter()
+                .position(|(_, c)| Some(*c) == class)
+                .map(|pos| pos as u32),
+        );
+        layout.pack_start(&combo, false, false, 0);
+        apply_button.connect_clicked(
+            glib::clone!(@weak combo, @weak priority, @weak popup => move |_| {
+                let class = match combo.active() {
+                    Some(index) => PRIORITY_CLASSES[index as usize].1,
+                    None => return,
+                };
+                let changed = crate::actions::run_process_action(
+                    &popup,
+                    "change the priority of",
+                    &name,
+                    || crate::actions::set_priority_class(pid, class),
+                );
+                if changed {
+                    priority.set_text(&priority_text(pid));
+                }
+            }),
+        );
+    }
+
+    layout.pack_start(&apply_button, false, false, 0);
+    labels.pack_start(&layout, false, false, 0);
+    priority
+}
+
+/// Adds the widgets to send a signal to the process.
+#[cfg(not(windows))]
+fn create_signal_controls(labels: &gtk::Box) -> (gtk::ComboBoxText, gtk::Button) {
+    use gtk::prelude::{ComboBoxExtManual, ComboBoxTextExt};
+
+    let layout = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    layout.set_margin_top(5);
+    layout.set_margin_bottom(5);
+    layout.set_margin_end(5);
+    layout.set_margin_start(5);
+
+    let combo = gtk::ComboBoxText::new();
+    for (name, _) in SIGNALS {
+        combo.append_text(name);
+    }
+    combo.set_active(Some(0));
+    let send_button = gtk::Button::with_label("Send signal");
+
+    layout.pack_start(&combo, false, false, 0);
+    layout.pack_start(&send_button, false, false, 0);
+    labels.pack_start(&layout, false, false, 0);
+    (combo, send_button)
+}
+
+/// Adds a button next to the executable path label opening its folder in the file manager. It's
+/// disabled if the executable cannot be accessed.
+fn add_open_folder_button(exe_label: &gtk::Label, popup: &gtk::Window, process: &sysinfo::Process) {
+    use gtk::gio::prelude::FileExt;
+
+    let line = match exe_label
+        .parent()
+        .and_then(|p| p.downcast::<gtk::Box>().ok())
+    {
+        Some(line) => line,
+        None => return,
+    };
+    let button = gtk::Button::from_icon_name(Some("folder-open"), gtk::IconSize::Menu);
+    button.set_relief(gtk::ReliefStyle::None);
+    button.set_tooltip_text(Some("Open the containing folder"));
+    line.pack_start(&button, false, false, 0);
+
+    let folder = match process.exe().parent() {
+        Some(folder) if process.exe().exists() => folder.to_owned(),
+        _ => {
+            button.set_sensitive(false);
+            return;
+        }
+    };
+    let name = process.name().to_owned();
+    button.connect_clicked(glib::clone!(@weak popup => move |_| {
+        let uri = gio::File::for_path(&folder).uri();
+        crate::actions::run_process_action(&popup, "open the folder of", &name, || {
+            gio::AppInfo::launch_default_for_uri(&uri, None::<&gio::AppLaunchContext>)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        });
+    }));
+}
+
 fn append_text_column(tree: &gtk::TreeView, pos: i32) -> gtk::CellRendererText {
     let column = gtk::TreeViewColumn::new();
     let cell = gtk::CellRendererText::new();
 
     column.pack_start(&cell, true);
     column.add_attribute(&cell, "text", pos);
+    column.add_attribute(&cell, "foreground", 2);
     if pos == 1 {
         cell.set_wrap_width(247);
         cell.set_wrap_mode(pango::WrapMode::Char);
@@ -172,7 +638,131 @@ fn append_text_column(tree: &gtk::TreeView, pos: i32) -> gtk::CellRendererText {
     cell
 }
 
-pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> ProcDialog {
+/// Puts `value` between double quotes for a `.env` file, escaping the backslashes, the double
+/// quotes, the dollars (so no variable is expanded), the backticks and the newlines. Shells
+/// sourcing the file read the same value, except for the newlines which they keep as `\n`.
+fn quote_env_value(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' | '`' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Fills the environment variables list. If `compare` is set, the variables are compared with the
+/// ones of the process viewer (which are most likely the ones of the current shell): added
+/// variables are displayed in green, changed ones in orange and the missing ones in red.
+fn fill_env_list(list_store: &gtk::ListStore, environ: &[String], compare: bool) {
+    list_store.clear();
+    let mut current_env: HashMap<String, String> = if compare {
+        std::env::vars().collect()
+    } else {
+        HashMap::new()
+    };
+    for env in environ {
+        let mut parts = env.splitn(2, '=');
+        let name = match parts.next() {
+            Some(n) => n,
+            None => continue,
+        };
+        let value = parts.next().unwrap_or("");
+        let color = if !compare {
+            None
+        } else {
+            match current_env.remove(name) {
+                None => Some(ENV_ADDED_COLOR),
+                Some(ref v) if v != value => Some(ENV_CHANGED_COLOR),
+                Some(_) => None,
+            }
+        };
+        list_store.insert_with_values(None, &[(0, &name), (1, &value), (2, &color)]);
+    }
+    let mut removed = current_env.into_iter().collect::<Vec<_>>();
+    removed.sort();
+    for (name, value) in removed {
+        list_store.insert_with_values(
+            None,
+            &[(0, &name), (1, &value), (2, &Some(ENV_REMOVED_COLOR))],
+        );
+    }
+}
+
+fn env_to_dotenv(list_store: &gtk::ListStore) -> String {
+    let mut out = String::new();
+    if let Some(iter) = list_store.iter_first() {
+        loop {
+            // The variables missing from the process environment aren't part of it.
+            let color = list_store.value(&iter, 2).get::<Option<String>>();
+            if color.ok().flatten().as_deref() != Some(ENV_REMOVED_COLOR) {
+                let name = list_store
+                    .value(&iter, 0)
+                    .get::<String>()
+                    .unwrap_or_default();
+                let value = list_store
+                    .value(&iter, 1)
+                    .get::<String>()
+                    .unwrap_or_default();
+                out.push_str(&format!("{}={}\n", name, quote_env_value(&value)));
+            }
+            if !list_store.iter_next(&iter) {
+                break;
+            }
+        }
+    }
+    out
+}
+
+/// Returns all the fields of the information tab, one per line, followed by the environment
+/// variables.
+fn process_info_to_text(labels: &gtk::Box, env_list: &gtk::ListStore) -> String {
+    let mut out = String::new();
+    for line in labels.children() {
+        let line = match line.downcast::<gtk::Box>() {
+            Ok(line) => line,
+            Err(_) => continue,
+        };
+        // The fields are made of a title label and a value label, other lines are skipped.
+        let texts = line
+            .children()
+            .into_iter()
+            .filter_map(|child| child.downcast::<gtk::Label>().ok())
+            .map(|label| label.text())
+            .collect::<Vec<_>>();
+        if let [title, value] = texts.as_slice() {
+            out.push_str(&format!("{} {}\n", title.trim_end(), value));
+        }
+    }
+    let environ = env_to_dotenv(env_list);
+    if !environ.is_empty() {
+        out.push_str("\nEnvironment variables:\n");
+        out.push_str(&environ);
+    }
+    out
+}
+
+fn format_disk_usage(disk_usage: &sysinfo::DiskUsage) -> String {
+    format!(
+        "{} read / {} written",
+        format_number(disk_usage.read_bytes),
+        format_number(disk_usage.written_bytes)
+    )
+}
+
+pub fn create_process_dialog(
+    process: &sysinfo::Process,
+    user: &str,
+    total_memory: u64,
+    settings: &Rc<RefCell<Settings>>,
+) -> ProcDialog {
     let mut notebook = NoteBook::new();
 
     let popup = gtk::Window::new(gtk::WindowType::Toplevel);
@@ -186,6 +776,7 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     //
     let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
     let close_button = gtk::Button::with_label("Close");
+    let snapshot_button = gtk::ToggleButton::with_label("Snapshot");
     let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
     scroll.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
 
@@ -195,13 +786,16 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
 
     create_and_add_new_label(&labels, "name", process.name());
     create_and_add_new_label(&labels, "pid", &process.pid().to_string());
-    let memory_peak = process.memory() * 1_000;
+    if !user.is_empty() {
+        create_and_add_new_label(&labels, "user", user);
+    }
+    let memory_peak = kib_to_bytes(process.memory());
     let memory_usage =
         create_and_add_new_label(&labels, "memory usage", &format_number(memory_peak));
     let memory_peak_label =
         create_and_add_new_label(&labels, "memory usage peak", &format_number(memory_peak));
-    let disk_peak = process.disk_usage();
-    let disk_peak = disk_peak.written_bytes + disk_peak.read_bytes;
+    let disk_usage = process.disk_usage();
+    let disk_peak = disk_usage.written_bytes + disk_usage.read_bytes;
     let s;
     #[cfg(not(any(windows, target_os = "freebsd")))]
     {
@@ -211,7 +805,7 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     {
         s = "I/O usage";
     }
-    let disk_usage = create_and_add_new_label(&labels, s, &format_number(disk_peak));
+    let disk_usage = create_and_add_new_label(&labels, s, &format_disk_usage(&disk_usage));
     let disk_peak_label =
         create_and_add_new_label(&labels, &format!("{} peak", s), &format_number(disk_peak));
     let cpu_usage = create_and_add_new_label(
@@ -220,6 +814,46 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
         &format!("{:.1}%", process.cpu_usage()),
     );
     let run_time = create_and_add_new_label(&labels, "Running since", &format_time(running_since));
+    create_and_add_new_label(
+        &labels,
+        "started at",
+        &format_timestamp(process.start_time()),
+    );
+    let priority = create_priority_controls(&labels, &popup, process);
+    #[cfg(not(windows))]
+    let (signal_combo, send_signal_button) = create_signal_controls(&labels);
+    #[cfg(target_os = "linux")]
+    let (voluntary_switches, nonvoluntary_switches) = {
+        let (voluntary, nonvoluntary) = get_context_switches(process.pid()).unwrap_or((0, 0));
+        (
+            create_and_add_new_label(
+                &labels,
+                "voluntary context switches",
+                &format!("{} total", voluntary),
+            ),
+            create_and_add_new_label(
+                &labels,
+                "nonvoluntary context switches",
+                &format!("{} total", nonvoluntary),
+            ),
+        )
+    };
+    #[cfg(target_os = "linux")]
+    let swap_usage = create_and_add_new_label(
+        &labels,
+        "swap usage",
+        &get_swap_usage(process.pid()).map_or_else(|| "unknown".to_owned(), format_number),
+    );
+    #[cfg(target_os = "linux")]
+    let threads_count =
+        create_and_add_new_label(&labels, "threads", &process.tasks.len().to_string());
+    #[cfg(target_os = "linux")]
+    let open_sockets = create_and_add_new_label(
+        &labels,
+        "open sockets",
+        &get_open_sockets_count(process.pid())
+            .map_or_else(|| "unknown".to_owned(), |c| c.to_string()),
+    );
     create_and_add_new_label(
         &labels,
         "command",
@@ -233,11 +867,12 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
                 .join(", ")
         ),
     );
-    create_and_add_new_label(
+    let exe_label = create_and_add_new_label(
         &labels,
         "executable path",
         &process.exe().display().to_string(),
     );
+    add_open_folder_button(&exe_label, &popup, process);
     let working_directory = create_and_add_new_label(
         &labels,
         "current working directory",
@@ -250,10 +885,35 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     );
 
     let env_tree = gtk::TreeView::new();
-    let list_store = gtk::ListStore::new(&[glib::Type::STRING, glib::Type::STRING]);
+    let list_store =
+        gtk::ListStore::new(&[glib::Type::STRING, glib::Type::STRING, glib::Type::STRING]);
+
+    // Only the variables whose name or value contains the filter text (case-insensitive) are
+    // displayed.
+    let env_filter_entry = gtk::SearchEntry::new();
+    env_filter_entry.set_placeholder_text(Some("Filter the variables"));
+    let env_filter = gtk::TreeModelFilter::new(&list_store, None);
+    env_filter.set_visible_func(
+        glib::clone!(@weak env_filter_entry => @default-return true, move |model, iter| {
+            let text = env_filter_entry.text().to_lowercase();
+            if text.is_empty() {
+                return true;
+            }
+            [0, 1].iter().any(|column| {
+                model
+                    .value(iter, *column)
+                    .get::<String>()
+                    .map(|s| s.to_lowercase().contains(&text))
+                    .unwrap_or(false)
+            })
+        }),
+    );
+    env_filter_entry.connect_search_changed(glib::clone!(@weak env_filter => move |_| {
+        env_filter.refilter();
+    }));
 
     env_tree.set_headers_visible(false);
-    env_tree.set_model(Some(&list_store));
+    env_tree.set_model(Some(&env_filter));
 
     append_text_column(&env_tree, 0);
     let cell = append_text_column(&env_tree, 1);
@@ -264,15 +924,7 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
         }
     });
 
-    for env in process.environ() {
-        let mut parts = env.splitn(2, '=');
-        let name = match parts.next() {
-            Some(n) => n,
-            None => continue,
-        };
-        let value = parts.next().unwrap_or("");
-        list_store.insert_with_values(None, &[(0, &name), (1, &value)]);
-    }
+    fill_env_list(&list_store, process.environ(), false);
 
     let components = gtk::Box::new(gtk::Orientation::Vertical, 0);
     components.add(&labels);
@@ -281,14 +933,45 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
         let label = gtk::Label::new(None);
         label.set_markup("<b>Environment variables</b>");
 
+        let copy_button = gtk::Button::with_label("Copy as .env");
+        copy_button.connect_clicked(glib::clone!(@weak list_store => move |_| {
+            let clipboard = gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD);
+            clipboard.set_text(&env_to_dotenv(&list_store));
+        }));
+        let compare_button = gtk::ToggleButton::with_label("Compare with my environment");
+        let environ = process.environ().to_vec();
+        compare_button.connect_toggled(glib::clone!(@weak list_store => move |button| {
+            fill_env_list(&list_store, &environ, button.is_active());
+        }));
+        let env_buttons = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        env_buttons.pack_start(&env_filter_entry, true, true, 0);
+        env_buttons.pack_start(&compare_button, false, false, 0);
+        env_buttons.pack_start(&copy_button, false, false, 0);
+
         components.add(&label);
+        components.add(&env_buttons);
         components.pack_start(&env_tree, false, false, 0);
     }
 
     scroll.add(&components);
 
+    let copy_all_button = gtk::Button::with_label("Copy all");
+    copy_all_button.set_tooltip_text(Some(
+        "Copy the information, the resources usage and the environment variables",
+    ));
+    copy_all_button.connect_clicked(glib::clone!(@weak labels, @weak list_store => move |_| {
+        let clipboard = gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD);
+        clipboard.set_text(&process_info_to_text(&labels, &list_store));
+    }));
+
+    let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    buttons.set_homogeneous(true);
+    buttons.pack_start(&snapshot_button, true, true, 0);
+    buttons.pack_start(&copy_all_button, true, true, 0);
+    buttons.pack_start(&close_button, true, true, 0);
+
     vertical_layout.pack_start(&scroll, true, true, 0);
-    vertical_layout.pack_start(&close_button, false, true, 0);
+    vertical_layout.pack_start(&buttons, false, true, 0);
 
     notebook.create_tab("Information", &vertical_layout);
 
@@ -314,11 +997,9 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     disk_usage_history.set_display_labels(false);
     disk_usage_history.set_overhead(Some(20.));
 
-    cpu_usage_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "",
-        None,
-    );
+    cpu_usage_history.push(new_history(), "", None);
+    // The labels don't follow the values below 100%.
+    cpu_usage_history.set_value_formatter(Some(Box::new(|v| format!("{:.1} %", v))));
     cpu_usage_history.set_label_callbacks(Some(Box::new(|v| {
         if v > 100. {
             let nb = v.ceil() as u64;
@@ -342,17 +1023,9 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     cpu_usage_history.invalidate();
     let cpu_usage_history = connect_graph(cpu_usage_history);
 
-    ram_usage_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "",
-        None,
-    );
+    ram_usage_history.push(new_history(), "", None);
 
-    disk_usage_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "",
-        None,
-    );
+    disk_usage_history.push(new_history(), "", None);
 
     ram_usage_history.set_label_callbacks(Some(Box::new(graph_label_units)));
     disk_usage_history.set_label_callbacks(Some(Box::new(graph_label_units)));
@@ -384,6 +1057,113 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     );
     notebook.create_tab("Resources usage", &scroll);
 
+    //
+    // THREADS TAB
+    //
+    // Only Linux allows to list the threads of a process, the tab isn't created otherwise.
+    #[cfg(target_os = "linux")]
+    let threads_list = {
+        let threads_tree = gtk::TreeView::new();
+        let threads_list =
+            gtk::ListStore::new(&[glib::Type::U32, glib::Type::STRING, glib::Type::STRING]);
+        // The rows are kept sorted by id.
+        threads_list.set_sort_column_id(gtk::SortColumn::Index(0), gtk::SortType::Ascending);
+        threads_tree.set_model(Some(&threads_list));
+        for (pos, title) in ["id", "name", "cpu usage"].iter().enumerate() {
+            let column = gtk::TreeViewColumn::new();
+            let cell = gtk::CellRendererText::new();
+            column.set_title(title);
+            column.pack_start(&cell, true);
+            column.add_attribute(&cell, "text", pos as i32);
+            column.set_expand(pos == 1);
+            threads_tree.append_column(&column);
+        }
+        fill_threads_list(&threads_list, process);
+
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        scroll.add(&threads_tree);
+        notebook.create_tab("Threads", &scroll);
+        threads_list
+    };
+
+    //
+    // OPEN FILES TAB
+    //
+    // The file descriptors are read from "/proc", the tab isn't created on other systems.
+    #[cfg(target_os = "linux")]
+    let files_list = {
+        let files_tree = gtk::TreeView::new();
+        let files_list = gtk::ListStore::new(&[glib::Type::U32, glib::Type::STRING]);
+        // The rows are kept sorted by file descriptor.
+        files_list.set_sort_column_id(gtk::SortColumn::Index(0), gtk::SortType::Ascending);
+        files_tree.set_model(Some(&files_list));
+        for (pos, title) in ["fd", "target"].iter().enumerate() {
+            let column = gtk::TreeViewColumn::new();
+            let cell = gtk::CellRendererText::new();
+            column.set_title(title);
+            column.pack_start(&cell, true);
+            column.add_attribute(&cell, "text", pos as i32);
+            column.set_expand(pos == 1);
+            files_tree.append_column(&column);
+        }
+        fill_files_list(
+            &files_list,
+            &get_file_descriptors(process.pid()).unwrap_or_default(),
+        );
+
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        scroll.add(&files_tree);
+        notebook.create_tab("Open files", &scroll);
+        files_list
+    };
+
+    //
+    // NETWORK CONNECTIONS TAB
+    //
+    // The sockets are matched with the ones listed in "/proc/net", only available on Linux.
+    #[cfg(target_os = "linux")]
+    let (connections_list, connections_page) = {
+        let connections_tree = gtk::TreeView::new();
+        let connections_list = gtk::ListStore::new(&[
+            glib::Type::STRING,
+            glib::Type::STRING,
+            glib::Type::STRING,
+            glib::Type::STRING,
+        ]);
+        connections_tree.set_model(Some(&connections_list));
+        for (pos, title) in ["protocol", "local address", "remote address", "state"]
+            .iter()
+            .enumerate()
+        {
+            let column = gtk::TreeViewColumn::new();
+            let cell = gtk::CellRendererText::new();
+            column.set_title(title);
+            column.pack_start(&cell, true);
+            column.add_attribute(&cell, "text", pos as i32);
+            column.set_expand(pos == 1 || pos == 2);
+            connections_tree.append_column(&column);
+        }
+
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        scroll.add(&connections_tree);
+        notebook.create_tab("Connections", &scroll);
+        // The connections are filled when their tab is displayed, then updated with the other
+        // values.
+        let pid = process.pid();
+        notebook.notebook.connect_switch_page(
+            glib::clone!(@weak connections_list, @weak scroll, @weak snapshot_button
+                         => move |_, page, _| {
+                if *page != scroll.clone().upcast::<gtk::Widget>() || snapshot_button.is_active() {
+                    return;
+                }
+                if let Some(file_descriptors) = get_file_descriptors(pid) {
+                    fill_connections_list(&connections_list, &file_descriptors);
+                }
+            }),
+        );
+        (connections_list, scroll)
+    };
+
     popup.add(&notebook.notebook);
     // To silence the annoying warning:
     // "(.:2257): Gtk-WARNING **: Allocating size to GtkWindow 0x7f8a31038290 without
@@ -391,10 +1171,34 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     // allocate?"
     popup.preferred_width();
     popup.set_size_request(500, 600);
+    {
+        let settings = settings.borrow();
+        popup.set_default_size(
+            settings.process_dialog_width,
+            settings.process_dialog_height,
+        );
+    }
 
     close_button.connect_clicked(glib::clone!(@weak popup => move |_| {
         popup.close();
     }));
+    snapshot_button.connect_toggled(|button| {
+        if button.is_active() {
+            button.set_label("Resume live updates");
+        } else {
+            button.set_label("Snapshot");
+        }
+    });
+    popup.connect_delete_event(
+        glib::clone!(@weak settings => @default-return Inhibit(false), move |popup, _| {
+            let (width, height) = popup.size();
+            let mut settings = settings.borrow_mut();
+            settings.process_dialog_width = width;
+            settings.process_dialog_height = height;
+            settings.save();
+            Inhibit(false)
+        }),
+    );
     let to_be_removed = Rc::new(RefCell::new(false));
     popup.connect_destroy(glib::clone!(@weak to_be_removed => move |_| {
         *to_be_removed.borrow_mut() = true;
@@ -408,6 +1212,19 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     popup.set_resizable(true);
     popup.show_all();
 
+    // The tab used last is selected by default. It needs to be done once the pages are visible.
+    let tab = settings.borrow().process_dialog_tab;
+    if tab < notebook.notebook.n_pages() {
+        notebook.notebook.set_current_page(Some(tab));
+    }
+    notebook
+        .notebook
+        .connect_switch_page(glib::clone!(@weak settings => move |_, _, page_num| {
+            let mut settings = settings.borrow_mut();
+            settings.process_dialog_tab = page_num;
+            settings.save();
+        }));
+
     let adjust = scroll.vadjustment();
     adjust.set_value(0.);
     scroll.set_vadjustment(Some(&adjust));
@@ -432,7 +1249,49 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
         memory_peak_label,
         disk_peak: RefCell::new(disk_peak),
         disk_peak_label,
+        snapshot_button,
+        #[cfg(not(windows))]
+        signal_combo,
+        #[cfg(not(windows))]
+        send_signal_button,
+        priority,
+        #[cfg(target_os = "linux")]
+        voluntary_switches,
+        #[cfg(target_os = "linux")]
+        nonvoluntary_switches,
+        #[cfg(target_os = "linux")]
+        context_switches: RefCell::new(None),
+        #[cfg(target_os = "linux")]
+        open_sockets,
+        #[cfg(target_os = "linux")]
+        swap_usage,
+        #[cfg(target_os = "linux")]
+        threads_count,
+        #[cfg(target_os = "linux")]
+        threads_list,
+        #[cfg(target_os = "linux")]
+        files_list,
+        #[cfg(target_os = "linux")]
+        connections_list,
+        #[cfg(target_os = "linux")]
+        connections_page,
         is_dead: false,
         to_be_removed,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_env_value_escapes() {
+        assert_eq!(quote_env_value(""), "\"\"");
+        assert_eq!(quote_env_value("/usr/bin:/bin"), "\"/usr/bin:/bin\"");
+        assert_eq!(quote_env_value("it's a value"), "\"it's a value\"");
+        assert_eq!(quote_env_value("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_env_value("C:\\dir"), "\"C:\\\\dir\"");
+        assert_eq!(quote_env_value("$HOME `id`"), "\"\\$HOME \\`id\\`\"");
+        assert_eq!(quote_env_value("a\nb"), "\"a\\nb\"");
+    }
+}
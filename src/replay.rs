@@ -0,0 +1,282 @@
+//! Replay of process lists exported to CSV files (with the "Export" button of the process list).
+//! The snapshots are displayed instead of the live processes, one at a time.
+
+use gtk::glib;
+use gtk::prelude::{BoxExt, ButtonExt, LabelExt, TreeStoreExt, TreeStoreExtManual, WidgetExt};
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::display_procs::{
+    cpu_severity_color, memory_severity_color, CPU_COLOR_COLUMN, CPU_COLUMN, CPU_HIGHLIGHT_COLUMN,
+    CPU_SORT_COLUMN, DISK_READ_COLUMN, DISK_READ_SORT_COLUMN, DISK_WRITE_COLUMN,
+    DISK_WRITE_SORT_COLUMN, GRAPHICAL_SESSION_COLUMN, GROUP_COLUMN, KERNEL_THREAD_COLUMN,
+    LEADER_COLUMN, LEAK_COLUMN, MEMORY_COLOR_COLUMN, MEMORY_COLUMN, MEMORY_HIGHLIGHT_COLUMN,
+    MEMORY_PERCENT_COLUMN, MEMORY_PERCENT_SORT_COLUMN, MEMORY_SORT_COLUMN, NAME_COLUMN,
+    NAME_SORT_COLUMN, NICE_COLUMN, PARENT_PID_COLUMN, PID_COLUMN, PINNED_COLUMN, RAW_NAME_COLUMN,
+    ROW_BACKGROUND_COLUMN, RUN_TIME_COLUMN, RUN_TIME_SORT_COLUMN, START_TIME_COLUMN,
+    START_TIME_SORT_COLUMN, THREADS_COLUMN, UID_COLUMN, USER_COLUMN,
+};
+use crate::utils::format_number;
+
+/// Titles of the process list columns which can't be filled from the exported processes. They're
+/// hidden while replaying.
+pub const UNAVAILABLE_COLUMNS: &[&str] = &[
+    "mem %",
+    "parent pid",
+    "start time",
+    "running for",
+    "user",
+    "leader",
+    "leak",
+    "nice",
+];
+
+/// A process of a snapshot, with the fields written by `export_to_csv`.
+struct SnapshotProcess {
+    pid: u32,
+    name: String,
+    cpu: f32,
+    // In bytes.
+    memory: u64,
+    disk_read: u64,
+    disk_written: u64,
+}
+
+struct Snapshot {
+    // The file name, displayed to know which snapshot is displayed.
+    name: String,
+    processes: Vec<SnapshotProcess>,
+}
+
+/// Splits a CSV line into its fields. The fields can be quoted, with `""` standing for a quote.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn load_snapshot(path: &Path) -> Result<Snapshot, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let invalid = |line: usize| format!("'{}' line {}: invalid process", path.display(), line + 1);
+    let mut processes = Vec::new();
+    // The first line contains the columns titles.
+    for (line_nb, line) in content.lines().enumerate().skip(1) {
+        if line.is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        if fields.len() != 6 {
+            return Err(invalid(line_nb));
+        }
+        processes.push(SnapshotProcess {
+            pid: fields[0].parse().map_err(|_| invalid(line_nb))?,
+            name: fields[1].clone(),
+            cpu: fields[2].parse().map_err(|_| invalid(line_nb))?,
+            memory: fields[3].parse().map_err(|_| invalid(line_nb))?,
+            disk_read: fields[4].parse().map_err(|_| invalid(line_nb))?,
+            disk_written: fields[5].parse().map_err(|_| invalid(line_nb))?,
+        });
+    }
+    Ok(Snapshot {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        processes,
+    })
+}
+
+/// Replaces the content of the process list with the processes of `snapshot`. The columns
+/// which aren't exported are filled with empty values.
+fn fill_model(list_store: &gtk::TreeStore, snapshot: &Snapshot) {
+    list_store.clear();
+    for process in &snapshot.processes {
+        list_store.insert_with_values(
+            None,
+            None,
+            &[
+                (PID_COLUMN as u32, &process.pid),
+                (NAME_COLUMN as u32, &process.name),
+                (CPU_COLUMN as u32, &format!("{:.1}", process.cpu)),
+                (MEMORY_COLUMN as u32, &format_number(process.memory)),
+                (DISK_READ_COLUMN as u32, &format_number(process.disk_read)),
+                (NAME_SORT_COLUMN as u32, &process.name.to_lowercase()),
+                (CPU_SORT_COLUMN as u32, &process.cpu),
+                (MEMORY_SORT_COLUMN as u32, &process.memory),
+                (DISK_READ_SORT_COLUMN as u32, &process.disk_read),
+                (UID_COLUMN as u32, &0u32),
+                (GRAPHICAL_SESSION_COLUMN as u32, &false),
+                (LEADER_COLUMN as u32, &""),
+                (LEAK_COLUMN as u32, &None::<&str>),
+                (RAW_NAME_COLUMN as u32, &process.name),
+                (NICE_COLUMN as u32, &0i32),
+                (CPU_HIGHLIGHT_COLUMN as u32, &None::<&str>),
+                (MEMORY_HIGHLIGHT_COLUMN as u32, &None::<&str>),
+                (
+                    DISK_WRITE_COLUMN as u32,
+                    &format_number(process.disk_written),
+                ),
+                (DISK_WRITE_SORT_COLUMN as u32, &process.disk_written),
+                (PARENT_PID_COLUMN as u32, &0u32),
+                (USER_COLUMN as u32, &""),
+                (CPU_COLOR_COLUMN as u32, &cpu_severity_color(process.cpu)),
+                (
+                    MEMORY_COLOR_COLUMN as u32,
+                    &memory_severity_color(process.memory),
+                ),
+                (MEMORY_PERCENT_COLUMN as u32, &""),
+                (MEMORY_PERCENT_SORT_COLUMN as u32, &0f32),
+                (START_TIME_COLUMN as u32, &""),
+                (START_TIME_SORT_COLUMN as u32, &0u64),
+                (THREADS_COLUMN as u32, &0u32),
+                (ROW_BACKGROUND_COLUMN as u32, &None::<&str>),
+                (PINNED_COLUMN as u32, &None::<&str>),
+                (RUN_TIME_COLUMN as u32, &""),
+                (RUN_TIME_SORT_COLUMN as u32, &0u64),
+                (GROUP_COLUMN as u32, &false),
+                (KERNEL_THREAD_COLUMN as u32, &false),
+            ],
+        );
+    }
+}
+
+/// Creates the bar to go from a snapshot to another and displays the first one. The snapshots
+/// are displayed in the order of `paths`.
+pub fn create_replay_bar(
+    paths: &[PathBuf],
+    list_store: &gtk::TreeStore,
+) -> Result<gtk::Box, String> {
+    let snapshots = paths
+        .iter()
+        .map(|path| load_snapshot(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    if snapshots.is_empty() {
+        return Err("No snapshot to replay".to_owned());
+    }
+    let snapshots = Rc::new(snapshots);
+    let current = Rc::new(Cell::new(0));
+
+    let layout = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    layout.set_margin_top(5);
+    layout.set_margin_bottom(5);
+    layout.set_margin_start(5);
+    layout.set_margin_end(5);
+    let previous_button = gtk::Button::with_label("Previous");
+    let label = gtk::Label::new(None);
+    let next_button = gtk::Button::with_label("Next");
+    layout.pack_start(&previous_button, false, false, 0);
+    layout.pack_start(&label, true, true, 0);
+    layout.pack_start(&next_button, false, false, 0);
+
+    let display = glib::clone!(@weak list_store, @weak label, @weak previous_button,
+                               @weak next_button, @strong snapshots => move |index: usize| {
+        let snapshot = &snapshots[index];
+        fill_model(&list_store, snapshot);
+        label.set_text(&format!(
+            "Replaying {} ({} / {})",
+            snapshot.name,
+            index + 1,
+            snapshots.len()
+        ));
+        previous_button.set_sensitive(index > 0);
+        next_button.set_sensitive(index + 1 < snapshots.len());
+    });
+    display(0);
+    let display = Rc::new(display);
+    previous_button.connect_clicked(glib::clone!(@strong display, @strong current => move |_| {
+        if current.get() > 0 {
+            current.set(current.get() - 1);
+            display(current.get());
+        }
+    }));
+    next_button.connect_clicked(
+        glib::clone!(@strong display, @strong current, @strong snapshots => move |_| {
+            if current.get() + 1 < snapshots.len() {
+                current.set(current.get() + 1);
+                display(current.get());
+            }
+        }),
+    );
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_csv_line_fields() {
+        assert_eq!(split_csv_line(""), vec![""]);
+        assert_eq!(split_csv_line("1,name,2.5"), vec!["1", "name", "2.5"]);
+        assert_eq!(split_csv_line("1,,3"), vec!["1", "", "3"]);
+        // The commas and the doubled quotes of the quoted fields are kept as is.
+        assert_eq!(
+            split_csv_line("1,\"a, \"\"b\"\"\",3"),
+            vec!["1", "a, \"b\"", "3"]
+        );
+    }
+
+    fn load(name: &str, content: &str) -> Result<Snapshot, String> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, content).expect("failed to write snapshot");
+        let snapshot = load_snapshot(&path);
+        let _ = std::fs::remove_file(&path);
+        snapshot
+    }
+
+    #[test]
+    fn load_snapshot_processes() {
+        let snapshot = load(
+            "process-viewer-replay-valid.csv",
+            "pid,name,cpu usage (%),memory (bytes),disk read (bytes),disk written (bytes)\n\
+             1,\"init\",0.5,1000,20,30\n\
+             \n\
+             42,\"a, b\",12.0,2000,0,0\n",
+        )
+        .expect("failed to load snapshot");
+        assert_eq!(snapshot.name, "process-viewer-replay-valid.csv");
+        assert_eq!(snapshot.processes.len(), 2);
+        let process = &snapshot.processes[0];
+        assert_eq!(process.pid, 1);
+        assert_eq!(process.name, "init");
+        assert_eq!(process.cpu, 0.5);
+        assert_eq!(process.memory, 1000);
+        assert_eq!(process.disk_read, 20);
+        assert_eq!(process.disk_written, 30);
+        assert_eq!(snapshot.processes[1].name, "a, b");
+    }
+
+    #[test]
+    fn load_snapshot_errors() {
+        // Only the titles: there's no process but it's valid.
+        let snapshot = load("process-viewer-replay-empty.csv", "pid,name\n");
+        assert!(snapshot
+            .expect("failed to load snapshot")
+            .processes
+            .is_empty());
+        assert!(load("process-viewer-replay-fields.csv", "titles\n1,init,0.5\n").is_err());
+        assert!(load(
+            "process-viewer-replay-number.csv",
+            "titles\n1,init,high,1000,20,30\n"
+        )
+        .is_err());
+        assert!(load_snapshot(Path::new("/nonexistent/process-viewer.csv")).is_err());
+    }
+}
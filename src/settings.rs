@@ -4,12 +4,13 @@
 // Copyright (c) 2019 Guillaume Gomez
 //
 
-use gtk::{self, glib};
+use gtk::{self, gdk, glib};
 
 use gtk::gio::prelude::ApplicationExt;
 use gtk::prelude::{
-    BoxExt, ContainerExt, DialogExt, GridExt, GtkWindowExt, SpinButtonExt, SpinButtonSignals,
-    WidgetExt,
+    BoxExt, ButtonExt, ColorButtonExt, ColorChooserExt, ContainerExt, DialogExt, EditableSignals,
+    EntryExt, GridExt, GtkWindowExt, Inhibit, ScrolledWindowExt, SpinButtonExt, SpinButtonSignals,
+    StyleContextExt, ToggleButtonExt, TreeModelFilterExt, WidgetExt,
 };
 
 use serde_derive::{Deserialize, Serialize};
@@ -20,21 +21,90 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-use crate::utils::{get_app, get_main_window};
+use sysinfo::{Pid, PidExt};
+
+use crate::graph::{
+    apply_history_len_to_all_graphs, apply_style_to_all_graphs, invalidate_all_graphs, GraphColors,
+    GraphStyle, DEFAULT_HISTORY_LEN,
+};
+use crate::utils::{get_app, get_main_window, set_use_binary_units};
 
 use crate::RequiredForSettings;
 use crate::APPLICATION_NAME;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
 pub struct Settings {
     pub display_fahrenheit: bool,
     pub display_graph: bool,
+    // Graph or numeric view for each section of the "System usage" tab.
+    pub display_cpu_graph: bool,
+    pub display_memory_graph: bool,
+    pub display_temperature_graph: bool,
+    pub display_network_graph: bool,
+    // Display the graph of the processors frequency below their usage.
+    pub display_cpu_frequency_graph: bool,
+    pub display_disk_io_graph: bool,
+    // Stack the processors usage in the graph instead of drawing them over each other.
+    pub stacked_cpu_graph: bool,
     // Timer length in milliseconds (500 minimum!).
     pub refresh_processes_rate: u32,
     // Timer length in milliseconds (500 minimum!).
     pub refresh_system_rate: u32,
     // Timer length in milliseconds (500 minimum!).
     pub refresh_network_rate: u32,
+    // Names or pids of the processes which cannot be killed from the UI.
+    pub protected_processes: Vec<String>,
+    // Titles of the process list columns, in the order they should be displayed.
+    pub process_columns_order: Vec<String>,
+    // Titles of the process list columns hidden by the user.
+    pub hidden_process_columns: Vec<String>,
+    // Display the pinned processes at the top of the list, whatever the sort order.
+    pub pinned_processes_on_top: bool,
+    // Highlight the processes which appeared or ended since the last refresh.
+    pub highlight_process_changes: bool,
+    // Number of consecutive memory increases after which a process is suspected of leaking.
+    pub leak_detection_samples: u32,
+    // Display options applied to all graphs.
+    pub graph_style: GraphStyle,
+    // Display the total CPU usage as the sum of all cores instead of their average.
+    pub total_cpu_usage_summed: bool,
+    // Iconify the main window when the application starts.
+    pub start_minimized: bool,
+    // Total CPU usage (in percent) from which its bar is displayed in yellow.
+    pub cpu_load_medium_threshold: f32,
+    // Total CPU usage (in percent) from which its bar is displayed in red.
+    pub cpu_load_high_threshold: f32,
+    // Sort the processes by name (ascending) when starting.
+    pub sort_by_name_by_default: bool,
+    // Title of the tab selected when the main window was last used. The title is used since the
+    // tabs can be reordered.
+    pub main_window_tab_title: String,
+    // Index of the tab selected when a process dialog was last used.
+    pub process_dialog_tab: u32,
+    // Desktop-wide shortcut raising the main window (for example "CTRL+ALT+P"), disabled if empty.
+    pub global_shortcut: String,
+    // Number of samples displayed by the graphs.
+    pub graph_history_len: usize,
+    // Size of the main window when it was last closed.
+    pub window_width: i32,
+    pub window_height: i32,
+    // Position of the main window when it was last closed, -1 to center it.
+    pub window_x: i32,
+    pub window_y: i32,
+    // Size of the process dialog when it was last closed.
+    pub process_dialog_width: i32,
+    pub process_dialog_height: i32,
+    // Display the sizes with binary units (KiB, MiB...) instead of SI ones (kB, MB...).
+    pub use_binary_units: bool,
+    // Colors of the CPU and memory graphs series.
+    pub graph_colors: GraphColors,
+    // Display the CPU and memory usage in the title of the main window.
+    pub usage_in_window_title: bool,
+    // Hide the kernel threads.
+    pub hide_kernel_threads: bool,
+    // Names of the processes displayed even if they look like kernel threads.
+    pub displayed_kernel_threads: Vec<String>,
 }
 
 impl Default for Settings {
@@ -42,9 +112,56 @@ impl Default for Settings {
         Settings {
             display_fahrenheit: false,
             display_graph: false,
+            display_cpu_graph: false,
+            display_memory_graph: false,
+            display_temperature_graph: false,
+            display_network_graph: false,
+            display_cpu_frequency_graph: false,
+            display_disk_io_graph: false,
+            stacked_cpu_graph: false,
             refresh_processes_rate: 1500,
             refresh_system_rate: 2000,
             refresh_network_rate: 1500,
+            protected_processes: vec!["1".to_owned()],
+            process_columns_order: Vec::new(),
+            // Only the columns which were always there are displayed by default, the others can
+            // be added from the column headers menu.
+            hidden_process_columns: [
+                "parent pid",
+                "start time",
+                "running for",
+                "user",
+                "leader",
+                "leak",
+                "nice",
+            ]
+            .iter()
+            .map(|title| title.to_string())
+            .collect(),
+            pinned_processes_on_top: true,
+            highlight_process_changes: true,
+            leak_detection_samples: 60,
+            graph_style: GraphStyle::default(),
+            total_cpu_usage_summed: false,
+            start_minimized: false,
+            cpu_load_medium_threshold: 50.,
+            cpu_load_high_threshold: 80.,
+            sort_by_name_by_default: false,
+            main_window_tab_title: String::new(),
+            process_dialog_tab: 0,
+            global_shortcut: String::new(),
+            graph_history_len: DEFAULT_HISTORY_LEN,
+            window_width: 630,
+            window_height: 700,
+            window_x: -1,
+            window_y: -1,
+            process_dialog_width: 500,
+            process_dialog_height: 600,
+            use_binary_units: false,
+            graph_colors: GraphColors::default(),
+            usage_in_window_title: true,
+            hide_kernel_threads: true,
+            displayed_kernel_threads: Vec::new(),
         }
     }
 }
@@ -56,7 +173,26 @@ impl Settings {
             File::open(p).map_err(|e| format!("Error while opening '{}': {}", p.display(), e))?;
         file.read_to_string(&mut input)
             .map_err(|e| format!("Error while opening '{}': {}", p.display(), e))?;
-        toml::from_str(&input).map_err(|e| format!("Error while opening '{}': {}", p.display(), e))
+        let mut settings: Settings = toml::from_str(&input)
+            .map_err(|e| format!("Error while opening '{}': {}", p.display(), e))?;
+        // Settings files written before the graph view could be chosen per section only have the
+        // global setting, which is then used for the sections which don't have one.
+        if let Ok(toml::Value::Table(table)) = toml::from_str::<toml::Value>(&input) {
+            for (key, value) in [
+                ("display_cpu_graph", &mut settings.display_cpu_graph),
+                ("display_memory_graph", &mut settings.display_memory_graph),
+                (
+                    "display_temperature_graph",
+                    &mut settings.display_temperature_graph,
+                ),
+                ("display_network_graph", &mut settings.display_network_graph),
+            ] {
+                if !table.contains_key(key) {
+                    *value = settings.display_graph;
+                }
+            }
+        }
+        Ok(settings)
     }
 
     pub fn load() -> Settings {
@@ -74,6 +210,17 @@ impl Settings {
         }
     }
 
+    /// The process viewer itself is always protected.
+    pub fn is_protected(&self, pid: Pid, name: &str) -> bool {
+        if pid.as_u32() == std::process::id() {
+            return true;
+        }
+        let pid = pid.to_string();
+        self.protected_processes
+            .iter()
+            .any(|entry| *entry == pid || entry == name)
+    }
+
     pub fn get_settings_file_path() -> PathBuf {
         let mut path = glib::user_config_dir();
         path.push(APPLICATION_NAME);
@@ -157,6 +304,23 @@ pub fn build_spin(label: &str, grid: &gtk::Grid, top: i32, refresh: u32) -> gtk:
     refresh_entry
 }
 
+fn color_to_rgba((r, g, b): (u8, u8, u8)) -> gdk::RGBA {
+    gdk::RGBA::new(
+        f64::from(r) / 255.,
+        f64::from(g) / 255.,
+        f64::from(b) / 255.,
+        1.,
+    )
+}
+
+fn rgba_to_color(rgba: &gdk::RGBA) -> (u8, u8, u8) {
+    (
+        (rgba.red() * 255.).round() as u8,
+        (rgba.green() * 255.).round() as u8,
+        (rgba.blue() * 255.).round() as u8,
+    )
+}
+
 pub fn show_settings_dialog(
     settings: &Rc<RefCell<Settings>>,
     rfs: &Rc<RefCell<RequiredForSettings>>,
@@ -195,9 +359,156 @@ pub fn show_settings_dialog(
         bsettings.refresh_system_rate,
     );
 
-    // Put the grid into the dialog's content area.
+    let protected_label = gtk::Label::new(Some("Protected processes (names or pids)"));
+    let protected_entry = gtk::Entry::new();
+    protected_label.set_halign(gtk::Align::Start);
+    protected_entry.set_hexpand(true);
+    protected_entry.set_text(&bsettings.protected_processes.join(", "));
+    grid.attach(&protected_label, 0, 3, 1, 1);
+    grid.attach(&protected_entry, 1, 3, 3, 1);
+
+    let leak_label = gtk::Label::new(Some("Memory leak detection (in refreshes)"));
+    let leak_entry = gtk::SpinButton::with_range(5., 3600., 1.);
+    leak_label.set_halign(gtk::Align::Start);
+    leak_entry.set_hexpand(true);
+    leak_entry.set_value(f64::from(bsettings.leak_detection_samples));
+    grid.attach(&leak_label, 0, 4, 1, 1);
+    grid.attach(&leak_entry, 1, 4, 3, 1);
+
+    // Graphs style, applied to all graphs at once.
+    let grid_check_box = gtk::CheckButton::with_label("Display grid on all graphs");
+    grid_check_box.set_active(bsettings.graph_style.display_grid);
+    grid.attach(&grid_check_box, 0, 5, 4, 1);
+    let decimate_check_box =
+        gtk::CheckButton::with_label("Only draw min/max per pixel on long graphs");
+    decimate_check_box.set_active(bsettings.graph_style.decimate);
+    grid.attach(&decimate_check_box, 0, 6, 4, 1);
+    let font_scale_label = gtk::Label::new(Some("Graphs labels font scale"));
+    let font_scale_entry = gtk::SpinButton::with_range(0.5, 3., 0.1);
+    font_scale_label.set_halign(gtk::Align::Start);
+    font_scale_entry.set_hexpand(true);
+    font_scale_entry.set_digits(1);
+    font_scale_entry.set_value(bsettings.graph_style.label_font_scale);
+    grid.attach(&font_scale_label, 0, 7, 1, 1);
+    grid.attach(&font_scale_entry, 1, 7, 3, 1);
+
+    let total_cpu_check_box =
+        gtk::CheckButton::with_label("Display total CPU usage as the sum of all cores");
+    total_cpu_check_box.set_active(bsettings.total_cpu_usage_summed);
+    grid.attach(&total_cpu_check_box, 0, 8, 4, 1);
+
+    let minimized_check_box = gtk::CheckButton::with_label("Start minimized");
+    minimized_check_box.set_active(bsettings.start_minimized);
+    grid.attach(&minimized_check_box, 0, 9, 4, 1);
+
+    let cpu_medium_label = gtk::Label::new(Some("CPU load displayed in yellow from (in %)"));
+    let cpu_medium_entry = gtk::SpinButton::with_range(0., 100., 1.);
+    cpu_medium_label.set_halign(gtk::Align::Start);
+    cpu_medium_entry.set_hexpand(true);
+    cpu_medium_entry.set_value(f64::from(bsettings.cpu_load_medium_threshold));
+    grid.attach(&cpu_medium_label, 0, 10, 1, 1);
+    grid.attach(&cpu_medium_entry, 1, 10, 3, 1);
+
+    let cpu_high_label = gtk::Label::new(Some("CPU load displayed in red from (in %)"));
+    let cpu_high_entry = gtk::SpinButton::with_range(0., 100., 1.);
+    cpu_high_label.set_halign(gtk::Align::Start);
+    cpu_high_entry.set_hexpand(true);
+    cpu_high_entry.set_value(f64::from(bsettings.cpu_load_high_threshold));
+    grid.attach(&cpu_high_label, 0, 11, 1, 1);
+    grid.attach(&cpu_high_entry, 1, 11, 3, 1);
+
+    let sort_by_name_check_box = gtk::CheckButton::with_label("Sort processes by name on startup");
+    sort_by_name_check_box.set_active(bsettings.sort_by_name_by_default);
+    grid.attach(&sort_by_name_check_box, 0, 12, 4, 1);
+
+    let shortcut_label = gtk::Label::new(Some("Global shortcut raising the window"));
+    let shortcut_entry = gtk::Entry::new();
+    shortcut_label.set_halign(gtk::Align::Start);
+    shortcut_entry.set_hexpand(true);
+    shortcut_entry.set_placeholder_text(Some("CTRL+ALT+P"));
+    shortcut_entry.set_text(&bsettings.global_shortcut);
+    grid.attach(&shortcut_label, 0, 13, 1, 1);
+    grid.attach(&shortcut_entry, 1, 13, 3, 1);
+
+    let history_label = gtk::Label::new(Some("Graphs history length (in samples)"));
+    let history_entry = gtk::SpinButton::with_range(10., 3600., 1.);
+    history_label.set_halign(gtk::Align::Start);
+    history_entry.set_hexpand(true);
+    history_entry.set_value(bsettings.graph_history_len as f64);
+    grid.attach(&history_label, 0, 14, 1, 1);
+    grid.attach(&history_entry, 1, 14, 3, 1);
+
+    let binary_units_check_box =
+        gtk::CheckButton::with_label("Display sizes with binary units (KiB, MiB...)");
+    binary_units_check_box.set_active(bsettings.use_binary_units);
+    grid.attach(&binary_units_check_box, 0, 15, 4, 1);
+
+    let colors_label = gtk::Label::new(Some("Graphs colors (CPU, RAM, swap)"));
+    colors_label.set_halign(gtk::Align::Start);
+    let current_colors = rfs.borrow().display_tab.borrow().graph_colors();
+    let colors_layout = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    let color_buttons = [
+        ("CPU", current_colors.cpu),
+        ("RAM", current_colors.ram),
+        ("Swap", current_colors.swap),
+    ]
+    .iter()
+    .map(|(name, color)| {
+        let button = gtk::ColorButton::with_rgba(&color_to_rgba(color.unwrap_or((0, 0, 0))));
+        button.set_tooltip_text(Some(name));
+        colors_layout.pack_start(&button, false, false, 0);
+        button
+    })
+    .collect::<Vec<_>>();
+    let reset_colors_button = gtk::Button::with_label("Default colors");
+    colors_layout.pack_start(&reset_colors_button, false, false, 0);
+    grid.attach(&colors_label, 0, 16, 1, 1);
+    grid.attach(&colors_layout, 1, 16, 3, 1);
+
+    let process_changes_check_box =
+        gtk::CheckButton::with_label("Highlight the processes which started or ended");
+    process_changes_check_box.set_active(bsettings.highlight_process_changes);
+    grid.attach(&process_changes_check_box, 0, 17, 4, 1);
+
+    let title_check_box =
+        gtk::CheckButton::with_label("Display the CPU and memory usage in the window title");
+    title_check_box.set_active(bsettings.usage_in_window_title);
+    grid.attach(&title_check_box, 0, 18, 4, 1);
+
+    let line_width_label = gtk::Label::new(Some("Graphs line width"));
+    let line_width_entry = gtk::SpinButton::with_range(0.5, 5., 0.5);
+    line_width_label.set_halign(gtk::Align::Start);
+    line_width_entry.set_hexpand(true);
+    line_width_entry.set_digits(1);
+    line_width_entry.set_value(bsettings.graph_style.line_width);
+    grid.attach(&line_width_label, 0, 19, 1, 1);
+    grid.attach(&line_width_entry, 1, 19, 3, 1);
+
+    let antialias_check_box = gtk::CheckButton::with_label("Antialias the graphs");
+    antialias_check_box.set_active(bsettings.graph_style.antialias);
+    grid.attach(&antialias_check_box, 0, 20, 4, 1);
+
+    let kernel_threads_check_box = gtk::CheckButton::with_label("Hide kernel threads");
+    kernel_threads_check_box.set_active(bsettings.hide_kernel_threads);
+    grid.attach(&kernel_threads_check_box, 0, 21, 4, 1);
+
+    let kernel_exceptions_label =
+        gtk::Label::new(Some("Processes never hidden as kernel threads (names)"));
+    let kernel_exceptions_entry = gtk::Entry::new();
+    kernel_exceptions_label.set_halign(gtk::Align::Start);
+    kernel_exceptions_entry.set_hexpand(true);
+    kernel_exceptions_entry.set_text(&bsettings.displayed_kernel_threads.join(", "));
+    grid.attach(&kernel_exceptions_label, 0, 22, 1, 1);
+    grid.attach(&kernel_exceptions_entry, 1, 22, 3, 1);
+
+    // Put the grid into the dialog's content area, in a scrolled window so the dialog fits on
+    // small screens.
+    let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    scroll.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+    scroll.set_min_content_height(400);
+    scroll.add(&grid);
     let content_area = dialog.content_area();
-    content_area.pack_start(&grid, true, true, 0);
+    content_area.pack_start(&scroll, true, true, 0);
     content_area.set_border_width(10);
 
     // Finally connect to all kinds of change notification signals for the different UI widgets.
@@ -205,21 +516,212 @@ pub fn show_settings_dialog(
     refresh_procs.connect_value_changed(glib::clone!(@weak settings, @weak rfs => move |entry| {
         let mut settings = settings.borrow_mut();
         settings.refresh_processes_rate = (entry.value() * 1000.) as u32;
-        *rfs.borrow().process_refresh_timeout.lock().expect("failed to lock process_refresh_timeout") = settings.refresh_processes_rate;
+        rfs.borrow().process_refresh_timeout.set(settings.refresh_processes_rate);
         settings.save();
     }));
     refresh_network.connect_value_changed(glib::clone!(@weak settings, @weak rfs => move |entry| {
         let mut settings = settings.borrow_mut();
         settings.refresh_network_rate = (entry.value() * 1000.) as u32;
-        *rfs.borrow().network_refresh_timeout.lock().expect("failed to lock network_refresh_timeout") = settings.refresh_network_rate;
+        rfs.borrow().network_refresh_timeout.set(settings.refresh_network_rate);
         settings.save();
     }));
     refresh_sys.connect_value_changed(glib::clone!(@weak settings, @weak rfs => move |entry| {
         let mut settings = settings.borrow_mut();
         settings.refresh_system_rate = (entry.value() * 1000.) as u32;
-        *rfs.borrow().system_refresh_timeout.lock().expect("failed to lock system_refresh_timeout") = settings.refresh_system_rate;
+        rfs.borrow().system_refresh_timeout.set(settings.refresh_system_rate);
+        settings.save();
+    }));
+
+    leak_entry.connect_value_changed(glib::clone!(@weak settings, @weak rfs => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.leak_detection_samples = entry.value() as u32;
+        rfs.borrow().leak_detector.borrow_mut().set_samples(settings.leak_detection_samples);
+        settings.save();
+    }));
+    grid_check_box.connect_toggled(glib::clone!(@weak settings => move |check_box| {
+        let mut settings = settings.borrow_mut();
+        settings.graph_style.display_grid = check_box.is_active();
+        apply_style_to_all_graphs(&settings.graph_style);
+        settings.save();
+    }));
+    decimate_check_box.connect_toggled(glib::clone!(@weak settings => move |check_box| {
+        let mut settings = settings.borrow_mut();
+        settings.graph_style.decimate = check_box.is_active();
+        apply_style_to_all_graphs(&settings.graph_style);
+        settings.save();
+    }));
+    font_scale_entry.connect_value_changed(glib::clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.graph_style.label_font_scale = entry.value();
+        apply_style_to_all_graphs(&settings.graph_style);
+        settings.save();
+    }));
+    line_width_entry.connect_value_changed(glib::clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.graph_style.line_width = entry.value();
+        apply_style_to_all_graphs(&settings.graph_style);
+        settings.save();
+    }));
+    antialias_check_box.connect_toggled(glib::clone!(@weak settings => move |check_box| {
+        let mut settings = settings.borrow_mut();
+        settings.graph_style.antialias = check_box.is_active();
+        apply_style_to_all_graphs(&settings.graph_style);
+        settings.save();
+    }));
+    history_entry.connect_value_changed(glib::clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.graph_history_len = entry.value() as usize;
+        apply_history_len_to_all_graphs(settings.graph_history_len);
+        settings.save();
+    }));
+    for (index, button) in color_buttons.iter().enumerate() {
+        button.connect_color_set(glib::clone!(@weak settings, @weak rfs => move |button| {
+            let mut settings = settings.borrow_mut();
+            let color = Some(rgba_to_color(&button.rgba()));
+            match index {
+                0 => settings.graph_colors.cpu = color,
+                1 => settings.graph_colors.ram = color,
+                _ => settings.graph_colors.swap = color,
+            }
+            rfs.borrow().display_tab.borrow().apply_graph_colors(&settings.graph_colors);
+            settings.save();
+        }));
+    }
+    reset_colors_button.connect_clicked(glib::clone!(@weak settings, @weak rfs => move |_| {
+        let mut settings = settings.borrow_mut();
+        settings.graph_colors = GraphColors::default();
+        let rfs = rfs.borrow();
+        let display_tab = rfs.display_tab.borrow();
+        display_tab.apply_graph_colors(&settings.graph_colors);
+        let colors = display_tab.graph_colors();
+        for (button, color) in color_buttons.iter().zip([colors.cpu, colors.ram, colors.swap]) {
+            button.set_rgba(&color_to_rgba(color.unwrap_or((0, 0, 0))));
+        }
+        settings.save();
+    }));
+    binary_units_check_box.connect_toggled(
+        glib::clone!(@weak settings, @weak rfs => move |check_box| {
+            let mut settings = settings.borrow_mut();
+            settings.use_binary_units = check_box.is_active();
+            set_use_binary_units(settings.use_binary_units);
+            invalidate_all_graphs();
+            let rfs = rfs.borrow();
+            let sys = rfs.sys.lock().expect("failed to lock to update the sizes");
+            rfs.display_tab.borrow_mut().update_sizes(&sys);
+            settings.save();
+        }),
+    );
+    process_changes_check_box.connect_toggled(
+        glib::clone!(@weak settings, @weak rfs => move |check_box| {
+            let mut settings = settings.borrow_mut();
+            settings.highlight_process_changes = check_box.is_active();
+            rfs.borrow()
+                .process_changes
+                .borrow_mut()
+                .set_enabled(settings.highlight_process_changes);
+            settings.save();
+        }),
+    );
+    total_cpu_check_box.connect_toggled(glib::clone!(@weak settings => move |check_box| {
+        let mut settings = settings.borrow_mut();
+        settings.total_cpu_usage_summed = check_box.is_active();
+        settings.save();
+    }));
+    cpu_medium_entry.connect_value_changed(glib::clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.cpu_load_medium_threshold = entry.value() as f32;
+        settings.save();
+    }));
+    cpu_high_entry.connect_value_changed(glib::clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.cpu_load_high_threshold = entry.value() as f32;
+        settings.save();
+    }));
+    sort_by_name_check_box.connect_toggled(glib::clone!(@weak settings => move |check_box| {
+        let mut settings = settings.borrow_mut();
+        settings.sort_by_name_by_default = check_box.is_active();
+        settings.save();
+    }));
+    title_check_box.connect_toggled(glib::clone!(@weak settings, @weak rfs => move |check_box| {
+        let mut settings = settings.borrow_mut();
+        settings.usage_in_window_title = check_box.is_active();
+        let rfs = rfs.borrow();
+        let sys = rfs.sys.lock().expect("failed to lock to update the window title");
+        crate::update_window_title(&sys, &settings);
+        settings.save();
+    }));
+    minimized_check_box.connect_toggled(glib::clone!(@weak settings => move |check_box| {
+        let mut settings = settings.borrow_mut();
+        settings.start_minimized = check_box.is_active();
+        settings.save();
+    }));
+    protected_entry.connect_changed(glib::clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.protected_processes = entry
+            .text()
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_owned())
+            .collect();
+        settings.save();
+    }));
+
+    kernel_threads_check_box.connect_toggled(
+        glib::clone!(@weak settings, @weak rfs => move |check_box| {
+            {
+                let mut settings = settings.borrow_mut();
+                settings.hide_kernel_threads = check_box.is_active();
+                settings.save();
+            }
+            // The settings are read when filtering, so they mustn't be borrowed anymore.
+            rfs.borrow().filter_model.refilter();
+        }),
+    );
+    kernel_exceptions_entry.connect_changed(
+        glib::clone!(@weak settings, @weak rfs => move |entry| {
+            {
+                let mut settings = settings.borrow_mut();
+                settings.displayed_kernel_threads = entry
+                    .text()
+                    .split(',')
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_owned())
+                    .collect();
+                settings.save();
+            }
+            rfs.borrow().filter_model.refilter();
+        }),
+    );
+
+    // The shortcut is only applied once it's complete: when the entry is activated or loses the
+    // focus.
+    let apply_shortcut = Rc::new(glib::clone!(@weak settings => move |entry: &gtk::Entry| {
+        let shortcut = entry.text().trim().to_owned();
+        let style_context = entry.style_context();
+        if !shortcut.is_empty() && !crate::global_shortcut::is_valid_trigger(&shortcut) {
+            style_context.add_class("error");
+            return;
+        }
+        style_context.remove_class("error");
+        let mut settings = settings.borrow_mut();
+        if settings.global_shortcut == shortcut {
+            return;
+        }
+        if let Some(window) = get_main_window() {
+            crate::global_shortcut::register(&get_app(), &window, &shortcut);
+        }
+        settings.global_shortcut = shortcut;
         settings.save();
     }));
+    shortcut_entry.connect_activate(glib::clone!(@strong apply_shortcut => move |entry| {
+        apply_shortcut(entry);
+    }));
+    shortcut_entry.connect_focus_out_event(move |entry, _| {
+        apply_shortcut(entry);
+        Inhibit(false)
+    });
 
     dialog.connect_response(move |dialog, _| {
         dialog.close();
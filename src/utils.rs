@@ -1,26 +1,40 @@
-use crate::graph::Graph;
+use crate::graph::{register_graph, Graph};
 
+use gtk::gdk;
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::gio::{self, MemoryInputStream};
 use gtk::glib;
 use gtk::glib::{Bytes, Cast};
-use gtk::prelude::{ButtonExt, GtkApplicationExt, Inhibit, WidgetExt};
+use gtk::prelude::{
+    ButtonExt, CheckMenuItemExt, DialogExt, FileChooserExt, GtkApplicationExt, GtkMenuExt,
+    GtkMenuItemExt, GtkWindowExt, Inhibit, MenuShellExt, WidgetExt, WidgetExtManual,
+};
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::ops::Index;
 use std::rc::Rc;
+use std::time::Instant;
 
 pub const MAIN_WINDOW_NAME: &str = "main-window";
+// Headrooms (in percent of the highest value) which can be chosen from the context menu of the
+// graphs.
+const GRAPH_HEADROOMS: &[u32] = &[0, 10, 20, 50, 100, 200, 300];
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RotateVec<T> {
     data: Vec<T>,
+    // When each sample was added (`None` for the initial values).
+    timestamps: Vec<Option<Instant>>,
     start: usize,
 }
 
 impl<T> RotateVec<T> {
     pub fn new(d: Vec<T>) -> RotateVec<T> {
-        RotateVec { data: d, start: 0 }
+        RotateVec {
+            timestamps: vec![None; d.len()],
+            data: d,
+            start: 0,
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -31,12 +45,51 @@ impl<T> RotateVec<T> {
         self.data.is_empty()
     }
 
+    /// Makes room for a new sample at index 0, which is timestamped with the current time.
     pub fn move_start(&mut self) {
         if self.start > 0 {
             self.start -= 1;
         } else {
             self.start = self.data.len() - 1;
         }
+        let pos = self.get_real_pos(0);
+        if let Some(timestamp) = self.timestamps.get_mut(pos) {
+            *timestamp = Some(Instant::now());
+        }
+    }
+
+    /// Changes the number of samples, dropping the oldest ones or adding `value` as the oldest
+    /// ones.
+    pub fn resize(&mut self, len: usize, value: T)
+    where
+        T: Clone,
+    {
+        let kept = self.data.len().min(len);
+        let mut data = Vec::with_capacity(len);
+        let mut timestamps = Vec::with_capacity(len);
+        for index in 0..kept {
+            let pos = self.get_real_pos(index);
+            data.push(self.data[pos].clone());
+            timestamps.push(self.timestamps[pos]);
+        }
+        data.resize(len, value);
+        timestamps.resize(len, None);
+        self.data = data;
+        self.timestamps = timestamps;
+        self.start = 0;
+    }
+
+    /// Returns when the sample at `index` was added.
+    pub fn timestamp(&self, index: usize) -> Option<Instant> {
+        self.timestamps
+            .get(self.get_real_pos(index))
+            .copied()
+            .flatten()
+    }
+
+    /// Iterates over all the samples, in no particular order.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.data.iter_mut()
     }
 
     /*pub fn get(&self, index: usize) -> Option<&T> {
@@ -57,44 +110,117 @@ impl<T> RotateVec<T> {
     }
 }
 
+thread_local! {
+    // Display the sizes with binary units (KiB, MiB...) instead of SI ones (kB, MB...).
+    static USE_BINARY_UNITS: Cell<bool> = Cell::new(false);
+}
+
+pub fn set_use_binary_units(use_binary_units: bool) {
+    USE_BINARY_UNITS.with(|v| v.set(use_binary_units));
+}
+
+pub fn use_binary_units() -> bool {
+    USE_BINARY_UNITS.with(|v| v.get())
+}
+
+/// `sysinfo` (and `/proc`) give the memory sizes in KiB, even though they call them "kB".
+pub fn kib_to_bytes(kib: u64) -> u64 {
+    kib * 1_024
+}
+
 pub fn format_number(nb: u64) -> String {
     format_number_full(nb, true)
 }
 
 pub fn format_number_full(nb: u64, use_unit: bool) -> String {
-    if nb < 1_000 {
-        format!("{}{}", nb, if use_unit { " B" } else { "" })
-    } else if nb < 1_000_000 {
-        format!(
-            "{}.{}{}",
-            nb / 1_000,
-            nb / 100 % 10,
-            if use_unit { " KB" } else { "" }
-        )
-    } else if nb < 1_000_000_000 {
-        format!(
-            "{}.{}{}",
-            nb / 1_000_000,
-            nb / 100_000 % 10,
-            if use_unit { " MB" } else { "" }
-        )
-    } else if nb < 1_000_000_000_000 {
-        format!(
-            "{}.{}{}",
-            nb / 1_000_000_000,
-            nb / 100_000_000 % 10,
-            if use_unit { " GB" } else { "" }
-        )
+    if !use_unit {
+        format_number_with_base(nb, 1_000, [""; 5])
+    } else if use_binary_units() {
+        format_number_with_base(nb, 1_024, [" B", " KiB", " MiB", " GiB", " TiB"])
+    } else {
+        format_number_with_base(nb, 1_000, [" B", " kB", " MB", " GB", " TB"])
+    }
+}
+
+/// Formats a number of bytes as bits, which is how network speeds are usually displayed. It always
+/// uses SI units.
+pub fn format_number_bits(nb: u64) -> String {
+    format_number_with_base(
+        nb.saturating_mul(8),
+        1_000,
+        [" b", " Kb", " Mb", " Gb", " Tb"],
+    )
+}
+
+fn format_number_with_base(nb: u64, base: u64, units: [&str; 5]) -> String {
+    let mut divisor = 1;
+    let mut unit = 0;
+    while unit + 1 < units.len() && nb / divisor >= base {
+        divisor *= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", nb, units[0])
     } else {
         format!(
             "{}.{}{}",
-            nb / 1_000_000_000_000,
-            nb / 100_000_000_000 % 10,
-            if use_unit { " TB" } else { "" }
+            nb / divisor,
+            nb % divisor * 10 / divisor,
+            units[unit]
         )
     }
 }
 
+/// Formats a unix timestamp as a local date and time, for example "2022-03-14 08:05:12".
+pub fn format_timestamp(timestamp: u64) -> String {
+    glib::DateTime::from_unix_local(timestamp as i64)
+        .and_then(|date| date.format("%Y-%m-%d %H:%M:%S"))
+        .map(|date| date.to_string())
+        .unwrap_or_default()
+}
+
+/// Formats a duration in seconds, for example "1d 3h 5m 12s".
+pub fn format_time(t: u64) -> String {
+    format!(
+        "{}{}{}{}s",
+        {
+            let days = t / 86_400;
+            if days > 0 {
+                format!("{}d ", days)
+            } else {
+                "".to_owned()
+            }
+        },
+        {
+            let hours = t / 3_600 % 24;
+            if hours > 0 {
+                format!("{}h ", hours)
+            } else {
+                "".to_owned()
+            }
+        },
+        {
+            let minutes = t / 60 % 60;
+            if minutes > 0 {
+                format!("{}m ", minutes)
+            } else {
+                "".to_owned()
+            }
+        },
+        t % 60
+    )
+}
+
+/// Labels of the graphs displaying percentages, `v` being the top of the graph.
+pub fn graph_label_percent(v: f64) -> [String; 4] {
+    [
+        format!("{:.0}", v),
+        format!("{:.0}", v / 2.),
+        "0".to_owned(),
+        "%".to_owned(),
+    ]
+}
+
 pub fn graph_label_units(v: f64) -> [String; 4] {
     graph_label_units_full(v, true)
 }
@@ -104,40 +230,32 @@ pub fn graph_label(v: f64) -> [String; 4] {
 }
 
 pub fn graph_label_units_full(v: f64, use_unit: bool) -> [String; 4] {
-    if v < 1_000. {
+    let (base, units) = if !use_unit {
+        (1_000., ["", "K", "M", "G", "T"])
+    } else if use_binary_units() {
+        (1_024., ["B", "KiB", "MiB", "GiB", "TiB"])
+    } else {
+        (1_000., ["B", "kB", "MB", "GB", "TB"])
+    };
+    let mut divisor = 1.;
+    let mut unit = 0;
+    while unit + 1 < units.len() && v / divisor >= base {
+        divisor *= base;
+        unit += 1;
+    }
+    if unit == 0 {
         [
             v.to_string(),
             format!("{}", v / 2.),
             "0".to_owned(),
-            if use_unit { "B" } else { "" }.to_owned(),
-        ]
-    } else if v < 1_000_000. {
-        [
-            format!("{:.1}", v / 1_000f64),
-            format!("{:.1}", v / 2_000f64),
-            "0".to_owned(),
-            if use_unit { "KB" } else { "K" }.to_owned(),
-        ]
-    } else if v < 1_000_000_000. {
-        [
-            format!("{:.1}", v / 1_000_000f64),
-            format!("{:.1}", v / 2_000_000f64),
-            "0".to_owned(),
-            if use_unit { "MB" } else { "M" }.to_owned(),
-        ]
-    } else if v < 1_000_000_000_000. {
-        [
-            format!("{:.1}", v / 1_000_000_000f64),
-            format!("{:.1}", v / 2_000_000_000f64),
-            "0".to_owned(),
-            if use_unit { "GB" } else { "G" }.to_owned(),
+            units[0].to_owned(),
         ]
     } else {
         [
-            format!("{:.1}", v / 1_000_000_000_000f64),
-            format!("{:.1}", v / 2_000_000_000_000f64),
+            format!("{:.1}", v / divisor),
+            format!("{:.1}", v / divisor / 2.),
             "0".to_owned(),
-            if use_unit { "TB" } else { "T" }.to_owned(),
+            units[unit].to_owned(),
         ]
     }
 }
@@ -145,6 +263,7 @@ pub fn graph_label_units_full(v: f64, use_unit: bool) -> [String; 4] {
 pub fn connect_graph(graph: Graph) -> Rc<RefCell<Graph>> {
     let area = graph.area.clone();
     let graph = Rc::new(RefCell::new(graph));
+    register_graph(&graph);
     area.connect_draw(
         glib::clone!(@weak graph => @default-return Inhibit(false), move |w, c| {
             graph.borrow()
@@ -154,9 +273,134 @@ pub fn connect_graph(graph: Graph) -> Rc<RefCell<Graph>> {
             Inhibit(false)
         }),
     );
+    // The tooltip displays the values under the cursor.
+    area.add_events(
+        gdk::EventMask::POINTER_MOTION_MASK
+            | gdk::EventMask::LEAVE_NOTIFY_MASK
+            | gdk::EventMask::BUTTON_PRESS_MASK,
+    );
+    area.connect_motion_notify_event(
+        glib::clone!(@weak graph => @default-return Inhibit(false), move |w, event| {
+            let text = graph
+                .borrow()
+                .tooltip_text(event.position().0, f64::from(w.allocated_width()));
+            w.set_tooltip_text(text.as_deref());
+            Inhibit(false)
+        }),
+    );
+    area.connect_leave_notify_event(|w, _| {
+        w.set_tooltip_text(None);
+        Inhibit(false)
+    });
+    // Right-clicking the graph allows to save it as an image and to change its scale.
+    let context_menu = gtk::Menu::new();
+    let save_item = gtk::MenuItem::with_label("Save as image");
+    save_item.connect_activate(glib::clone!(@weak graph => move |_| {
+        save_graph_as_image(&graph);
+    }));
+    context_menu.append(&save_item);
+    let log_scale_item = gtk::CheckMenuItem::with_label("Logarithmic scale");
+    log_scale_item.connect_toggled(glib::clone!(@weak graph => move |item| {
+        graph.borrow_mut().set_log_scale(item.is_active());
+    }));
+    context_menu.append(&log_scale_item);
+    // The space left above the highest value.
+    let headroom_menu = gtk::Menu::new();
+    let mut headroom_items: Vec<(gtk::RadioMenuItem, u32)> = Vec::new();
+    for headroom in GRAPH_HEADROOMS {
+        let label = if *headroom == 0 {
+            "None".to_owned()
+        } else {
+            format!("{}%", headroom)
+        };
+        let item = match headroom_items.first() {
+            Some((first, _)) => gtk::RadioMenuItem::with_label_from_widget(first, Some(&label)),
+            None => gtk::RadioMenuItem::builder().label(&label).build(),
+        };
+        let overhead = if *headroom == 0 {
+            None
+        } else {
+            Some(f64::from(*headroom))
+        };
+        item.connect_toggled(glib::clone!(@weak graph => move |item| {
+            if item.is_active() {
+                graph.borrow_mut().set_overhead(overhead);
+            }
+        }));
+        headroom_menu.append(&item);
+        headroom_items.push((item, *headroom));
+    }
+    let headroom_item = gtk::MenuItem::with_label("Headroom");
+    headroom_item.set_submenu(Some(&headroom_menu));
+    context_menu.append(&headroom_item);
+    context_menu.show_all();
+    area.connect_button_press_event(
+        glib::clone!(@strong context_menu, @weak graph, @weak log_scale_item,
+                     @weak headroom_item => @default-return Inhibit(false), move |_, event| {
+            if event.event_type() != gdk::EventType::ButtonPress || event.button() != 3 {
+                return Inhibit(false);
+            }
+            let (log_scale, overhead, minimum) = {
+                let graph = graph.borrow();
+                (graph.log_scale(), graph.overhead(), graph.minimum())
+            };
+            log_scale_item.set_active(log_scale);
+            // The headroom isn't used by the graphs with a minimum.
+            headroom_item.set_sensitive(minimum.is_none());
+            let overhead = overhead.unwrap_or(0.);
+            if let Some((item, _)) = headroom_items
+                .iter()
+                .find(|(_, headroom)| (f64::from(*headroom) - overhead).abs() < f64::EPSILON)
+            {
+                item.set_active(true);
+            }
+            context_menu.popup_at_pointer(Some(&**event));
+            Inhibit(true)
+        }),
+    );
     graph
 }
 
+/// Asks where to save the graph and writes it as a PNG image.
+fn save_graph_as_image(graph: &Rc<RefCell<Graph>>) {
+    let window = graph
+        .borrow()
+        .area
+        .toplevel()
+        .and_then(|w| w.downcast::<gtk::Window>().ok());
+    let dialog = gtk::FileChooserDialog::with_buttons(
+        Some("Save the graph"),
+        window.as_ref(),
+        gtk::FileChooserAction::Save,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Save", gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_current_name("graph.png");
+    dialog.set_do_overwrite_confirmation(true);
+    dialog.connect_response(glib::clone!(@weak graph => move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            if let Some(path) = dialog.filename() {
+                if let Err(e) = graph.borrow().save_to_png(&path) {
+                    let m = gtk::MessageDialog::new(
+                        window.as_ref(),
+                        gtk::DialogFlags::DESTROY_WITH_PARENT,
+                        gtk::MessageType::Error,
+                        gtk::ButtonsType::Ok,
+                        &e,
+                    );
+                    m.set_modal(true);
+                    m.connect_response(|m, _| m.close());
+                    m.show_all();
+                }
+            }
+        }
+        dialog.close();
+    }));
+    dialog.show_all();
+}
+
 impl<T> Index<usize> for RotateVec<T> {
     type Output = T;
 
@@ -195,3 +439,52 @@ pub fn create_button_with_image(image_bytes: &'static [u8], fallback_text: &str)
     }
     button
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_number_bits_thresholds() {
+        assert_eq!(format_number_bits(0), "0 b");
+        assert_eq!(format_number_bits(124), "992 b");
+        assert_eq!(format_number_bits(125), "1.0 Kb");
+        assert_eq!(format_number_bits(124_999), "999.9 Kb");
+        assert_eq!(format_number_bits(125_000), "1.0 Mb");
+        assert_eq!(format_number_bits(125_000_000), "1.0 Gb");
+        assert_eq!(format_number_bits(125_000_000_000), "1.0 Tb");
+        // The number of bits doesn't overflow.
+        assert_eq!(format_number_bits(u64::MAX), "18446744.0 Tb");
+    }
+
+    #[test]
+    fn format_number_bits_ignores_binary_units() {
+        set_use_binary_units(true);
+        assert_eq!(format_number_bits(128), "1.0 Kb");
+        set_use_binary_units(false);
+    }
+
+    #[test]
+    fn format_number_decimal_thresholds() {
+        set_use_binary_units(false);
+        assert_eq!(format_number(999), "999 B");
+        assert_eq!(format_number(1_000), "1.0 kB");
+        assert_eq!(format_number(1_500), "1.5 kB");
+        assert_eq!(format_number(999_999), "999.9 kB");
+        assert_eq!(format_number(1_000_000), "1.0 MB");
+        assert_eq!(format_number(1_000_000_000), "1.0 GB");
+    }
+
+    #[test]
+    fn format_number_binary_thresholds() {
+        set_use_binary_units(true);
+        assert_eq!(format_number(1_023), "1023 B");
+        assert_eq!(format_number(1_024), "1.0 KiB");
+        assert_eq!(format_number(1_048_575), "1023.9 KiB");
+        assert_eq!(format_number(1_048_576), "1.0 MiB");
+        assert_eq!(format_number(1 << 30), "1.0 GiB");
+        assert_eq!(format_number(kib_to_bytes(16 * 1_024 * 1_024)), "16.0 GiB");
+        set_use_binary_units(false);
+        assert_eq!(format_number(1_024), "1.0 kB");
+    }
+}